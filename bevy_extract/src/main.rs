@@ -1,18 +1,85 @@
-use bevy::prelude::*;
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
 use rand::Rng;
+use serde::Serialize;
+use std::{env, fs::File, io::BufWriter, path::PathBuf};
 
 const CUBE_COUNT: usize = 1000;
 
+// File the cubes are exported to when no path is given with `--export`
+const DEFAULT_EXPORT_PATH: &str = "cubes.json";
+
+const USAGE: &str = "usage: bevy_extract [--headless] [--export <path>]";
+
 // Component to mark entities that should be cleaned up
 #[derive(Component)]
 struct Temporary;
 
+// Path the spawned cubes are exported to
+#[derive(Resource)]
+struct ExportPath(PathBuf);
+
+// A spawned cube as exported for raymarks, which rasterizes the file with `--cubes <path>`
+#[derive(Serialize)]
+struct ExportedCube {
+    position: [f32; 3],
+    size: [f32; 3],
+    color: [f32; 3],
+}
+
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_systems(Startup, setup)
-        .add_systems(Update, (spawn_random_cubes, cleanup_cubes))
-        .run();
+    // Parse `--headless` and `--export <path>`
+    let mut headless = false;
+    let mut export_path = PathBuf::from(DEFAULT_EXPORT_PATH);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => headless = true,
+            "--export" => match args.next() {
+                Some(path) => export_path = PathBuf::from(path),
+                None => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let mut app = App::new();
+    if headless {
+        // Extracting the scene only needs its entities and assets, so no window or GPU is
+        // required, and it can run in CI
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>();
+    } else {
+        app.add_plugins(DefaultPlugins);
+    }
+    // Spawn the cubes once, so they persist until they are extracted or replaced
+    app.insert_resource(ExportPath(export_path))
+        .add_systems(Startup, (setup, spawn_random_cubes))
+        .add_systems(PostUpdate, export_cubes);
+    if !headless {
+        // Replace the cubes with a new random set when space is pressed
+        app.add_systems(
+            Update,
+            (cleanup_cubes, spawn_random_cubes)
+                .chain()
+                .run_if(input_just_pressed(KeyCode::Space)),
+        );
+    }
+
+    if headless {
+        // Run exactly one update, which spawns and exports the cubes, instead of looping
+        app.finish();
+        app.cleanup();
+        app.update();
+    } else {
+        app.run();
+    }
 }
 
 fn setup(mut commands: Commands) {
@@ -40,7 +107,7 @@ fn spawn_random_cubes(
 ) {
     let mut rng = rand::rng();
 
-    // Spawn 5 random cubes
+    // Spawn random cubes
     for _ in 0..CUBE_COUNT {
         let size = Vec3::new(
             rng.random_range(0.5..2.0), // x
@@ -78,3 +145,42 @@ fn cleanup_cubes(mut commands: Commands, query: Query<Entity, With<Temporary>>)
         commands.entity(entity).despawn();
     }
 }
+
+fn export_cubes(
+    query: Query<(&Transform, &Mesh3d, &MeshMaterial3d<StandardMaterial>), With<Temporary>>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    export_path: Res<ExportPath>,
+    mut exported: Local<bool>,
+) {
+    if *exported || query.is_empty() {
+        return;
+    }
+    *exported = true;
+
+    // Sizes come from the bounds of each cube's mesh, and colors from its material
+    let cubes: Vec<ExportedCube> = query
+        .iter()
+        .filter_map(|(transform, mesh, material)| {
+            let aabb = meshes.get(&mesh.0)?.compute_aabb()?;
+            let color = materials.get(&material.0)?.base_color.to_srgba();
+            Some(ExportedCube {
+                position: transform.translation.to_array(),
+                size: (Vec3::from(aabb.half_extents) * 2.0 * transform.scale).to_array(),
+                color: [color.red, color.green, color.blue],
+            })
+        })
+        .collect();
+
+    let result = File::create(&export_path.0)
+        .map_err(serde_json::Error::io)
+        .and_then(|file| serde_json::to_writer(BufWriter::new(file), &cubes));
+    match result {
+        Ok(()) => info!(
+            "Exported {} cubes to {}.",
+            cubes.len(),
+            export_path.0.display()
+        ),
+        Err(error) => error!("Failed to export cubes: {}", error),
+    }
+}
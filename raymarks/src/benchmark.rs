@@ -0,0 +1,785 @@
+use crate::{
+    context::{BenchmarkContext, SaveError},
+    report::{Bottleneck, Bound},
+    resolution::Resolution,
+};
+use log::{debug, error, info, warn};
+use serde::{Serialize, Serializer};
+use std::{
+    cmp::Reverse,
+    error::Error,
+    fmt,
+    path::PathBuf,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// Options controlling how many times each benchmark pass is run, and how their timings are
+/// summarized.
+#[derive(Clone, Debug)]
+pub struct BenchmarkOptions {
+    /// Number of unmeasured passes run first, to let the driver warm up.
+    pub warmup: usize,
+    /// Number of measured passes.
+    pub samples: usize,
+    /// Number of passes recorded per submission. See `BenchmarkContext::set_batch_size`.
+    pub batch_size: u32,
+    /// Fixed interval between the starts of consecutive passes, sleeping in between, so the GPU
+    /// reaches a thermal steady state instead of heating up in a tight loop. `None` runs passes
+    /// back to back.
+    pub pace: Option<Duration>,
+    /// Duration to keep measuring passes for, instead of a fixed number of samples, to report
+    /// how timings drift as the GPU heats up and throttles. `None` takes the configured number
+    /// of samples.
+    pub soak: Option<Duration>,
+    /// Percentiles of the GPU durations to report, such as 95 and 99. Percentiles are skipped
+    /// when there are too few samples to tell them apart from the slowest sample.
+    pub percentiles: Vec<f64>,
+    /// Number of slowest GPU samples whose indices are reported, to find stutters.
+    pub slowest_samples: usize,
+    /// Largest coefficient of variation, the standard deviation over the mean, of the GPU
+    /// durations before a measurement is flagged as noisy, such as from background load or
+    /// thermal throttling. `None` never flags measurements.
+    pub max_variation: Option<f64>,
+    /// Whether to measure a noisy configuration once more, keeping the second measurement.
+    pub rerun_noisy: bool,
+    /// Longest duration each benchmark of a suite may run for before a watchdog marks it failed
+    /// and aborts its measurements, such as when a driver bug hangs it. `None` lets benchmarks
+    /// run for as long as they take.
+    pub timeout: Option<Duration>,
+    /// Path the report of the suite is written to as JSON whenever a benchmark times out, so the
+    /// results completed before are kept even if the benchmark never returns.
+    pub partial_results: Option<PathBuf>,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self {
+            warmup: 3,
+            samples: 10,
+            batch_size: 1,
+            pace: None,
+            soak: None,
+            percentiles: vec![95.0, 99.0],
+            slowest_samples: 5,
+            max_variation: None,
+            rerun_noisy: false,
+            timeout: None,
+            partial_results: None,
+        }
+    }
+}
+
+/// Summary statistics of the GPU durations of a benchmark's measured passes. Durations are
+/// serialized in milliseconds.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Statistics {
+    pub samples: usize,
+    #[serde(rename = "min_ms", serialize_with = "serialize_milliseconds")]
+    pub min: Duration,
+    #[serde(rename = "mean_ms", serialize_with = "serialize_milliseconds")]
+    pub mean: Duration,
+    #[serde(rename = "median_ms", serialize_with = "serialize_milliseconds")]
+    pub median: Duration,
+    #[serde(rename = "std_dev_ms", serialize_with = "serialize_milliseconds")]
+    pub std_dev: Duration,
+}
+
+/// Serialize a duration as a floating-point number of milliseconds.
+fn serialize_milliseconds<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+impl Statistics {
+    /// Compute statistics over the given sample durations, or `None` if there are no samples.
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2
+        } else {
+            sorted[middle]
+        };
+
+        let mean = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+        let variance = sorted
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean.as_secs_f64()).powi(2))
+            .sum::<f64>()
+            / sorted.len() as f64;
+
+        Some(Self {
+            samples: sorted.len(),
+            min: sorted[0],
+            mean,
+            median,
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+        })
+    }
+
+    /// Get the standard deviation over the mean, which compares the spread of durations of
+    /// different lengths. Zero if the mean is zero.
+    pub fn coefficient_of_variation(&self) -> f64 {
+        if self.mean.is_zero() {
+            return 0.0;
+        }
+        self.std_dev.as_secs_f64() / self.mean.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "min {:?}, mean {:?}, median {:?}, std dev {:?} over {} samples",
+            self.min, self.mean, self.median, self.std_dev, self.samples
+        )
+    }
+}
+
+/// Change in the GPU timing of passes over a soak, comparing its first and last samples.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Drift {
+    /// GPU timing of the first tenth of the samples.
+    pub start: Statistics,
+    /// GPU timing of the last tenth of the samples.
+    pub end: Statistics,
+    /// Mean duration at the end over the mean duration at the start, so values above 1 mean
+    /// passes slowed down, such as from thermal throttling.
+    pub ratio: f64,
+}
+
+impl Drift {
+    /// Compare the first and last tenths of the given sample durations, in the order they were
+    /// taken, or `None` if there are fewer than 2 samples, or the first tenth took no time, so
+    /// there is no ratio.
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.len() < 2 {
+            return None;
+        }
+        let window = (samples.len() / 10).max(1);
+        let start = Statistics::from_samples(&samples[..window])?;
+        let end = Statistics::from_samples(&samples[samples.len() - window..])?;
+        if start.mean.is_zero() {
+            return None;
+        }
+        Some(Self {
+            start,
+            end,
+            ratio: end.mean.as_secs_f64() / start.mean.as_secs_f64(),
+        })
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mean {:?} at the start, {:?} at the end ({:.2}x)",
+            self.start.mean, self.end.mean, self.ratio
+        )
+    }
+}
+
+/// Tail of the GPU durations of a benchmark's measured passes, which averages hide.
+#[derive(Clone, Debug, Serialize)]
+pub struct TailLatency {
+    /// Requested percentiles of the durations, in increasing order, leaving out those with too
+    /// few samples.
+    pub percentiles: Vec<Percentile>,
+    /// Indices of the slowest samples, in the order they were taken, slowest first.
+    pub slowest_samples: Vec<usize>,
+}
+
+/// Duration which the given percentage of samples took at most.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Percentile {
+    pub percentile: f64,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_milliseconds")]
+    pub duration: Duration,
+}
+
+impl TailLatency {
+    /// Compute the given percentiles, between 0 and 100, of the given sample durations in the
+    /// order they were taken, along with the indices of the given number of slowest samples.
+    /// Percentiles are computed by nearest rank, and left out unless at least one sample lies
+    /// above them, so 3 samples report no 99th percentile. Returns `None` if there are no
+    /// samples.
+    pub fn from_samples(
+        samples: &[Duration],
+        percentiles: &[f64],
+        slowest_count: usize,
+    ) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let mut percentiles: Vec<f64> = percentiles
+            .iter()
+            .copied()
+            .filter(|percentile| (0.0..100.0).contains(percentile))
+            .filter(|percentile| sorted.len() as f64 >= (100.0 / (100.0 - percentile)).ceil())
+            .collect();
+        percentiles.sort_by(f64::total_cmp);
+        percentiles.dedup();
+        let percentiles = percentiles
+            .into_iter()
+            .map(|percentile| {
+                let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+                Percentile {
+                    percentile,
+                    duration: sorted[rank.max(1) - 1],
+                }
+            })
+            .collect();
+
+        // Sort stably, so equally slow samples are listed in the order they were taken
+        let mut slowest_samples: Vec<usize> = (0..samples.len()).collect();
+        slowest_samples.sort_by_key(|&index| Reverse(samples[index]));
+        slowest_samples.truncate(slowest_count);
+
+        Some(Self {
+            percentiles,
+            slowest_samples,
+        })
+    }
+}
+
+impl fmt::Display for TailLatency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for percentile in &self.percentiles {
+            write!(f, "p{} {:?}, ", percentile.percentile, percentile.duration)?;
+        }
+        write!(f, "slowest samples {:?}", self.slowest_samples)
+    }
+}
+
+/// GPU and CPU timing of a benchmark's measured passes.
+#[derive(Clone, Debug)]
+pub struct Measurement {
+    /// GPU durations of the passes, or `None` if the adapter does not support timestamp queries.
+    pub gpu: Option<Statistics>,
+    /// CPU durations of recording the passes' commands, or `None` if no passes were measured.
+    pub encode: Option<Statistics>,
+    /// CPU durations of finishing each submission's command buffer, or `None` if no passes were
+    /// measured.
+    pub finish: Option<Statistics>,
+    /// CPU durations of the calls submitting each command buffer to the queue, or `None` if no
+    /// passes were measured.
+    pub submit: Option<Statistics>,
+    /// Change in GPU durations over a soak, or `None` if the passes were not soaked or timed.
+    pub drift: Option<Drift>,
+    /// Percentiles and slowest samples of the GPU durations, or `None` if the passes were not
+    /// timed.
+    pub tail: Option<TailLatency>,
+    /// Whether the GPU durations varied more than the configured maximum, so they may not be
+    /// trustworthy.
+    pub noisy: bool,
+}
+
+impl Measurement {
+    /// Classify the measured passes as CPU- or GPU-bound, given the number of passes per
+    /// submission they were measured with. The CPU time per pass is the mean time to record it,
+    /// plus its share of the mean time to finish and submit a batch. Returns `None` if the passes
+    /// were not timed on both the CPU and the GPU.
+    pub fn bottleneck(&self, batch_size: u32) -> Option<Bottleneck> {
+        let (Some(gpu), Some(encode), Some(finish), Some(submit)) =
+            (self.gpu, self.encode, self.finish, self.submit)
+        else {
+            return None;
+        };
+        let cpu = encode.mean.as_secs_f64()
+            + (finish.mean + submit.mean).as_secs_f64() / batch_size.max(1) as f64;
+        let cpu_to_gpu_ratio = cpu / gpu.mean.as_secs_f64();
+        Some(Bottleneck {
+            bound: if cpu_to_gpu_ratio >= 1.0 {
+                Bound::Cpu
+            } else {
+                Bound::Gpu
+            },
+            cpu_to_gpu_ratio,
+        })
+    }
+}
+
+/// Error which aborts the measurement of a pass, whose error type is `E`.
+#[derive(Debug)]
+pub enum BenchmarkError<E> {
+    /// The pass failed to record, such as when its pipeline could not be built.
+    Pass(E),
+    /// The device reported a validation error while passes were recorded or submitted, so their
+    /// output and timings cannot be trusted.
+    Validation(wgpu::Error),
+    /// The device was lost, with the reason given, so no more passes can run on it.
+    DeviceLost(String),
+    /// The context's abort flag was set, such as by a watchdog once the benchmark ran for too
+    /// long, or the GPU did not finish a pass within `BenchmarkContext::POLL_TIMEOUT`.
+    Aborted,
+}
+
+impl<E: fmt::Display> fmt::Display for BenchmarkError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pass(error) => write!(f, "pass failed: {}", error),
+            Self::Validation(error) => write!(f, "validation error: {}", error),
+            Self::DeviceLost(reason) => write!(f, "device lost: {}", reason),
+            Self::Aborted => write!(f, "aborted"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for BenchmarkError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Pass(error) => Some(error),
+            Self::Validation(error) => Some(error),
+            Self::DeviceLost(_) | Self::Aborted => None,
+        }
+    }
+}
+
+/// Run a pass for the configured number of warmup iterations, then measure its GPU duration for
+/// the configured number of samples. Passes are submitted in batches of the configured size, and
+/// one GPU sample is taken per submission, timing the last pass of the batch. The CPU time taken
+/// to record each measured pass, and to finish and submit each batch, is sampled too. Passes are
+/// paced and soaked as configured, and the configured percentiles and slowest samples of the GPU
+/// durations are reported. If the GPU durations vary more than the configured maximum, the
+/// measurement is flagged as noisy with a warning, and taken once more if configured. Each batch
+/// is recorded and submitted within an error scope, so returns an error if the pass fails, the
+/// device reports a validation error, or the device is lost.
+pub fn measure<E>(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mut pass: impl FnMut(&mut BenchmarkContext) -> Result<(), E>,
+) -> Result<Measurement, BenchmarkError<E>> {
+    let measurement = measure_once(context, options, &mut pass)?;
+    if measurement.noisy && options.rerun_noisy {
+        info!("Measuring the noisy configuration again.");
+        return measure_once(context, options, &mut pass);
+    }
+    Ok(measurement)
+}
+
+/// Private function to take a single measurement of a pass, as described by `measure`.
+fn measure_once<E>(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mut pass: impl FnMut(&mut BenchmarkContext) -> Result<(), E>,
+) -> Result<Measurement, BenchmarkError<E>> {
+    context.set_batch_size(options.batch_size);
+    let mut last_pass = Instant::now();
+    context.push_error_scope();
+    for _ in 0..options.warmup {
+        pace(options.pace, &mut last_pass);
+        record(context, &mut pass)?;
+        context.flush_if_needed();
+    }
+    context.submit();
+    check_error_scope(context)?;
+    // Reading the duration also waits for the warmup passes to finish
+    context
+        .last_pass_duration()
+        .map_err(|error| readback_error(context, error))?;
+
+    let mut submissions = 0;
+    let mut gpu_samples = Vec::with_capacity(options.samples);
+    let mut encode_samples = Vec::with_capacity(options.samples);
+    let mut finish_samples = Vec::with_capacity(options.samples);
+    let mut submit_samples = Vec::with_capacity(options.samples);
+    let soak_end = options.soak.map(|soak| Instant::now() + soak);
+    loop {
+        let done = match soak_end {
+            Some(soak_end) => Instant::now() >= soak_end,
+            None => submissions >= options.samples,
+        };
+        // Only stop between submissions, so every batch is complete
+        if done && context.pending_passes() == 0 {
+            break;
+        }
+        pace(options.pace, &mut last_pass);
+        if context.pending_passes() == 0 {
+            context.push_error_scope();
+        }
+        let start = Instant::now();
+        record(context, &mut pass)?;
+        encode_samples.push(start.elapsed());
+        context.flush_if_needed();
+        if context.pending_passes() > 0 {
+            continue;
+        }
+        check_error_scope(context)?;
+        submissions += 1;
+        if let Some(timing) = context.last_submit_timing() {
+            finish_samples.push(timing.encode);
+            submit_samples.push(timing.submit);
+        }
+        if let Some(duration) = context
+            .last_pass_duration()
+            .map_err(|error| readback_error(context, error))?
+        {
+            gpu_samples.push(duration);
+        }
+    }
+    let drift = options.soak.and_then(|_| Drift::from_samples(&gpu_samples));
+    if let Some(drift) = drift {
+        info!(
+            "Timings drifted over a soak of {} submissions: {}.",
+            submissions, drift
+        );
+    }
+    let tail =
+        TailLatency::from_samples(&gpu_samples, &options.percentiles, options.slowest_samples);
+    if let Some(tail) = &tail {
+        debug!("Tail latency of {} submissions: {}.", submissions, tail);
+    }
+    let gpu = Statistics::from_samples(&gpu_samples);
+    let noisy = match (gpu, options.max_variation) {
+        (Some(gpu), Some(max_variation)) if gpu.coefficient_of_variation() > max_variation => {
+            warn!(
+                "GPU durations varied by {:.1}% of their mean, above the maximum of {:.1}%, so \
+                 they may be skewed by background load or throttling: {}.",
+                gpu.coefficient_of_variation() * 100.0,
+                max_variation * 100.0,
+                gpu
+            );
+            true
+        }
+        _ => false,
+    };
+    Ok(Measurement {
+        gpu,
+        encode: Statistics::from_samples(&encode_samples),
+        finish: Statistics::from_samples(&finish_samples),
+        submit: Statistics::from_samples(&submit_samples),
+        drift,
+        tail,
+        noisy,
+    })
+}
+
+/// Run passes for the configured number of warmup iterations, then measure the wall time from
+/// recording them until the GPU has finished them for the configured number of samples. Unlike
+/// `measure`, the given closure may submit passes itself, and everything it records is submitted
+/// after it returns, so work split across several submissions is timed as a whole, including on
+/// adapters without timestamp queries. Passes are paced as configured. Returns statistics of the
+/// wall times, or `None` if no samples were configured, or an error as `measure` does.
+pub fn measure_until_finished<E>(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mut passes: impl FnMut(&mut BenchmarkContext) -> Result<(), E>,
+) -> Result<Option<Statistics>, BenchmarkError<E>> {
+    let mut last_pass = Instant::now();
+    let mut samples = Vec::with_capacity(options.samples);
+    for iteration in 0..options.warmup + options.samples {
+        pace(options.pace, &mut last_pass);
+        context.push_error_scope();
+        let start = Instant::now();
+        record(context, &mut passes)?;
+        // Submissions finish in order, so waiting on the last waits on any made by the passes
+        let submission = context.submit();
+        let finished = context.wait_for_submission(&submission);
+        let elapsed = start.elapsed();
+        check_error_scope(context)?;
+        finished.map_err(|error| readback_error(context, error))?;
+        if iteration >= options.warmup {
+            samples.push(elapsed);
+        }
+    }
+    Ok(Statistics::from_samples(&samples))
+}
+
+/// Private function to record a pass within an open error scope, popping the scope if the pass
+/// fails so scopes stay balanced.
+fn record<E>(
+    context: &mut BenchmarkContext,
+    pass: &mut impl FnMut(&mut BenchmarkContext) -> Result<(), E>,
+) -> Result<(), BenchmarkError<E>> {
+    pass(context).map_err(|error| {
+        context.pop_error_scope_sync();
+        BenchmarkError::Pass(error)
+    })
+}
+
+/// Private function to close the error scope of a submission, returning the validation error it
+/// captured, or an error if the device has been lost or the context aborted.
+fn check_error_scope<E>(context: &BenchmarkContext) -> Result<(), BenchmarkError<E>> {
+    if let Some(error) = context.pop_error_scope_sync() {
+        return Err(BenchmarkError::Validation(error));
+    }
+    if let Some(reason) = context.device_lost() {
+        return Err(BenchmarkError::DeviceLost(reason));
+    }
+    if context.aborted() {
+        return Err(BenchmarkError::Aborted);
+    }
+    Ok(())
+}
+
+/// Private function to convert an error waiting for passes or reading back their GPU duration to
+/// the benchmark error it implies. A GPU which did not finish within the poll timeout aborts the
+/// measurement, so the runner can continue with the next benchmark, and any other failure to map
+/// the timestamps means the device was lost.
+fn readback_error<E>(context: &BenchmarkContext, error: SaveError) -> BenchmarkError<E> {
+    match (context.device_lost(), error) {
+        (Some(reason), _) => BenchmarkError::DeviceLost(reason),
+        (None, SaveError::GpuTimeout(timeout)) => {
+            error!(
+                "The GPU did not finish a pass within {:?}, so the measurement is aborted.",
+                timeout
+            );
+            BenchmarkError::Aborted
+        }
+        (None, error) => BenchmarkError::DeviceLost(error.to_string()),
+    }
+}
+
+/// Private function to sleep until the given interval has passed since the last pass started, if
+/// passes are paced, then mark the start of the next pass.
+fn pace(interval: Option<Duration>, last_pass: &mut Instant) {
+    if let Some(interval) = interval {
+        if let Some(remaining) = interval.checked_sub(last_pass.elapsed()) {
+            sleep(remaining);
+        }
+    }
+    *last_pass = Instant::now();
+}
+
+/// Side of the smallest square resolution tried by `find_max_resolution`.
+const MIN_RESOLUTION_SIDE: u32 = 64;
+
+/// Precision in pixels to which `find_max_resolution` bisects the side of the largest resolution.
+const RESOLUTION_SIDE_PRECISION: u32 = 16;
+
+/// Find the largest square resolution at which the mean GPU duration of the pass stays within the
+/// target, such as 16.6ms to sustain 60 frames per second. The side starts at 64 pixels and
+/// doubles until the target is exceeded or the render target cannot grow, then bisects between
+/// the largest side within the target and the smallest beyond it. The render target is left at
+/// the last resolution tried. Returns `None` if the target is not a positive number of
+/// milliseconds, timestamp queries are unsupported or even the smallest resolution exceeds the
+/// target, or an error if measuring any resolution fails.
+pub fn find_max_resolution<E>(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    target_ms: f32,
+    mut pass: impl FnMut(&mut BenchmarkContext) -> Result<(), E>,
+) -> Result<Option<Resolution>, BenchmarkError<E>> {
+    if !(target_ms > 0.0 && target_ms.is_finite()) {
+        warn!(
+            "Target frame time {} ms is not a positive number of milliseconds.",
+            target_ms
+        );
+        return Ok(None);
+    }
+    let target = Duration::from_secs_f32(target_ms / 1000.0);
+    let mut within_target =
+        |context: &mut BenchmarkContext, side: u32| -> Result<_, BenchmarkError<E>> {
+            if context.resize_render_target((side, side)).is_err() {
+                return Ok(Some(false));
+            }
+            let measurement = measure(context, options, &mut pass)?;
+            Ok(measurement.gpu.map(|statistics| statistics.mean <= target))
+        };
+
+    // First, double the side until the target is exceeded
+    let mut low = 0;
+    let mut high = MIN_RESOLUTION_SIDE;
+    loop {
+        match within_target(context, high)? {
+            Some(true) => low = high,
+            Some(false) => break,
+            None => {
+                warn!("Timestamp queries are unsupported, so passes cannot be timed.");
+                return Ok(None);
+            }
+        }
+        match high.checked_mul(2) {
+            Some(doubled) => high = doubled,
+            None => return Ok(Some(Resolution::new(low, low))),
+        }
+    }
+    if low == 0 {
+        return Ok(None);
+    }
+
+    // Then, bisect between the largest side within the target and the smallest beyond it
+    while high - low > RESOLUTION_SIDE_PRECISION {
+        let middle = low + (high - low) / 2;
+        if within_target(context, middle)? == Some(true) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(Some(Resolution::new(low, low)))
+}
+
+/// Progress through the configurations of a benchmark, logging each completed configuration
+/// along with a rough estimate of the time remaining.
+pub struct Progress {
+    name: String,
+    total: usize,
+    finished: usize,
+    start: Instant,
+}
+
+impl Progress {
+    /// Start tracking progress through the given number of configurations of the named benchmark.
+    pub fn new(name: &str, total: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            total,
+            finished: 0,
+            start: Instant::now(),
+        }
+    }
+
+    /// Log that the described configuration has finished, with the mean GPU duration of its
+    /// passes if they were timed.
+    pub fn finish(&mut self, description: &str, mean: Option<Duration>) {
+        self.finished += 1;
+        let mean = mean.map_or("untimed".to_string(), |mean| {
+            format!("mean {:.3}ms", mean.as_secs_f64() * 1000.0)
+        });
+        info!(
+            "{} {} of {} ({}): {}, ETA {:.0?}.",
+            self.name,
+            self.finished,
+            self.total,
+            description,
+            mean,
+            self.remaining()
+        );
+    }
+
+    /// Count a configuration as finished without logging a result, such as when it was skipped.
+    pub fn skip(&mut self) {
+        self.finished += 1;
+    }
+
+    /// Private method to estimate the time remaining, assuming every configuration takes the
+    /// average time of those finished so far.
+    fn remaining(&self) -> Duration {
+        let remaining = self.total.saturating_sub(self.finished) as u32;
+        match self.finished {
+            0 => Duration::ZERO,
+            finished => self.start.elapsed() / finished as u32 * remaining,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn milliseconds(values: &[u64]) -> Vec<Duration> {
+        values.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn statistics_of_no_samples() {
+        assert!(Statistics::from_samples(&[]).is_none());
+        assert!(TailLatency::from_samples(&[], &[95.0], 5).is_none());
+        assert!(Drift::from_samples(&milliseconds(&[1])).is_none());
+    }
+
+    #[test]
+    fn statistics_of_one_sample() {
+        let statistics = Statistics::from_samples(&milliseconds(&[7])).unwrap();
+        assert_eq!(statistics.samples, 1);
+        assert_eq!(statistics.min, Duration::from_millis(7));
+        assert_eq!(statistics.mean, Duration::from_millis(7));
+        assert_eq!(statistics.median, Duration::from_millis(7));
+        assert_eq!(statistics.std_dev, Duration::ZERO);
+    }
+
+    #[test]
+    fn statistics_of_unsorted_samples() {
+        let statistics =
+            Statistics::from_samples(&milliseconds(&[9, 2, 5, 4, 4, 5, 7, 4])).unwrap();
+        assert_eq!(statistics.samples, 8);
+        assert_eq!(statistics.min, Duration::from_millis(2));
+        assert_eq!(statistics.mean, Duration::from_millis(5));
+        // The median of an even number of samples is the mean of the middle two
+        assert_eq!(statistics.median, Duration::from_millis(9) / 2);
+        // Population standard deviation
+        assert!((statistics.std_dev.as_secs_f64() - 0.002).abs() < 1e-9);
+        assert!((statistics.coefficient_of_variation() - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn statistics_of_zero_durations() {
+        let statistics = Statistics::from_samples(&[Duration::ZERO; 3]).unwrap();
+        assert_eq!(statistics.mean, Duration::ZERO);
+        assert_eq!(statistics.coefficient_of_variation(), 0.0);
+    }
+
+    #[test]
+    fn percentiles_by_nearest_rank() {
+        let samples = milliseconds(&(1..=100).rev().collect::<Vec<_>>());
+        let tail = TailLatency::from_samples(&samples, &[99.0, 50.0, 95.0, 95.0], 3).unwrap();
+        let percentiles: Vec<(f64, Duration)> = tail
+            .percentiles
+            .iter()
+            .map(|percentile| (percentile.percentile, percentile.duration))
+            .collect();
+        assert_eq!(
+            percentiles,
+            [
+                (50.0, Duration::from_millis(50)),
+                (95.0, Duration::from_millis(95)),
+                (99.0, Duration::from_millis(99)),
+            ]
+        );
+        assert_eq!(tail.slowest_samples, [0, 1, 2]);
+    }
+
+    #[test]
+    fn percentiles_without_enough_samples() {
+        let tail =
+            TailLatency::from_samples(&milliseconds(&[3, 1, 2]), &[0.0, 50.0, 99.0, 100.0], 5)
+                .unwrap();
+        let percentiles: Vec<f64> = tail
+            .percentiles
+            .iter()
+            .map(|percentile| percentile.percentile)
+            .collect();
+        assert_eq!(percentiles, [0.0, 50.0]);
+        assert_eq!(tail.percentiles[0].duration, Duration::from_millis(1));
+        assert_eq!(tail.percentiles[1].duration, Duration::from_millis(2));
+        assert_eq!(tail.slowest_samples, [0, 2, 1]);
+    }
+
+    #[test]
+    fn slowest_samples_keep_order_of_ties() {
+        let tail = TailLatency::from_samples(&milliseconds(&[4, 8, 4, 8]), &[], 3).unwrap();
+        assert_eq!(tail.slowest_samples, [1, 3, 0]);
+    }
+
+    #[test]
+    fn drift_compares_first_and_last_tenths() {
+        let mut samples = milliseconds(&[2; 20]);
+        samples[18] = Duration::from_millis(3);
+        samples[19] = Duration::from_millis(3);
+        let drift = Drift::from_samples(&samples).unwrap();
+        assert_eq!(drift.start.samples, 2);
+        assert_eq!(drift.end.mean, Duration::from_millis(3));
+        assert!((drift.ratio - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn drift_from_instant_start_is_none() {
+        let mut samples = milliseconds(&[0; 20]);
+        samples[19] = Duration::from_millis(3);
+        assert!(Drift::from_samples(&samples).is_none());
+    }
+}
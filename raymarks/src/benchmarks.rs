@@ -0,0 +1,1919 @@
+use crate::{
+    benchmark::{
+        find_max_resolution, measure, measure_until_finished, BenchmarkOptions, Measurement,
+        Progress,
+    },
+    camera::Camera,
+    color::{linear_to_srgb, srgb8_to_linear8, tonemap_to_srgb8},
+    config::{BenchmarkConfig, BenchmarkKind},
+    context::{BenchmarkContext, BenchmarkContextBuilder, ColorEncoding},
+    diff::{diff_images, DiffOptions},
+    geometry::{
+        asset_directory, load_cubes, load_obj, scatter_instances, sort_back_to_front,
+        stacked_instances, Cube, CubesError, Mesh, ObjError,
+    },
+    mesh::{Instance, InstanceStorage, VertexPrecision},
+    pass_parameters::PassParameters,
+    report::{BenchmarkFailure, BenchmarkReport, BenchmarkResult, Comparison},
+    resolution::Resolution,
+    scene::{MaterialId, Scene},
+    shaders::Shader,
+    watchdog::Watchdog,
+};
+use glam::Vec3;
+use log::{error, info, warn};
+use std::{
+    convert::Infallible,
+    fs::read_dir,
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use wgpu::{BlendState, TextureFormat};
+
+/// Seed used to scatter bunny instances, so runs are reproducible.
+const INSTANCE_SEED: u64 = 0;
+
+/// Benchmarks to run, along with the resolutions and model counts they are run at.
+#[derive(Clone, Debug)]
+pub struct BenchmarkParameters {
+    /// Benchmarks to run, in order.
+    pub benchmarks: Vec<BenchmarkKind>,
+    /// Render target sizes.
+    pub resolutions: Vec<Resolution>,
+    /// Numbers of bunnies drawn by the rasterization, instance storage, cube faces, scissor
+    /// quarter, clear draw and vertex precision benchmarks.
+    pub bunny_counts: Vec<u32>,
+    /// Numbers of triangles drawn by the triangle fill benchmark.
+    pub triangle_counts: Vec<u32>,
+    /// Numbers of stacked quads blended by the translucent overdraw benchmark.
+    pub overdraw_layers: Vec<u32>,
+    /// Frame time in milliseconds the max resolution benchmark keeps passes within.
+    pub target_frame_ms: f32,
+}
+
+impl Default for BenchmarkParameters {
+    /// The parameters of the embedded `benchmarks.toml` config.
+    fn default() -> Self {
+        BenchmarkConfig::default().parameters()
+    }
+}
+
+/// Run each of the given benchmarks once with the given parameters, blocking the current thread
+/// until they are complete, and return a report of their results.
+pub fn run_all(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    parameters: &BenchmarkParameters,
+) -> BenchmarkReport {
+    pollster::block_on(run_benchmark_async(context, options, parameters))
+}
+
+/// Run each of the given benchmarks once on every adapter available on the builder's backends, one
+/// adapter at a time, blocking the current thread until they are complete. Each context is set up
+/// by `configure` before the benchmarks run on it. Adapters whose device cannot be created are
+/// skipped with a warning. Returns a report per adapter, whose results are tagged with its name.
+pub fn run_all_adapters(
+    builder: &BenchmarkContextBuilder,
+    options: &BenchmarkOptions,
+    parameters: &BenchmarkParameters,
+    mut configure: impl FnMut(&mut BenchmarkContext),
+) -> Vec<BenchmarkReport> {
+    let adapters = builder.enumerate_adapters();
+    info!("Running benchmarks on {} adapters.", adapters.len());
+    let mut reports = Vec::with_capacity(adapters.len());
+    for adapter in adapters {
+        let name = adapter.get_info().name;
+        let mut context = match BenchmarkContext::new_on_adapter_sync(adapter, builder.clone()) {
+            Ok(context) => context,
+            Err(error) => {
+                warn!("Skipping adapter {}: {}", name, error);
+                continue;
+            }
+        };
+        info!("Running benchmarks on adapter {}.", name);
+        configure(&mut context);
+        reports.push(run_all(&mut context, options, parameters));
+        // Release this adapter's resources before the next adapter's device is created
+        context.shutdown();
+    }
+    reports
+}
+
+/// Run each of the given benchmarks once with the given parameters, for callers already running
+/// inside an async executor, and return a report of their results. If the device is lost, the
+/// remaining benchmarks are skipped, and the report holds the results completed before. If the
+/// options set a timeout, a watchdog thread marks benchmarks which run past it failed, writes the
+/// report to the options' partial results path, and aborts their measurements so the next
+/// benchmark runs.
+pub async fn run_benchmark_async(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    parameters: &BenchmarkParameters,
+) -> BenchmarkReport {
+    // The watchdog records failures in the report while benchmarks are running
+    let report = Arc::new(Mutex::new(BenchmarkReport::new(context.adapter_info())));
+    let watchdog = options.timeout.map(|timeout| {
+        Watchdog::spawn(
+            timeout,
+            context.abort_handle(),
+            report.clone(),
+            options.partial_results.clone(),
+        )
+    });
+    // Benchmarks drawing bunnies share one load of the model, made only if any of them runs
+    let bunny = parameters
+        .benchmarks
+        .iter()
+        .any(BenchmarkKind::draws_bunnies)
+        .then(bunny_mesh);
+    let no_mesh = Mesh::default();
+    // Benchmarks log timings as they go, so say once rather than for each pass why there are none
+    if !context.times_passes() {
+        warn!("Timestamp queries are unsupported, so passes will not be timed.");
+    }
+    for benchmark in &parameters.benchmarks {
+        if let Some(reason) = context.device_lost() {
+            error!(
+                "Skipping {:?} and later benchmarks, as the device was lost: {}",
+                benchmark, reason
+            );
+            break;
+        }
+        // Label command encoders with the benchmark, to find its submissions in frame captures
+        context.set_debug_label(&format!("{:?}", benchmark));
+        if let Some(watchdog) = &watchdog {
+            watchdog.start(&format!("{:?}", benchmark));
+        }
+        // Benchmarks drawing bunnies cannot run without the model, so fail them up front
+        let mesh = match &bunny {
+            Some(Ok(mesh)) => mesh,
+            Some(Err(error)) if benchmark.draws_bunnies() => {
+                error!(
+                    "Skipping {:?}, as the bunny model could not be loaded: {}",
+                    benchmark, error
+                );
+                report.lock().unwrap().failures.push(BenchmarkFailure {
+                    benchmark: format!("{:?}", benchmark),
+                    reason: format!("could not load the bunny model: {}", error),
+                });
+                if let Some(watchdog) = &watchdog {
+                    watchdog.finish();
+                }
+                continue;
+            }
+            // Other benchmarks don't draw the mesh
+            _ => &no_mesh,
+        };
+        let results = match benchmark {
+            BenchmarkKind::BunnyRasterization => {
+                bunny_rasterization(
+                    context,
+                    options,
+                    mesh,
+                    parameters.resolutions.clone(),
+                    parameters.bunny_counts.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::InstanceStorage => {
+                instance_storage(
+                    context,
+                    options,
+                    mesh,
+                    parameters.resolutions.clone(),
+                    parameters.bunny_counts.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::SphereRaymarch => {
+                sphere_raymarch(context, options, parameters.resolutions.clone()).await
+            }
+            BenchmarkKind::SdfComparison => {
+                sdf_comparison(context, options, parameters.resolutions.clone()).await
+            }
+            BenchmarkKind::PostprocessBlur => {
+                postprocess_blur(context, options, parameters.resolutions.clone()).await
+            }
+            BenchmarkKind::StencilMask => {
+                stencil_mask(context, options, parameters.resolutions.clone()).await
+            }
+            BenchmarkKind::MaxResolution => {
+                max_resolution(context, options, parameters.target_frame_ms).await
+            }
+            BenchmarkKind::TranslucentOverdraw => {
+                translucent_overdraw(
+                    context,
+                    options,
+                    parameters.resolutions.clone(),
+                    parameters.overdraw_layers.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::ScissorQuarter => {
+                scissor_quarter(
+                    context,
+                    options,
+                    mesh,
+                    parameters.resolutions.clone(),
+                    parameters.bunny_counts.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::ClearDraw => {
+                clear_draw(
+                    context,
+                    options,
+                    mesh,
+                    parameters.resolutions.clone(),
+                    parameters.bunny_counts.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::VertexPrecision => {
+                vertex_precision(
+                    context,
+                    options,
+                    mesh,
+                    parameters.resolutions.clone(),
+                    parameters.bunny_counts.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::SelfTest => {
+                self_test(context, options, parameters.resolutions.clone()).await
+            }
+            BenchmarkKind::CubeFaces => {
+                cube_faces(
+                    context,
+                    options,
+                    mesh,
+                    parameters.resolutions.clone(),
+                    parameters.bunny_counts.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::TriangleFill => {
+                triangle_fill(
+                    context,
+                    options,
+                    parameters.resolutions.clone(),
+                    parameters.triangle_counts.clone(),
+                )
+                .await
+            }
+            BenchmarkKind::SubmissionOverlap => {
+                submission_overlap(context, options, parameters.resolutions.clone()).await
+            }
+        };
+        if watchdog.as_ref().is_some_and(Watchdog::finish) {
+            warn!(
+                "Keeping the {} results of {:?} completed before it timed out.",
+                results.len(),
+                benchmark
+            );
+        }
+        let mut report = report.lock().unwrap();
+        report
+            .failures
+            .extend(results.iter().filter_map(BenchmarkResult::failure));
+        report.results.extend(results);
+    }
+    // Stopping the watchdog releases its reference to the report
+    drop(watchdog);
+    Arc::into_inner(report).unwrap().into_inner().unwrap()
+}
+
+/// Private function to log the timings of a measured pass, described such as "Raymarch pass at
+/// 640x480", add its result to the given results, and log the progress through the benchmark's
+/// configurations, described such as "res 640x480". GPU timings are left out of the log if passes
+/// are not timed, which `run_benchmark_async` warns about once.
+fn record(
+    results: &mut Vec<BenchmarkResult>,
+    progress: &mut Progress,
+    pass: &str,
+    configuration: &str,
+    measurement: &Measurement,
+    result: BenchmarkResult,
+) {
+    if let Some(statistics) = measurement.gpu {
+        info!("{}: {}.", pass, statistics);
+    }
+    if let Some(statistics) = measurement.encode {
+        info!("{} encoding: {}.", pass, statistics);
+    }
+    if let (Some(finish), Some(submit)) = (measurement.finish, measurement.submit) {
+        info!("{} submission: finish {}; submit {}.", pass, finish, submit);
+    }
+    if let Some(pipeline_statistics) = result.pipeline_statistics {
+        info!("{} statistics: {}.", pass, pipeline_statistics);
+    }
+    results.push(result);
+    progress.finish(
+        configuration,
+        measurement.gpu.map(|statistics| statistics.mean),
+    );
+}
+
+/// Benchmark which renders configurable amounts of the given mesh, loaded from the Stanford bunny
+/// model, using rasterization at configurable resolutions. Returns the results of the
+/// configurations which completed.
+pub async fn bunny_rasterization(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mesh: &Mesh,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    bunny_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    context.set_mesh(&mesh.vertices, &mesh.indices);
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new(
+        "Bunny rasterization",
+        bunny_counts.len() * resolutions.len(),
+    );
+    for count in bunny_counts {
+        context.set_instances(&scatter_instances(count, INSTANCE_SEED));
+        for &size in &resolutions {
+            if let Err(error) = context.resize_render_target(size) {
+                error!("Skipping resolution {}: {}", size, error);
+                progress.skip();
+                continue;
+            }
+            let measurement = match measure(context, options, |context| {
+                context.rasterization_pass(count)
+            }) {
+                Ok(measurement) => measurement,
+                Err(error) => {
+                    error!("Rasterization benchmark failed: {}", error);
+                    return results;
+                }
+            };
+            let result = BenchmarkResult {
+                count: Some(count),
+                draw_mode: Some(context.draw_mode()),
+                instance_storage: Some(context.instance_storage()),
+                ..BenchmarkResult::from_measurement(
+                    context,
+                    "bunny_rasterization",
+                    size,
+                    &measurement,
+                )
+            };
+            record(
+                &mut results,
+                &mut progress,
+                &format!(
+                    "Rasterization pass of {} bunnies at {} ({:?} draws)",
+                    count,
+                    size,
+                    context.draw_mode()
+                ),
+                &format!("res {}, count {}", size, count),
+                &measurement,
+                result,
+            );
+            if let Err(error) = context
+                .save_render_target(&format!("bunny_rasterization_{}", count))
+                .await
+            {
+                error!(
+                    "Failed to save render target of {} bunnies at {}: {}",
+                    count, size, error
+                );
+            }
+            if let Err(error) = context
+                .save_depth_buffer(&format!("bunny_rasterization_{}_depth", count))
+                .await
+            {
+                error!(
+                    "Failed to save depth buffer of {} bunnies at {}: {}",
+                    count, size, error
+                );
+            }
+        }
+    }
+    info!("Bunny rasterization benchmark complete.");
+    results
+}
+
+/// Private function to load the Stanford bunny model from the asset directory, once per run for
+/// the benchmarks drawing bunnies. They fail without it, rather than timing a stand-in model.
+fn bunny_mesh() -> Result<Mesh, ObjError> {
+    load_obj(&asset_directory().join("bunny.obj"))
+}
+
+/// Benchmark which renders configurable amounts of bunny instances read from a vertex buffer, a
+/// uniform buffer and a storage buffer in turn, at configurable resolutions, to compare the speed
+/// of each and the number of instances each allows. Counts beyond the instances a storage allows
+/// on the device are skipped for it. Returns the results of the configurations which completed.
+pub async fn instance_storage(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mesh: &Mesh,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    bunny_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    const STORAGES: [InstanceStorage; 3] = [
+        InstanceStorage::Vertex,
+        InstanceStorage::Uniform,
+        InstanceStorage::Storage,
+    ];
+    context.set_mesh(&mesh.vertices, &mesh.indices);
+    for storage in STORAGES {
+        info!(
+            "{:?} instance storage allows up to {} instances on this device.",
+            storage,
+            context.max_instances(storage)
+        );
+    }
+
+    let previous_storage = context.instance_storage();
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new(
+        "Instance storage",
+        bunny_counts.len() * resolutions.len() * STORAGES.len(),
+    );
+    for count in bunny_counts {
+        context.set_instances(&scatter_instances(count, INSTANCE_SEED));
+        for &size in &resolutions {
+            if let Err(error) = context.resize_render_target(size) {
+                error!("Skipping resolution {}: {}", size, error);
+                for _ in STORAGES {
+                    progress.skip();
+                }
+                continue;
+            }
+            let mut vertex_mean = None;
+            for storage in STORAGES {
+                let max_instances = context.max_instances(storage);
+                if count as u64 > max_instances {
+                    warn!(
+                        "Skipping {} bunnies with {:?} instance storage, which allows at most {}.",
+                        count, storage, max_instances
+                    );
+                    progress.skip();
+                    continue;
+                }
+                context.set_instance_storage(storage);
+                let measurement = match measure(context, options, |context| {
+                    context.rasterization_pass(count)
+                }) {
+                    Ok(measurement) => measurement,
+                    Err(error) => {
+                        error!("Instance storage benchmark failed: {}", error);
+                        context.set_instance_storage(previous_storage);
+                        return results;
+                    }
+                };
+                let result = BenchmarkResult {
+                    count: Some(count),
+                    draw_mode: Some(context.draw_mode()),
+                    instance_storage: Some(storage),
+                    ..BenchmarkResult::from_measurement(
+                        context,
+                        "instance_storage",
+                        size,
+                        &measurement,
+                    )
+                };
+                record(
+                    &mut results,
+                    &mut progress,
+                    &format!(
+                        "Rasterization pass of {} bunnies at {} with {:?} instance storage",
+                        count, size, storage
+                    ),
+                    &format!("res {}, count {}, {:?}", size, count, storage),
+                    &measurement,
+                    result,
+                );
+                match (measurement.gpu, vertex_mean) {
+                    (Some(statistics), None) if storage == InstanceStorage::Vertex => {
+                        vertex_mean = Some(statistics.mean)
+                    }
+                    (Some(statistics), Some(vertex_mean)) => info!(
+                        "{:?} instance storage took {:.2}x as long as vertex instance storage.",
+                        storage,
+                        statistics.mean.as_secs_f64() / vertex_mean.as_secs_f64()
+                    ),
+                    _ => {}
+                }
+            }
+        }
+    }
+    context.set_instance_storage(previous_storage);
+    info!("Instance storage benchmark complete.");
+    results
+}
+
+/// Benchmark which raymarches a signed distance field of spheres at configurable resolutions.
+/// Returns the results of the resolutions which completed.
+pub async fn sphere_raymarch(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> Vec<BenchmarkResult> {
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Sphere raymarch", resolutions.len());
+    for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+        let measurement = match measure(context, options, BenchmarkContext::raymarch_pass) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("Raymarch benchmark failed: {}", error);
+                return results;
+            }
+        };
+        let result =
+            BenchmarkResult::from_measurement(context, "sphere_raymarch", size, &measurement);
+        record(
+            &mut results,
+            &mut progress,
+            &format!("Raymarch pass at {}", size),
+            &format!("res {}", size),
+            &measurement,
+            result,
+        );
+        if let Err(error) = context.save_render_target("sphere_raymarch").await {
+            error!(
+                "Failed to save raymarched render target at {}: {}",
+                size, error
+            );
+        }
+    }
+    info!("Sphere raymarch benchmark complete.");
+    results
+}
+
+/// Benchmark which fills the render target with configurable amounts of procedurally generated
+/// triangles at configurable resolutions, without vertex buffers or depth testing. Returns the
+/// results of the configurations which completed.
+pub async fn triangle_fill(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    triangle_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Triangle fill", triangle_counts.len() * resolutions.len());
+    for count in triangle_counts {
+        for &size in &resolutions {
+            if let Err(error) = context.resize_render_target(size) {
+                error!("Skipping resolution {}: {}", size, error);
+                progress.skip();
+                continue;
+            }
+            let measurement =
+                match measure(context, options, |context| context.triangles_pass(count)) {
+                    Ok(measurement) => measurement,
+                    Err(error) => {
+                        error!("Triangle fill benchmark failed: {}", error);
+                        return results;
+                    }
+                };
+            let result = BenchmarkResult {
+                count: Some(count),
+                ..BenchmarkResult::from_measurement(context, "triangle_fill", size, &measurement)
+            };
+            record(
+                &mut results,
+                &mut progress,
+                &format!("Triangle fill pass of {} triangles at {}", count, size),
+                &format!("res {}, count {}", size, count),
+                &measurement,
+                result,
+            );
+            if let Err(error) = context
+                .save_render_target(&format!("triangle_fill_{}", count))
+                .await
+            {
+                error!(
+                    "Failed to save render target of {} triangles at {}: {}",
+                    count, size, error
+                );
+            }
+        }
+    }
+    info!("Triangle fill benchmark complete.");
+    results
+}
+
+/// Benchmark which renders the spheres of the raymarch shader with both rasterization and
+/// raymarching at configurable resolutions, from matching cameras. Both passes are timed, and an
+/// image of both renders and their difference heatmap side by side is saved. Returns the results
+/// of the resolutions which completed.
+pub async fn sdf_comparison(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> Vec<BenchmarkResult> {
+    let scene = Scene::sdf_spheres();
+    let instance_count = scene.instance_count() as u32;
+    let previous_camera = context.camera();
+    let previous_parameters = context.pass_parameters();
+    let parameters = PassParameters::default();
+    context.set_scene(&scene);
+    context.set_pass_parameters(parameters);
+    context.set_camera(Camera::raymarch(parameters.camera_position));
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("SDF comparison", resolutions.len());
+    let mut rasterized = Vec::new();
+    let mut raymarched = Vec::new();
+    for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+
+        // Both passes render to the same target, so each is read back right after it is measured
+        let rasterization = match measure(context, options, |context| {
+            context.rasterization_pass(instance_count)
+        }) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("SDF comparison rasterization failed: {}", error);
+                break;
+            }
+        };
+        if let Err(error) = context.read_render_target_into(&mut rasterized).await {
+            error!("Failed to read rasterized spheres at {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+        let raymarch = match measure(context, options, BenchmarkContext::raymarch_pass) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("SDF comparison raymarch failed: {}", error);
+                break;
+            }
+        };
+        if let Err(error) = context.read_render_target_into(&mut raymarched).await {
+            error!("Failed to read raymarched spheres at {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+
+        let (difference, heatmap) = diff_images(&rasterized, &raymarched, &DiffOptions::default());
+        let row_bytes = size.width as usize * 4;
+        let side_by_side: Vec<u8> = rasterized
+            .chunks_exact(row_bytes)
+            .zip(raymarched.chunks_exact(row_bytes))
+            .zip(heatmap.chunks_exact(row_bytes))
+            .flat_map(|((rasterized, raymarched), heatmap)| {
+                [rasterized, raymarched, heatmap].concat()
+            })
+            .collect();
+        let image = match context.save_rgba8(
+            "sdf_comparison",
+            (size.width * 3, size.height),
+            &side_by_side,
+        ) {
+            Ok(path) => Some(path),
+            Err(error) => {
+                error!("Failed to save SDF comparison at {}: {}", size, error);
+                None
+            }
+        };
+
+        // Raymarch once more, unmeasured, recording where the marcher takes the most steps
+        context.set_record_steps(true);
+        match context.raymarch_pass() {
+            Ok(()) => {
+                context.submit();
+                if let Err(error) = context.save_step_heatmap("sdf_comparison_steps").await {
+                    error!("Failed to save raymarch steps at {}: {}", size, error);
+                }
+            }
+            Err(error) => error!("Failed to record raymarch steps at {}: {}", size, error),
+        }
+        context.set_record_steps(false);
+
+        let ratio = match (rasterization.gpu, raymarch.gpu) {
+            (Some(rasterization), Some(raymarch)) => {
+                Some(raymarch.mean.as_secs_f64() / rasterization.mean.as_secs_f64())
+            }
+            _ => None,
+        };
+        if let Some(ratio) = ratio {
+            info!(
+                "SDF comparison at {}: raymarching took {:.2}x as long as rasterization, with a \
+                 mean difference of {:.2}.",
+                size, ratio, difference.mean_difference
+            );
+        }
+        results.push(BenchmarkResult {
+            draw_mode: Some(context.draw_mode()),
+            instance_storage: Some(context.instance_storage()),
+            noisy: rasterization.noisy || raymarch.noisy,
+            comparison: Some(Comparison {
+                rasterization: rasterization.gpu,
+                raymarch: raymarch.gpu,
+                raymarch_to_rasterization_ratio: ratio,
+                max_difference: difference.max_difference,
+                mean_difference: difference.mean_difference,
+                image,
+            }),
+            ..BenchmarkResult::new(context, "sdf_comparison", size)
+        });
+        progress.finish(
+            &format!("res {}", size),
+            raymarch.gpu.map(|statistics| statistics.mean),
+        );
+    }
+    context.set_camera(previous_camera);
+    context.set_pass_parameters(previous_parameters);
+    info!("SDF comparison benchmark complete.");
+    results
+}
+
+/// Benchmark which raymarches and rasterizes the spheres of the SDF comparison at configurable
+/// resolutions, first submitting each pass separately, then recording both into a single
+/// submission. The device has a single queue, as described on `BenchmarkContext`, so this measures
+/// what a submission boundary between the compute and render passes costs, and whether the driver
+/// overlaps them when they share a submission. Both passes write the same render target, so
+/// drivers which track the dependency still run them one after another. Each configuration is
+/// timed from recording the passes until the GPU has finished both, since timestamp queries only
+/// time one pass, and recorded as `submission_overlap_serial` and `submission_overlap_single`
+/// results. Returns the results of the resolutions which completed.
+pub async fn submission_overlap(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> Vec<BenchmarkResult> {
+    let scene = Scene::sdf_spheres();
+    let instance_count = scene.instance_count() as u32;
+    let previous_camera = context.camera();
+    let previous_parameters = context.pass_parameters();
+    let parameters = PassParameters::default();
+    context.set_scene(&scene);
+    context.set_pass_parameters(parameters);
+    context.set_camera(Camera::raymarch(parameters.camera_position));
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Submission overlap", resolutions.len());
+    for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+        let serial = match measure_until_finished(context, options, |context| {
+            context.raymarch_pass()?;
+            context.submit();
+            context.rasterization_pass(instance_count)
+        }) {
+            Ok(statistics) => statistics,
+            Err(error) => {
+                error!("Serial submission benchmark failed: {}", error);
+                break;
+            }
+        };
+        let single = match measure_until_finished(context, options, |context| {
+            context.raymarch_pass()?;
+            context.rasterization_pass(instance_count)
+        }) {
+            Ok(statistics) => statistics,
+            Err(error) => {
+                error!("Single submission benchmark failed: {}", error);
+                break;
+            }
+        };
+        if let (Some(serial), Some(single)) = (serial, single) {
+            info!(
+                "Raymarch and rasterization passes at {}: separate submissions {}; single \
+                 submission {}; {:.2}x as long in a single submission.",
+                size,
+                serial,
+                single,
+                single.mean.as_secs_f64() / serial.mean.as_secs_f64()
+            );
+        }
+        for (benchmark, statistics) in [
+            ("submission_overlap_serial", serial),
+            ("submission_overlap_single", single),
+        ] {
+            results.push(BenchmarkResult {
+                draw_mode: Some(context.draw_mode()),
+                instance_storage: Some(context.instance_storage()),
+                statistics,
+                ..BenchmarkResult::new(context, benchmark, size)
+            });
+        }
+        progress.finish(
+            &format!("res {}", size),
+            single.map(|statistics| statistics.mean),
+        );
+    }
+    context.set_camera(previous_camera);
+    context.set_pass_parameters(previous_parameters);
+    info!("Submission overlap benchmark complete.");
+    results
+}
+
+/// Benchmark which blurs the raymarched spheres with a compute post-process at configurable
+/// resolutions. Only the post-process passes are measured, not the raymarch pass rendering the
+/// image they blur. Returns the results of the resolutions which completed.
+pub async fn postprocess_blur(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> Vec<BenchmarkResult> {
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Post-process blur", resolutions.len());
+    for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+        // Render the spheres once, so every measured pass has an image to blur
+        if let Err(shader_error) = context.raymarch_pass() {
+            error!("Post-process benchmark failed to render: {}", shader_error);
+            return results;
+        }
+        context.submit();
+        let measurement = match measure(context, options, |context| {
+            context.compute_postprocess(Shader::Blur)
+        }) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("Post-process benchmark failed: {}", error);
+                return results;
+            }
+        };
+        let result =
+            BenchmarkResult::from_measurement(context, "postprocess_blur", size, &measurement);
+        record(
+            &mut results,
+            &mut progress,
+            &format!("Blur post-process pass at {}", size),
+            &format!("res {}", size),
+            &measurement,
+            result,
+        );
+        if let Err(error) = context.save_render_target("postprocess_blur").await {
+            error!(
+                "Failed to save blurred render target at {}: {}",
+                size, error
+            );
+        }
+    }
+    info!("Post-process blur benchmark complete.");
+    results
+}
+
+/// Benchmark which draws a stencil mask, then fills the render target where the stencil test
+/// passes, at configurable resolutions. Returns the results of the resolutions which completed.
+pub async fn stencil_mask(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> Vec<BenchmarkResult> {
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Stencil mask", resolutions.len());
+    for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+        let measurement = match measure(context, options, BenchmarkContext::stencil_pass) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("Stencil benchmark failed: {}", error);
+                return results;
+            }
+        };
+        let result = BenchmarkResult::from_measurement(context, "stencil_mask", size, &measurement);
+        record(
+            &mut results,
+            &mut progress,
+            &format!("Stencil pass at {}", size),
+            &format!("res {}", size),
+            &measurement,
+            result,
+        );
+        if let Err(error) = context.save_render_target("stencil_mask").await {
+            error!(
+                "Failed to save stencil render target at {}: {}",
+                size, error
+            );
+        }
+    }
+    info!("Stencil mask benchmark complete.");
+    results
+}
+
+/// Benchmark which searches for the largest square resolution at which raymarching the spheres
+/// stays within the target frame time, and measures the raymarch pass at that resolution.
+/// Returns no results if passes cannot be timed or no resolution stays within the target.
+pub async fn max_resolution(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    target_frame_ms: f32,
+) -> Vec<BenchmarkResult> {
+    let mut results = Vec::new();
+    let size = match find_max_resolution(
+        context,
+        options,
+        target_frame_ms,
+        BenchmarkContext::raymarch_pass,
+    ) {
+        Ok(Some(size)) => size,
+        Ok(None) => {
+            warn!(
+                "No resolution raymarches within {}ms, or passes cannot be timed.",
+                target_frame_ms
+            );
+            return results;
+        }
+        Err(error) => {
+            error!("Max resolution benchmark failed: {}", error);
+            return results;
+        }
+    };
+
+    // The search leaves the render target at the last resolution tried, which may be beyond it
+    if let Err(error) = context.resize_render_target(size) {
+        error!("Failed to resize to max resolution {}: {}", size, error);
+        return results;
+    }
+    let measurement = match measure(context, options, BenchmarkContext::raymarch_pass) {
+        Ok(measurement) => measurement,
+        Err(error) => {
+            error!("Max resolution benchmark failed: {}", error);
+            return results;
+        }
+    };
+    if let Some(statistics) = measurement.gpu {
+        info!(
+            "Max resolution raymarching within {}ms: {} ({}).",
+            target_frame_ms, size, statistics
+        );
+    }
+    results.push(BenchmarkResult::from_measurement(
+        context,
+        "max_resolution",
+        size,
+        &measurement,
+    ));
+    info!("Max resolution benchmark complete.");
+    results
+}
+
+/// Benchmark which alpha blends configurable numbers of translucent quads stacked in front of the
+/// camera, each covering the same pixels, at configurable resolutions. Quads are sorted back to
+/// front, and the blend state, opacity and camera are restored afterwards. Returns the results of
+/// the configurations which completed.
+pub async fn translucent_overdraw(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    layer_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    let previous_camera = context.camera();
+    let previous_blend_state = context.blend_state();
+    let previous_opacity = context.opacity();
+    let camera = Camera {
+        eye: Vec3::new(0.0, 0.0, 3.0),
+        target: Vec3::ZERO,
+        ..previous_camera
+    };
+    context.set_camera(camera);
+    context.set_blend_state(Some(BlendState::ALPHA_BLENDING));
+    context.set_opacity(0.25);
+    let quad = Mesh::quad();
+    context.set_mesh(&quad.vertices, &quad.indices);
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new(
+        "Translucent overdraw",
+        layer_counts.len() * resolutions.len(),
+    );
+    'layers: for count in layer_counts {
+        let mut instances = stacked_instances(count, 0.01);
+        sort_back_to_front(&mut instances, camera.eye);
+        context.set_instances(&instances);
+        for &size in &resolutions {
+            if let Err(error) = context.resize_render_target(size) {
+                error!("Skipping resolution {}: {}", size, error);
+                progress.skip();
+                continue;
+            }
+            let measurement = match measure(context, options, |context| {
+                context.rasterization_pass(count)
+            }) {
+                Ok(measurement) => measurement,
+                Err(error) => {
+                    error!("Translucent overdraw benchmark failed: {}", error);
+                    break 'layers;
+                }
+            };
+            let result = BenchmarkResult {
+                count: Some(count),
+                draw_mode: Some(context.draw_mode()),
+                instance_storage: Some(context.instance_storage()),
+                ..BenchmarkResult::from_measurement(
+                    context,
+                    "translucent_overdraw",
+                    size,
+                    &measurement,
+                )
+            };
+            record(
+                &mut results,
+                &mut progress,
+                &format!("Translucent overdraw pass of {} layers at {}", count, size),
+                &format!("res {}, layers {}", size, count),
+                &measurement,
+                result,
+            );
+            if let Err(error) = context
+                .save_render_target(&format!("translucent_overdraw_{}", count))
+                .await
+            {
+                error!(
+                    "Failed to save render target of {} layers at {}: {}",
+                    count, size, error
+                );
+            }
+        }
+    }
+    context.set_camera(previous_camera);
+    context.set_blend_state(previous_blend_state);
+    context.set_opacity(previous_opacity);
+    info!("Translucent overdraw benchmark complete.");
+    results
+}
+
+/// Benchmark which renders the six faces of a cube map centered among configurable amounts of
+/// bunnies into the layers of an array render target at configurable resolutions. Each measured
+/// pass renders the next face, so timings are per face. Returns the results of the
+/// configurations which completed.
+pub async fn cube_faces(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mesh: &Mesh,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    bunny_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    const FACES: u32 = 6;
+    let previous_camera = context.camera();
+    let previous_layer_count = context.layer_count();
+    let previous_target_layer = context.target_layer();
+    if !context.set_layer_count(FACES) {
+        error!(
+            "Skipping cube faces benchmark, as the render target cannot have {} layers, such as \
+             when multisampling.",
+            FACES
+        );
+        return Vec::new();
+    }
+    context.set_mesh(&mesh.vertices, &mesh.indices);
+    // The scattered bunnies fill a box in front of the origin, so center the cube map in it
+    let center = Vec3::new(0.0, 0.0, 0.5);
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Cube faces", bunny_counts.len() * resolutions.len());
+    'counts: for count in bunny_counts {
+        context.set_instances(&scatter_instances(count, INSTANCE_SEED));
+        for &size in &resolutions {
+            if let Err(error) = context.resize_render_target(size) {
+                error!("Skipping resolution {}: {}", size, error);
+                progress.skip();
+                continue;
+            }
+            // Cameras are written through the queue, so within a batch every face is rendered
+            // from the last camera written, which does not change the work done
+            let mut face = 0;
+            let measurement = match measure(context, options, |context| {
+                context.set_target_layer(face);
+                context.set_camera(Camera::cube_face(center, face));
+                face = (face + 1) % FACES;
+                context.rasterization_pass(count)
+            }) {
+                Ok(measurement) => measurement,
+                Err(error) => {
+                    error!("Cube faces benchmark failed: {}", error);
+                    break 'counts;
+                }
+            };
+            let result = BenchmarkResult {
+                count: Some(count),
+                draw_mode: Some(context.draw_mode()),
+                instance_storage: Some(context.instance_storage()),
+                ..BenchmarkResult::from_measurement(context, "cube_faces", size, &measurement)
+            };
+            record(
+                &mut results,
+                &mut progress,
+                &format!("Cube face pass of {} bunnies at {}", count, size),
+                &format!("res {}, count {}", size, count),
+                &measurement,
+                result,
+            );
+
+            // Render every face once more, each in its own submission so it sees its own camera.
+            // Every pass copies all layers, so the last submission holds the whole cube map
+            for face in 0..FACES {
+                context.set_target_layer(face);
+                context.set_camera(Camera::cube_face(center, face));
+                if let Err(error) = context.rasterization_pass(count) {
+                    error!("Failed to render cube face {}: {}", face, error);
+                    continue 'counts;
+                }
+                context.submit();
+            }
+            for face in 0..FACES {
+                if let Err(error) = context
+                    .save_render_target_layer(&format!("cube_faces_{}_face{}", count, face), face)
+                    .await
+                {
+                    error!(
+                        "Failed to save cube face {} of {} bunnies at {}: {}",
+                        face, count, size, error
+                    );
+                }
+            }
+        }
+    }
+    context.set_layer_count(previous_layer_count);
+    context.set_target_layer(previous_target_layer);
+    context.set_camera(previous_camera);
+    info!("Cube faces benchmark complete.");
+    results
+}
+
+/// Names of the corners of the gradient, in the order their pixels are checked.
+const GRADIENT_CORNERS: [&str; 4] = ["top left", "top right", "bottom left", "bottom right"];
+
+/// Benchmark which draws a gradient with a known color at each corner at configurable
+/// resolutions, then reads it back and checks the corner pixels. This validates the path from
+/// shader to render, readback and conversion, catching format, byte order and row padding
+/// regressions without any geometry or assets. Mismatches are logged as errors, and the result of
+/// the resolution is marked as failed, which the report records as a failure. Returns the
+/// results of the resolutions which completed.
+pub async fn self_test(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> Vec<BenchmarkResult> {
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Self-test", resolutions.len());
+    let mut pixels = Vec::new();
+    for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+        let measurement = match measure(context, options, BenchmarkContext::gradient_pass) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("Self-test failed: {}", error);
+                return results;
+            }
+        };
+
+        // The readback is part of what is tested, so failing to read the gradient fails the check
+        let passed = match context.read_render_target_into(&mut pixels).await {
+            Ok(()) => Some(gradient_corners_match(context, size, &pixels)),
+            Err(error) => {
+                error!("Failed to read back gradient at {}: {}", size, error);
+                None
+            }
+        };
+        let result = BenchmarkResult {
+            passed: Some(passed == Some(true)),
+            ..BenchmarkResult::from_measurement(context, "self_test", size, &measurement)
+        };
+        record(
+            &mut results,
+            &mut progress,
+            &format!("Gradient pass at {}", size),
+            &format!("res {}", size),
+            &measurement,
+            result,
+        );
+        match passed {
+            Some(true) => info!("Self-test passed at {}.", size),
+            Some(false) => {
+                if let Err(error) = context.save_render_target("self_test").await {
+                    error!("Failed to save gradient at {}: {}", size, error);
+                }
+            }
+            None => {}
+        }
+    }
+    info!("Self-test complete.");
+    results
+}
+
+/// Private function to check the corners of the gradient read back from a render target resized
+/// to the given size match the colors drawn, logging each mismatch as an error.
+fn gradient_corners_match(context: &BenchmarkContext, size: Resolution, pixels: &[u8]) -> bool {
+    // Pixels are read back at the output size, which is smaller than the render target if it is
+    // a smaller mip level or supersampled
+    let (width, height) = context.output_size();
+    let target_size = context.mip_level_size(context.target_mip_level());
+    let supersample = context.supersample();
+    // Supersampled pixels are averaged from 8-bit values, so may round differently
+    let tolerance = if supersample > 1 { 1 } else { 0 };
+    let expected = corner_pixels(width, height).map(|(x, y)| {
+        expected_rgba8(
+            context,
+            supersampled_gradient_color(x, y, supersample, target_size),
+        )
+    });
+    let mismatches = mismatched_corners(pixels, (width, height), expected, tolerance);
+    for (name, actual, expected) in &mismatches {
+        error!(
+            "Self-test at {}: {} pixel is {:?}, expected {:?}.",
+            size, name, actual, expected
+        );
+    }
+    mismatches.is_empty()
+}
+
+/// Private function to get the coordinates of the corner pixels of an image of the given size, in
+/// the order of `GRADIENT_CORNERS`.
+fn corner_pixels(width: u32, height: u32) -> [(u32, u32); 4] {
+    [
+        (0, 0),
+        (width - 1, 0),
+        (0, height - 1),
+        (width - 1, height - 1),
+    ]
+}
+
+/// Private function to find the corners of 8-bit RGBA pixels of the given size which differ from
+/// the expected colors, given in the order of `GRADIENT_CORNERS`, by more than the given tolerance
+/// in any channel. Returns the name, actual and expected color of each mismatched corner.
+fn mismatched_corners(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    expected: [[u8; 4]; 4],
+    tolerance: u8,
+) -> Vec<(&'static str, [u8; 4], [u8; 4])> {
+    let mut mismatches = Vec::new();
+    for ((name, (x, y)), expected) in GRADIENT_CORNERS
+        .into_iter()
+        .zip(corner_pixels(width, height))
+        .zip(expected)
+    {
+        let offset = (y as usize * width as usize + x as usize) * 4;
+        let actual: [u8; 4] = pixels[offset..offset + 4].try_into().unwrap();
+        if actual
+            .iter()
+            .zip(expected)
+            .any(|(&actual, expected)| actual.abs_diff(expected) > tolerance)
+        {
+            mismatches.push((name, actual, expected));
+        }
+    }
+    mismatches
+}
+
+/// Private function to compute the linear color of the given pixel of the gradient read back
+/// from a render target of the given size, supersampled by the given factor, which is the mean
+/// of the colors of the block of pixels the shader draws it from.
+fn supersampled_gradient_color(
+    x: u32,
+    y: u32,
+    supersample: u32,
+    (width, height): (u32, u32),
+) -> [f32; 4] {
+    let mut color = [0.0; 4];
+    for block_y in y * supersample..(y + 1) * supersample {
+        for block_x in x * supersample..(x + 1) * supersample {
+            // Matches the gradient shader, which reaches exactly 0 and 1 at the edge pixels
+            let u = block_x as f32 / (width - 1).max(1) as f32;
+            let v = block_y as f32 / (height - 1).max(1) as f32;
+            for (sum, value) in color.iter_mut().zip([u, v, 1.0 - u, 1.0]) {
+                *sum += value;
+            }
+        }
+    }
+    color.map(|sum| sum / (supersample * supersample) as f32)
+}
+
+/// Private function to compute the 8-bit RGBA value a linear color reads back as from the render
+/// target, following the tone mapping and output encoding applied to saved images.
+fn expected_rgba8(context: &BenchmarkContext, color: [f32; 4]) -> [u8; 4] {
+    let mut expected = [0; 4];
+    for (channel, (&value, expected)) in color.iter().zip(&mut expected).enumerate() {
+        // Alpha is linear, so it is only scaled
+        *expected = if channel == 3 {
+            (value * 255.0).round() as u8
+        } else if context.target_format() == TextureFormat::Rgba16Float {
+            tonemap_to_srgb8(value)
+        } else {
+            (linear_to_srgb(value) * 255.0).round() as u8
+        };
+        if channel != 3 && context.output_encoding() == ColorEncoding::Linear {
+            *expected = srgb8_to_linear8(*expected);
+        }
+    }
+    expected
+}
+
+/// Benchmark which renders configurable amounts of bunnies at configurable resolutions, first
+/// over the whole render target and then restricted by a scissor rectangle to its center
+/// quarter, to measure the fill rate saved by partial-screen rendering. Results are recorded for
+/// the scissored passes, with the time relative to the whole target logged. Returns the results
+/// of the configurations which completed.
+pub async fn scissor_quarter(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mesh: &Mesh,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    bunny_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    context.set_mesh(&mesh.vertices, &mesh.indices);
+    let previous_scissor_rect = context.scissor_rect();
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Scissor quarter", bunny_counts.len() * resolutions.len());
+    'counts: for count in bunny_counts {
+        context.set_instances(&scatter_instances(count, INSTANCE_SEED));
+        for &size in &resolutions {
+            if let Err(error) = context.resize_render_target(size) {
+                error!("Skipping resolution {}: {}", size, error);
+                progress.skip();
+                continue;
+            }
+            context.clear_scissor_rect();
+            let full = match measure(context, options, |context| {
+                context.rasterization_pass(count)
+            }) {
+                Ok(measurement) => measurement,
+                Err(error) => {
+                    error!("Scissor benchmark failed: {}", error);
+                    break 'counts;
+                }
+            };
+
+            // The center quarter is half the width and height, so a quarter of the pixels
+            let (width, height) = (size.width.max(2), size.height.max(2));
+            context.set_scissor_rect(width / 4, height / 4, width / 2, height / 2);
+            let quarter = match measure(context, options, |context| {
+                context.rasterization_pass(count)
+            }) {
+                Ok(measurement) => measurement,
+                Err(error) => {
+                    error!("Scissor benchmark failed: {}", error);
+                    break 'counts;
+                }
+            };
+            if let Some(statistics) = full.gpu {
+                info!(
+                    "Rasterization pass of {} bunnies at {} over the whole target: {}.",
+                    count, size, statistics
+                );
+            }
+            let result = BenchmarkResult {
+                count: Some(count),
+                draw_mode: Some(context.draw_mode()),
+                instance_storage: Some(context.instance_storage()),
+                ..BenchmarkResult::from_measurement(context, "scissor_quarter", size, &quarter)
+            };
+            record(
+                &mut results,
+                &mut progress,
+                &format!(
+                    "Rasterization pass of {} bunnies at {} over the center quarter",
+                    count, size
+                ),
+                &format!("res {}, count {}", size, count),
+                &quarter,
+                result,
+            );
+            if let (Some(full), Some(quarter)) = (full.gpu, quarter.gpu) {
+                info!(
+                    "The center quarter took {:.2}x as long as the whole target.",
+                    quarter.mean.as_secs_f64() / full.mean.as_secs_f64()
+                );
+            }
+            if let Err(error) = context
+                .save_render_target(&format!("scissor_quarter_{}", count))
+                .await
+            {
+                error!(
+                    "Failed to save render target of {} bunnies at {}: {}",
+                    count, size, error
+                );
+            }
+        }
+    }
+    match previous_scissor_rect {
+        Some((x, y, width, height)) => {
+            context.set_scissor_rect(x, y, width, height);
+        }
+        None => context.clear_scissor_rect(),
+    }
+    info!("Scissor quarter benchmark complete.");
+    results
+}
+
+/// Benchmark which separates the cost of clearing the render target from the cost of drawing
+/// into it. At each configurable resolution, passes which only clear the render target are
+/// measured, then passes drawing configurable amounts of bunnies over the previous contents
+/// without clearing them. On tiled GPUs, clears are nearly free, so the two barely add up to a
+/// cleared draw. Results are recorded for both, with the clear's count left empty. Returns the
+/// results of the configurations which completed.
+pub async fn clear_draw(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mesh: &Mesh,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    bunny_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    context.set_mesh(&mesh.vertices, &mesh.indices);
+    let previous_clear_target = context.clears_target();
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Clear draw", resolutions.len() * (bunny_counts.len() + 1));
+    'resolutions: for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            for _ in 0..=bunny_counts.len() {
+                progress.skip();
+            }
+            continue;
+        }
+        // Clear passes ignore whether rasterization passes clear the target
+        let clear = match measure(context, options, |context| {
+            context.clear_pass();
+            Ok::<(), Infallible>(())
+        }) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("Clear benchmark failed: {}", error);
+                break;
+            }
+        };
+        let result = BenchmarkResult::from_measurement(context, "clear_only", size, &clear);
+        record(
+            &mut results,
+            &mut progress,
+            &format!("Clear pass at {}", size),
+            &format!("res {}, clear", size),
+            &clear,
+            result,
+        );
+
+        context.set_clears_target(false);
+        for &count in &bunny_counts {
+            context.set_instances(&scatter_instances(count, INSTANCE_SEED));
+            let draw = match measure(context, options, |context| {
+                context.rasterization_pass(count)
+            }) {
+                Ok(measurement) => measurement,
+                Err(error) => {
+                    error!("Clear draw benchmark failed: {}", error);
+                    break 'resolutions;
+                }
+            };
+            let result = BenchmarkResult {
+                count: Some(count),
+                draw_mode: Some(context.draw_mode()),
+                instance_storage: Some(context.instance_storage()),
+                ..BenchmarkResult::from_measurement(context, "load_draw", size, &draw)
+            };
+            record(
+                &mut results,
+                &mut progress,
+                &format!(
+                    "Rasterization pass of {} bunnies at {} without clearing",
+                    count, size
+                ),
+                &format!("res {}, count {}", size, count),
+                &draw,
+                result,
+            );
+            if let (Some(clear), Some(draw)) = (clear.gpu, draw.gpu) {
+                info!(
+                    "Clearing alone took {:.2}x as long.",
+                    clear.mean.as_secs_f64() / draw.mean.as_secs_f64()
+                );
+            }
+        }
+    }
+    context.set_clears_target(previous_clear_target);
+    info!("Clear draw benchmark complete.");
+    results
+}
+
+/// Run the shader directory benchmark at the given parameters' resolutions, blocking the current
+/// thread until it is complete, and return a report of its results, which compare the shaders.
+/// Returns an error if the directory cannot be read.
+pub fn run_shader_directory(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    parameters: &BenchmarkParameters,
+    directory: &Path,
+) -> io::Result<BenchmarkReport> {
+    let mut report = BenchmarkReport::new(context.adapter_info());
+    report.results = pollster::block_on(shader_directory(
+        context,
+        options,
+        directory,
+        parameters.resolutions.clone(),
+    ))?;
+    Ok(report)
+}
+
+/// Benchmark which runs each `.wgsl` file of a directory, in order of filename, as the shader of
+/// raymarch passes at configurable resolutions, so every shader renders the same full-screen pass.
+/// Each shader is validated and its pipeline built before it is measured, and shaders which fail
+/// are skipped with a warning. Results and saved images are named after the shader's file stem,
+/// and the shaders are ranked by mean GPU duration at each resolution once all have run. Returns
+/// the results of the configurations which completed, or an error if the directory cannot be
+/// read.
+pub async fn shader_directory(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    directory: &Path,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> io::Result<Vec<BenchmarkResult>> {
+    let mut paths = Vec::new();
+    for entry in read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == "wgsl")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    info!(
+        "Benchmarking {} shaders from {}.",
+        paths.len(),
+        directory.display()
+    );
+    let previous_shader = context.raymarch_shader().clone();
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Shader directory", paths.len() * resolutions.len());
+    'shaders: for path in paths {
+        if let Some(reason) = context.device_lost() {
+            error!(
+                "Skipping {} and later shaders, as the device was lost: {}",
+                path.display(),
+                reason
+            );
+            break;
+        }
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        context.set_raymarch_shader(Shader::from_path(&path));
+        // Building the pipeline within an error scope also catches shaders whose bindings or
+        // entry points do not match the raymarch pass
+        context.push_error_scope();
+        let built = context.raymarch_pipeline();
+        let validation_error = context.pop_error_scope().await;
+        let invalid = match (built, validation_error) {
+            (Err(error), _) => Some(error.to_string()),
+            (Ok(_), Some(error)) => Some(error.to_string()),
+            (Ok(_), None) => None,
+        };
+        if let Some(error) = invalid {
+            warn!("Skipping shader {}: {}", path.display(), error);
+            for _ in 0..resolutions.len() {
+                progress.skip();
+            }
+            continue;
+        }
+
+        for (index, &size) in resolutions.iter().enumerate() {
+            if let Err(error) = context.resize_render_target(size) {
+                error!("Skipping resolution {}: {}", size, error);
+                progress.skip();
+                continue;
+            }
+            let measurement = match measure(context, options, BenchmarkContext::raymarch_pass) {
+                Ok(measurement) => measurement,
+                Err(error) => {
+                    error!("Shader {} failed: {}", path.display(), error);
+                    for _ in index..resolutions.len() {
+                        progress.skip();
+                    }
+                    continue 'shaders;
+                }
+            };
+            let result = BenchmarkResult::from_measurement(context, &name, size, &measurement);
+            record(
+                &mut results,
+                &mut progress,
+                &format!("Shader {} at {}", name, size),
+                &format!("{}, res {}", name, size),
+                &measurement,
+                result,
+            );
+            if let Err(error) = context.save_render_target(&name).await {
+                error!(
+                    "Failed to save render target of shader {} at {}: {}",
+                    name, size, error
+                );
+            }
+        }
+    }
+    context.set_raymarch_shader(previous_shader);
+
+    for &size in &resolutions {
+        let mut ranking: Vec<(&str, Duration)> = results
+            .iter()
+            .filter(|result| result.resolution == size)
+            .filter_map(|result| Some((result.benchmark.as_str(), result.statistics?.mean)))
+            .collect();
+        ranking.sort_by_key(|&(_, mean)| mean);
+        for (rank, (name, mean)) in ranking.iter().enumerate() {
+            info!(
+                "Shader ranking at {}: {}. {} ({:.3} ms).",
+                size,
+                rank + 1,
+                name,
+                mean.as_secs_f64() * 1000.0
+            );
+        }
+    }
+    info!("Shader directory benchmark complete.");
+    Ok(results)
+}
+
+/// Load the cubes exported by `bevy_extract` from the given path and rasterize them at each of
+/// the given resolutions, blocking the current thread until they are complete, instead of running
+/// the configured benchmarks. Returns a report of their results, or an error if the cubes cannot
+/// be loaded.
+pub fn run_cube_scene(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    parameters: &BenchmarkParameters,
+    path: &Path,
+) -> Result<BenchmarkReport, CubesError> {
+    let cubes = load_cubes(path)?;
+    let mut report = BenchmarkReport::new(context.adapter_info());
+    report.results = pollster::block_on(cube_scene(
+        context,
+        options,
+        &cubes,
+        parameters.resolutions.clone(),
+    ));
+    Ok(report)
+}
+
+/// Benchmark which rasterizes a scene of cubes, such as those exported by `bevy_extract`, at
+/// configurable resolutions. The cubes are baked into a single mesh drawn once, from the context's
+/// camera, which by default matches the camera of the Bevy scene. Returns the results of the
+/// resolutions which completed.
+pub async fn cube_scene(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    cubes: &[Cube],
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+) -> Vec<BenchmarkResult> {
+    let scene = Scene::new().with_object(
+        Mesh::cubes(cubes),
+        vec![Instance::IDENTITY],
+        MaterialId::WHITE,
+    );
+    context.set_scene(&scene);
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new("Cube scene", resolutions.len());
+    for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            progress.skip();
+            continue;
+        }
+        let measurement = match measure(context, options, |context| context.rasterization_pass(1)) {
+            Ok(measurement) => measurement,
+            Err(error) => {
+                error!("Cube scene benchmark failed: {}", error);
+                break;
+            }
+        };
+        let result = BenchmarkResult {
+            count: Some(cubes.len() as u32),
+            draw_mode: Some(context.draw_mode()),
+            instance_storage: Some(context.instance_storage()),
+            ..BenchmarkResult::from_measurement(context, "cube_scene", size, &measurement)
+        };
+        record(
+            &mut results,
+            &mut progress,
+            &format!("Rasterization pass of {} cubes at {}", cubes.len(), size),
+            &format!("res {}", size),
+            &measurement,
+            result,
+        );
+        if let Err(error) = context.save_render_target("cube_scene").await {
+            error!(
+                "Failed to save render target of cubes at {}: {}",
+                size, error
+            );
+        }
+    }
+    info!("Cube scene benchmark complete.");
+    results
+}
+
+/// Benchmark which compares rasterizing configurable amounts of bunnies from single- and
+/// half-precision vertex buffers at configurable resolutions, to measure the memory bandwidth
+/// saved by compressing vertex attributes. High counts make vertex fetches a larger share of each
+/// pass. Results are recorded for both precisions, or only single precision on devices which
+/// cannot fetch half-precision vertices. Returns the results of the configurations which
+/// completed.
+pub async fn vertex_precision(
+    context: &mut BenchmarkContext,
+    options: &BenchmarkOptions,
+    mesh: &Mesh,
+    resolutions: impl IntoIterator<Item = impl Into<Resolution>>,
+    bunny_counts: Vec<u32>,
+) -> Vec<BenchmarkResult> {
+    let previous_precision = context.vertex_precision();
+    let precisions: Vec<(VertexPrecision, &str)> = [
+        (VertexPrecision::Single, "vertex_f32"),
+        (VertexPrecision::Half, "vertex_f16"),
+    ]
+    .into_iter()
+    .filter(|&(precision, name)| {
+        let supported = context.supports_vertex_precision(precision);
+        if !supported {
+            warn!(
+                "Skipping {}, as {:?} precision vertices are unsupported.",
+                name, precision
+            );
+        }
+        supported
+    })
+    .collect();
+
+    let resolutions: Vec<Resolution> = resolutions.into_iter().map(Into::into).collect();
+    let mut results = Vec::new();
+    let mut progress = Progress::new(
+        "Vertex precision",
+        resolutions.len() * bunny_counts.len() * precisions.len(),
+    );
+    'resolutions: for size in resolutions {
+        if let Err(error) = context.resize_render_target(size) {
+            error!("Skipping resolution {}: {}", size, error);
+            for _ in 0..bunny_counts.len() * precisions.len() {
+                progress.skip();
+            }
+            continue;
+        }
+        for &count in &bunny_counts {
+            let mut single_mean = None;
+            for &(precision, name) in &precisions {
+                // Meshes keep the precision they were uploaded with
+                context.set_vertex_precision(precision);
+                context.set_mesh(&mesh.vertices, &mesh.indices);
+                context.set_instances(&scatter_instances(count, INSTANCE_SEED));
+                let measurement = match measure(context, options, |context| {
+                    context.rasterization_pass(count)
+                }) {
+                    Ok(measurement) => measurement,
+                    Err(error) => {
+                        error!("Vertex precision benchmark failed: {}", error);
+                        break 'resolutions;
+                    }
+                };
+                let result = BenchmarkResult {
+                    count: Some(count),
+                    draw_mode: Some(context.draw_mode()),
+                    instance_storage: Some(context.instance_storage()),
+                    ..BenchmarkResult::from_measurement(context, name, size, &measurement)
+                };
+                record(
+                    &mut results,
+                    &mut progress,
+                    &format!(
+                        "Rasterization pass of {} bunnies at {} with {:?} precision vertices",
+                        count, size, precision
+                    ),
+                    &format!("res {}, count {}, {:?}", size, count, precision),
+                    &measurement,
+                    result,
+                );
+                match (measurement.gpu, single_mean) {
+                    (Some(statistics), None) => single_mean = Some(statistics.mean),
+                    (Some(statistics), Some(single_mean)) => info!(
+                        "{:?} precision vertices took {:.2}x as long as single precision.",
+                        precision,
+                        statistics.mean.as_secs_f64() / single_mean.as_secs_f64()
+                    ),
+                    (None, _) => {}
+                }
+            }
+        }
+    }
+    context.set_vertex_precision(previous_precision);
+    // Upload the mesh again, so it matches the restored precision
+    context.set_mesh(&mesh.vertices, &mesh.indices);
+    info!("Vertex precision benchmark complete.");
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Colors of the corners of the gradient checked by the tests, in the order of
+    /// `GRADIENT_CORNERS`.
+    const CORNERS: [[u8; 4]; 4] = [
+        [0, 0, 255, 255],
+        [255, 0, 0, 255],
+        [0, 255, 255, 255],
+        [255, 255, 0, 255],
+    ];
+
+    /// 8-bit RGBA pixels of the given size, gray except for the given corner colors.
+    fn pixels((width, height): (u32, u32), corners: [[u8; 4]; 4]) -> Vec<u8> {
+        let mut pixels = vec![128; (width * height * 4) as usize];
+        for ((x, y), color) in corner_pixels(width, height).into_iter().zip(corners) {
+            let offset = (y * width + x) as usize * 4;
+            pixels[offset..offset + 4].copy_from_slice(&color);
+        }
+        pixels
+    }
+
+    #[test]
+    fn matching_corners_pass() {
+        let size = (5, 3);
+        assert!(mismatched_corners(&pixels(size, CORNERS), size, CORNERS, 0).is_empty());
+    }
+
+    #[test]
+    fn mismatched_corners_are_reported() {
+        let size = (5, 3);
+        let mut corners = CORNERS;
+        corners[2] = [0, 0, 0, 255];
+        let mismatches = mismatched_corners(&pixels(size, corners), size, CORNERS, 0);
+        assert_eq!(
+            mismatches,
+            [("bottom left", [0, 0, 0, 255], [0, 255, 255, 255])]
+        );
+    }
+
+    #[test]
+    fn corners_within_tolerance_pass() {
+        let size = (4, 4);
+        let mut corners = CORNERS;
+        corners[1] = [254, 1, 0, 255];
+        let pixels = pixels(size, corners);
+        assert!(mismatched_corners(&pixels, size, CORNERS, 1).is_empty());
+        assert_eq!(mismatched_corners(&pixels, size, CORNERS, 0).len(), 1);
+    }
+
+    #[test]
+    fn single_pixel_images_have_one_corner() {
+        let size = (1, 1);
+        let pixels = [0, 0, 255, 255];
+        let mismatches = mismatched_corners(&pixels, size, CORNERS, 0);
+        assert_eq!(mismatches.len(), 3);
+    }
+
+    #[test]
+    fn supersampled_gradient_colors_average_their_blocks() {
+        // The top left pixel of a 4x4 target downsampled by 2 averages its 2x2 block
+        let color = supersampled_gradient_color(0, 0, 2, (4, 4));
+        let step = 1.0 / 3.0;
+        assert!((color[0] - step / 2.0).abs() < 1e-6);
+        assert!((color[1] - step / 2.0).abs() < 1e-6);
+        assert!((color[2] - (1.0 - step / 2.0)).abs() < 1e-6);
+        assert_eq!(color[3], 1.0);
+        assert_eq!(
+            supersampled_gradient_color(3, 0, 1, (4, 4)),
+            [1.0, 0.0, 0.0, 1.0]
+        );
+    }
+}
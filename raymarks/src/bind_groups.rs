@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use wgpu::*;
+
+/// Cached pipeline of the context whose layout a cached bind group was created with. Layouts
+/// derived from a pipeline's shader are unique to that pipeline, so the pipeline stands in for
+/// its layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LayoutSource {
+    Mipmap,
+    Rasterization,
+    Raymarch,
+    Postprocess,
+}
+
+/// Resource of the context bound by an entry of a cached bind group. Passes create new texture
+/// views every time, so views are identified by their texture and the parts of their descriptor
+/// which vary between passes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BoundResource {
+    /// A view of a single mip level and array layer of the render target, in the given format.
+    RenderTarget {
+        format: TextureFormat,
+        mip_level: u32,
+        array_layer: u32,
+    },
+    /// The texture raymarch passes write step counts to.
+    StepTexture,
+    /// The copy of the render target read by post-process passes.
+    PostprocessInput,
+    LinearSampler,
+    CameraBuffer,
+    /// The instance buffer of the scene object with the given index.
+    Instances(usize),
+}
+
+/// Numbers of bind group cache lookups which reused a bind group, and which created one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BindGroupCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Bind groups created by passes, keyed by the layout they were created with and the resources
+/// they bind, so passes reuse them instead of recreating them every time. Keys name the context's
+/// resources rather than identify them, so the cache must be cleared whenever a target, buffer or
+/// pipeline it may refer to is recreated, which starts a new generation of keys.
+#[derive(Debug, Default)]
+pub struct BindGroupCache {
+    bind_groups: HashMap<(u64, LayoutSource, Vec<BoundResource>), BindGroup>,
+    generation: u64,
+    stats: BindGroupCacheStats,
+}
+
+impl BindGroupCache {
+    /// Get the bind group of the given layout binding the given resources, creating it with
+    /// `create` if it is not cached yet.
+    pub fn get_or_create(
+        &mut self,
+        layout: LayoutSource,
+        resources: &[BoundResource],
+        create: impl FnOnce() -> BindGroup,
+    ) -> &BindGroup {
+        let key = (self.generation, layout, resources.to_vec());
+        if self.bind_groups.contains_key(&key) {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        self.bind_groups.entry(key).or_insert_with(create)
+    }
+
+    /// Get the number of cached bind groups.
+    pub fn len(&self) -> usize {
+        self.bind_groups.len()
+    }
+
+    /// Get whether no bind groups are cached.
+    pub fn is_empty(&self) -> bool {
+        self.bind_groups.is_empty()
+    }
+
+    /// Get the numbers of lookups which reused and created a bind group since the cache was
+    /// created.
+    pub fn stats(&self) -> BindGroupCacheStats {
+        self.stats
+    }
+
+    /// Drop every cached bind group and start a new generation of keys, keeping the statistics.
+    pub fn clear(&mut self) {
+        self.bind_groups.clear();
+        self.generation += 1;
+    }
+}
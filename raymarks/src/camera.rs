@@ -0,0 +1,182 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use wgpu::{util::DeviceExt, *};
+
+/// Perspective camera from which rasterization passes are rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// Position of the camera.
+    pub eye: Vec3,
+    /// Point the camera looks at.
+    pub target: Vec3,
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+    /// Ratio of width over height of the rendered image.
+    pub aspect: f32,
+    /// Direction which is up in the rendered image.
+    pub up: Vec3,
+}
+
+impl Camera {
+    /// Distance to the near clipping plane.
+    pub const NEAR: f32 = 0.1;
+
+    /// Distance to the far clipping plane.
+    pub const FAR: f32 = 100.0;
+
+    /// Distance from the eye of the raymarch shader to its image plane, whose half height is 1.
+    const RAYMARCH_FOCAL_LENGTH: f32 = 2.0;
+
+    /// A camera matching the rays cast by the raymarch shader from the given position, which look
+    /// down the negative z axis.
+    pub fn raymarch(position: Vec3) -> Self {
+        Self {
+            eye: position,
+            target: position - Vec3::Z,
+            fov: 2.0 * (1.0 / Self::RAYMARCH_FOCAL_LENGTH).atan(),
+            ..Default::default()
+        }
+    }
+
+    /// A camera rendering the given face of a cube map centered on the given position, with faces
+    /// ordered +X, -X, +Y, -Y, +Z, -Z as in the layers of a cube texture. Each face has a 90
+    /// degree field of view, so the six faces together cover every direction.
+    pub fn cube_face(position: Vec3, face: u32) -> Self {
+        let (direction, up) = match face % 6 {
+            0 => (Vec3::X, Vec3::NEG_Y),
+            1 => (Vec3::NEG_X, Vec3::NEG_Y),
+            2 => (Vec3::Y, Vec3::Z),
+            3 => (Vec3::NEG_Y, Vec3::NEG_Z),
+            4 => (Vec3::Z, Vec3::NEG_Y),
+            _ => (Vec3::NEG_Z, Vec3::NEG_Y),
+        };
+        Self {
+            eye: position,
+            target: position + direction,
+            fov: 90f32.to_radians(),
+            aspect: 1.0,
+            up,
+        }
+    }
+
+    /// Compute the matrix transforming world space positions to clip space.
+    pub fn view_projection(&self) -> Mat4 {
+        let view = Mat4::look_at_rh(self.eye, self.target, self.up);
+        let projection = Mat4::perspective_rh(self.fov, self.aspect, Self::NEAR, Self::FAR);
+        projection * view
+    }
+
+    /// Convert a value from the depth buffer, which is 0 at the near plane and 1 at the far
+    /// plane, back to the distance from the eye along the view direction.
+    pub fn linear_depth(&self, depth: f32) -> f32 {
+        Self::NEAR * Self::FAR / (Self::FAR - depth * (Self::FAR - Self::NEAR))
+    }
+}
+
+impl Default for Camera {
+    /// A camera looking at the origin from above and in front, with a 45 degree field of view.
+    fn default() -> Self {
+        Self {
+            eye: Vec3::new(-2.0, 2.5, 5.0),
+            target: Vec3::ZERO,
+            fov: 45f32.to_radians(),
+            aspect: 1.0,
+            up: Vec3::Y,
+        }
+    }
+}
+
+/// Camera data laid out as expected by the rasterization shader.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct CameraUniform {
+    view_projection: [[f32; 4]; 4],
+}
+
+impl From<&Camera> for CameraUniform {
+    fn from(camera: &Camera) -> Self {
+        Self {
+            view_projection: camera.view_projection().to_cols_array_2d(),
+        }
+    }
+}
+
+/// Camera uniform which has been uploaded to a GPU buffer, along with the bind group exposing it
+/// to shaders.
+pub struct CameraBuffer {
+    buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+impl CameraBuffer {
+    /// Upload the given camera to a new GPU buffer and create a bind group for it.
+    pub fn new(device: &Device, camera: &Camera) -> Self {
+        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("raymarks.camera"),
+            contents: bytemuck::bytes_of(&CameraUniform::from(camera)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("raymarks.camera_bind_group_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("raymarks.camera_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// Write the given camera to the buffer, taking effect from the next submission.
+    pub fn write(&self, queue: &Queue, camera: &Camera) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::from(camera)),
+        );
+    }
+
+    /// Get the layout of the bind group, for creating pipeline layouts.
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Get the bind group exposing the camera to shaders.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Get the GPU buffer, to bind it in bind groups of other layouts.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Get the size of the GPU buffer in bytes.
+    pub fn size(&self) -> BufferAddress {
+        self.buffer.size()
+    }
+
+    /// Free the GPU buffer.
+    pub fn destroy(&self) {
+        self.buffer.destroy();
+    }
+}
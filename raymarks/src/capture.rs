@@ -0,0 +1,159 @@
+use crate::context::{BenchmarkContext, SaveError};
+use flume::Receiver;
+use wgpu::*;
+
+/// Texel data of the render target captured by `BenchmarkContext::capture`, in the format of the
+/// render target with rows unpadded.
+#[derive(Clone, Debug)]
+pub struct CapturedFrame {
+    /// Number of frames captured before this one since the capture ring was created.
+    pub index: usize,
+    pub size: (u32, u32),
+    pub data: Vec<u8>,
+}
+
+/// Receiver of the result of mapping a staging buffer.
+type MapReceiver = Receiver<Result<(), BufferAsyncError>>;
+
+/// A staging buffer of a capture ring, along with the index and size of the frame copied into
+/// it, if any, and the receiver of the result of mapping it.
+struct CaptureSlot {
+    buffer: Buffer,
+    in_flight: Option<(usize, (u32, u32), MapReceiver)>,
+}
+
+/// A ring of staging buffers which the render target is copied into in turn, for video-rate
+/// capture.
+///
+/// A buffer cannot stay mapped while the GPU copies into it, so instead of mapping one buffer
+/// persistently, each copy is mapped as soon as it is submitted and read back only once its
+/// buffer comes around again. Reading frame N then overlaps rendering the frames after it,
+/// instead of stalling the CPU until the GPU has finished frame N.
+pub struct CaptureRing {
+    slots: Vec<CaptureSlot>,
+    next: usize,
+    captured: usize,
+    bytes_per_texel: u32,
+}
+
+impl CaptureRing {
+    /// Create a ring of the given number of staging buffers, at least 1, each large enough for a
+    /// render target of the given size.
+    pub(crate) fn new(device: &Device, depth: u32, size: (u32, u32), bytes_per_texel: u32) -> Self {
+        let buffer_size =
+            BenchmarkContext::padded_bytes_per_row(size.0, bytes_per_texel) as u64 * size.1 as u64;
+        let slots = (0..depth.max(1))
+            .map(|_| CaptureSlot {
+                buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some(&format!("raymarks.capture_ring.{}x{}", size.0, size.1)),
+                    size: buffer_size,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                in_flight: None,
+            })
+            .collect();
+        Self {
+            slots,
+            next: 0,
+            captured: 0,
+            bytes_per_texel,
+        }
+    }
+
+    /// Get the number of staging buffers in the ring, which is the number of frames captured
+    /// before the first one is read back.
+    pub fn depth(&self) -> u32 {
+        self.slots.len() as u32
+    }
+
+    /// Get the number of frames which have been copied and not yet read back.
+    pub fn in_flight(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.in_flight.is_some())
+            .count()
+    }
+
+    /// Get the staging buffer the next frame will be copied into. It must have been emptied by
+    /// `take_oldest` first.
+    pub(crate) fn next_buffer(&self) -> &Buffer {
+        &self.slots[self.next].buffer
+    }
+
+    /// Map the staging buffer a frame of the given size has just been submitted to be copied
+    /// into, without waiting for the copy, and move on to the next buffer.
+    pub(crate) fn begin_read(&mut self, size: (u32, u32)) {
+        let slot = &mut self.slots[self.next];
+        let (sender, receiver) = flume::bounded(1);
+        let padded_bytes_per_row =
+            BenchmarkContext::padded_bytes_per_row(size.0, self.bytes_per_texel);
+        slot.buffer
+            .slice(..padded_bytes_per_row as u64 * size.1 as u64)
+            .map_async(MapMode::Read, move |r| {
+                // The reader may have stopped waiting for the mapping
+                let _ = sender.send(r);
+            });
+        slot.in_flight = Some((self.captured, size, receiver));
+        self.captured += 1;
+        self.next = (self.next + 1) % self.slots.len();
+    }
+
+    /// Read back the frame in the staging buffer the next frame will be copied into, waiting
+    /// until the GPU has finished copying it. Returns `None` if that buffer is empty, as it is
+    /// until the ring has been filled.
+    pub(crate) fn take_oldest(
+        &mut self,
+        device: &Device,
+    ) -> Result<Option<CapturedFrame>, SaveError> {
+        let slot = &mut self.slots[self.next];
+        let Some((index, size, receiver)) = slot.in_flight.take() else {
+            return Ok(None);
+        };
+        if let Err(error) = BenchmarkContext::wait_until_mapped(device, &receiver) {
+            // A timed out mapping is still pending, and must be cancelled before the next copy
+            if let SaveError::GpuTimeout(_) = error {
+                slot.buffer.unmap();
+            }
+            return Err(error);
+        }
+
+        let padded_bytes_per_row =
+            BenchmarkContext::padded_bytes_per_row(size.0, self.bytes_per_texel) as usize;
+        let unpadded_bytes_per_row = (size.0 * self.bytes_per_texel) as usize;
+        let mut data = Vec::with_capacity(unpadded_bytes_per_row * size.1 as usize);
+        BenchmarkContext::unpad_rows(
+            &slot
+                .buffer
+                .slice(..(padded_bytes_per_row * size.1 as usize) as u64)
+                .get_mapped_range(),
+            padded_bytes_per_row,
+            unpadded_bytes_per_row,
+            &mut data,
+        );
+        slot.buffer.unmap();
+        Ok(Some(CapturedFrame { index, size, data }))
+    }
+
+    /// Read back every frame still in flight, oldest first, waiting until the GPU has finished
+    /// copying each of them.
+    pub(crate) fn drain(&mut self, device: &Device) -> Result<Vec<CapturedFrame>, SaveError> {
+        let mut frames = Vec::with_capacity(self.in_flight());
+        for _ in 0..self.slots.len() {
+            frames.extend(self.take_oldest(device)?);
+            self.next = (self.next + 1) % self.slots.len();
+        }
+        Ok(frames)
+    }
+
+    /// Destroy the staging buffers, first unmapping any with a read in flight, whose mapping is
+    /// either pending or complete.
+    pub fn destroy(&self) {
+        for slot in &self.slots {
+            if slot.in_flight.is_some() {
+                slot.buffer.unmap();
+            }
+            slot.buffer.destroy();
+        }
+    }
+}
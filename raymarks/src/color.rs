@@ -0,0 +1,185 @@
+/// Convert the bits of an IEEE 754 half-precision float to a single-precision float.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        // Subnormal numbers have no implicit leading one
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Convert a single-precision float to the bits of the nearest IEEE 754 half-precision float,
+/// rounding ties to even. Values too large for half precision become infinite.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        // Keep a mantissa bit set for NaNs, so they do not become infinities
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x200 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal numbers shift the implicit leading one into the mantissa
+        return sign | round_shift(mantissa | 0x80_0000, (14 - exponent) as u32) as u16;
+    }
+    // Rounding up may carry into the exponent, which gives the next power of two or infinity
+    sign | round_shift(((exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+/// Private function to shift a value right by the given number of bits, rounding ties to even.
+fn round_shift(value: u32, shift: u32) -> u32 {
+    let shifted = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if remainder > halfway || (remainder == halfway && shifted & 1 == 1) {
+        shifted + 1
+    } else {
+        shifted
+    }
+}
+
+/// Encode a linear color channel in [0, 1] with the sRGB transfer function.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decode an sRGB-encoded color channel in [0, 1] to linear.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Decode an 8-bit sRGB value to an 8-bit linear value. Dark values lose precision.
+pub fn srgb8_to_linear8(value: u8) -> u8 {
+    (srgb_to_linear(value as f32 / 255.0) * 255.0).round() as u8
+}
+
+/// Map a value in [0, 1] to an 8-bit sRGB color on the turbo ramp, from dark blue through green
+/// to dark red, using the polynomial approximation of the ramp.
+pub fn turbo(value: f32) -> [u8; 3] {
+    let t = value.clamp(0.0, 1.0);
+    let polynomial = |c: [f32; 6]| {
+        let channel = c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * (c[4] + t * c[5]))));
+        (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+    [
+        polynomial([
+            0.135_721_38,
+            4.615_392_6,
+            -42.660_324,
+            132.131_08,
+            -152.942_4,
+            59.286_38,
+        ]),
+        polynomial([
+            0.091_402_61,
+            2.194_188_4,
+            4.842_966_6,
+            -14.185_033,
+            4.277_298_6,
+            2.829_566,
+        ]),
+        polynomial([
+            0.106_673_3,
+            12.641_946,
+            -60.582_047,
+            110.362_77,
+            -89.903_11,
+            27.348_25,
+        ]),
+    ]
+}
+
+/// Tone-map an HDR linear color channel with the Reinhard operator and encode it as an 8-bit sRGB
+/// value.
+pub fn tonemap_to_srgb8(value: f32) -> u8 {
+    let value = value.max(0.0);
+    (linear_to_srgb(value / (1.0 + value)) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_floats_round_trip() {
+        for bits in 0..=u16::MAX {
+            let value = f16_to_f32(bits);
+            if value.is_nan() {
+                continue;
+            }
+            assert_eq!(f32_to_f16(value), bits, "{:#06x} became {}", bits, value);
+        }
+    }
+
+    #[test]
+    fn half_float_subnormals() {
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x03ff), 1023.0 * 2f32.powi(-24));
+        assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(-2f32.powi(-20)), 0x8010);
+        // Values below half the smallest subnormal flush to signed zero
+        assert_eq!(f32_to_f16(2f32.powi(-26)), 0x0000);
+        assert_eq!(f32_to_f16(-2f32.powi(-26)), 0x8000);
+        // Rounding up the largest subnormal carries into the smallest normal number
+        assert_eq!(f32_to_f16(2f32.powi(-14) - 2f32.powi(-26)), 0x0400);
+    }
+
+    #[test]
+    fn half_floats_round_ties_to_even() {
+        // 1 + 2^-11 lies halfway between 1 and the next half float
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11) + 2f32.powi(-20)), 0x3c01);
+        // Halfway between zero and the smallest subnormal, and between the two smallest
+        assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_f16(3.0 * 2f32.powi(-25)), 0x0002);
+        assert_eq!(round_shift(0b1010, 2), 0b10);
+        assert_eq!(round_shift(0b1110, 2), 0b100);
+        assert_eq!(round_shift(0b1011, 2), 0b11);
+        assert_eq!(round_shift(0b1001, 2), 0b10);
+    }
+
+    #[test]
+    fn half_floats_overflow_to_infinity() {
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(65519.0), 0x7bff);
+        // Halfway to the next power of two rounds up to infinity
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(1.0e6), 0x7c00);
+        assert_eq!(f32_to_f16(-1.0e6), 0xfc00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn half_float_nans() {
+        for value in [f32::NAN, -f32::NAN, f32::from_bits(0x7f80_0001)] {
+            let bits = f32_to_f16(value);
+            assert_eq!(bits & 0x7c00, 0x7c00);
+            assert_ne!(bits & 0x3ff, 0);
+            assert!(f16_to_f32(bits).is_nan());
+        }
+    }
+}
@@ -0,0 +1,489 @@
+use crate::{
+    benchmark::BenchmarkOptions,
+    benchmarks::{run_all, BenchmarkParameters},
+    context::{BenchmarkContext, BenchmarkContextBuilder, ContextError},
+    report::BenchmarkReport,
+    resolution::Resolution,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    error::Error,
+    fmt,
+    fs::read_to_string,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use wgpu::Backends;
+
+/// Config used when none is given, embedded from `benchmarks.toml` in the crate root.
+const DEFAULT_CONFIG: &str = include_str!("../benchmarks.toml");
+
+/// Benchmarks which can be selected in a config, each of which renders with its own shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkKind {
+    /// Rasterizes bunnies with the rasterization shader.
+    BunnyRasterization,
+    /// Rasterizes bunnies with instances read from vertex, uniform and storage buffers in turn.
+    InstanceStorage,
+    /// Raymarches spheres with the raymarch shader.
+    SphereRaymarch,
+    /// Fills the render target with procedural triangles with the triangles shader.
+    TriangleFill,
+    /// Renders the raymarched spheres with both the rasterization and raymarch shaders, and
+    /// compares their timings and images.
+    SdfComparison,
+    /// Blurs the raymarched spheres with the blur post-process shader.
+    PostprocessBlur,
+    /// Draws a stencil mask, then fills where the stencil test passes, with the stencil shader.
+    StencilMask,
+    /// Searches for the largest resolution at which the raymarch shader stays within the target
+    /// frame time.
+    MaxResolution,
+    /// Blends stacked translucent quads with the rasterization shader, to measure overdraw.
+    TranslucentOverdraw,
+    /// Rasterizes bunnies into the six faces of a cube map, each a layer of the render target.
+    CubeFaces,
+    /// Draws a gradient with the gradient shader and checks its corners after reading it back.
+    SelfTest,
+    /// Rasterizes bunnies over the whole render target, then scissored to its center quarter.
+    ScissorQuarter,
+    /// Clears the render target without drawing, then rasterizes bunnies without clearing it.
+    ClearDraw,
+    /// Rasterizes bunnies from single- and half-precision vertex buffers.
+    VertexPrecision,
+    /// Raymarches and rasterizes the spheres with the passes submitted separately, then in a
+    /// single submission, and compares the time until both finish.
+    SubmissionOverlap,
+}
+
+impl BenchmarkKind {
+    /// Whether the benchmark draws the Stanford bunny model, so it cannot run without it.
+    pub fn draws_bunnies(&self) -> bool {
+        matches!(
+            self,
+            Self::BunnyRasterization
+                | Self::InstanceStorage
+                | Self::CubeFaces
+                | Self::ScissorQuarter
+                | Self::ClearDraw
+                | Self::VertexPrecision
+        )
+    }
+}
+
+/// Graphics backends which can be selected in a config or on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    All,
+    Vulkan,
+    Dx12,
+    Metal,
+    Gl,
+}
+
+impl From<Backend> for Backends {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::All => Backends::all(),
+            Backend::Vulkan => Backends::VULKAN,
+            Backend::Dx12 => Backends::DX12,
+            Backend::Metal => Backends::METAL,
+            Backend::Gl => Backends::GL,
+        }
+    }
+}
+
+/// Benchmark matrix described by a TOML file, such as `benchmarks.toml`. Fields missing from the
+/// file fall back to their values in the embedded default config, which must have every field.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchmarkConfig {
+    /// Benchmarks to run, in order.
+    #[serde(default = "default_benchmarks")]
+    pub benchmarks: Vec<BenchmarkKind>,
+    /// Render target sizes, such as `"1920x1080"`.
+    #[serde(default = "default_resolutions")]
+    pub resolutions: Vec<Resolution>,
+    /// Numbers of bunnies drawn by the rasterization, instance storage, cube faces, scissor
+    /// quarter, clear draw and vertex precision benchmarks.
+    #[serde(default = "default_counts")]
+    pub counts: Vec<u32>,
+    /// Numbers of triangles drawn by the triangle fill benchmark.
+    #[serde(default = "default_triangle_counts")]
+    pub triangle_counts: Vec<u32>,
+    /// Numbers of stacked quads blended by the translucent overdraw benchmark.
+    #[serde(default = "default_overdraw_layers")]
+    pub overdraw_layers: Vec<u32>,
+    /// Frame time in milliseconds the max resolution benchmark keeps passes within.
+    #[serde(default = "default_target_frame_ms")]
+    pub target_frame_ms: f32,
+    /// Number of measured passes per configuration.
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+    /// Number of unmeasured warmup passes per configuration.
+    #[serde(default = "default_warmup")]
+    pub warmup: usize,
+    /// Interval in milliseconds between the starts of consecutive passes, or 0 to run passes
+    /// back to back.
+    #[serde(default = "default_pace_ms")]
+    pub pace_ms: f32,
+    /// Duration in seconds to measure each configuration for instead of a number of samples, or 0
+    /// to take the configured number of samples.
+    #[serde(default = "default_soak_seconds")]
+    pub soak_seconds: f32,
+    /// Percentiles of the GPU durations to report, such as 95 and 99.
+    #[serde(default = "default_percentiles")]
+    pub percentiles: Vec<f64>,
+    /// Number of slowest GPU samples whose indices are reported.
+    #[serde(default = "default_slowest_samples")]
+    pub slowest_samples: usize,
+    /// Largest coefficient of variation of the GPU durations before a configuration is flagged
+    /// as noisy, or 0 to never flag configurations.
+    #[serde(default = "default_max_variation")]
+    pub max_variation: f64,
+    /// Whether to measure noisy configurations once more.
+    #[serde(default = "default_rerun_noisy")]
+    pub rerun_noisy: bool,
+    /// Duration in seconds each benchmark may run for before it is marked failed and aborted, or
+    /// 0 to let benchmarks run for as long as they take.
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: f32,
+    /// Graphics backend to select an adapter from.
+    #[serde(default = "default_backend")]
+    pub backend: Backend,
+}
+
+/// Error produced when a config file cannot be read or parsed, or the context it describes
+/// cannot be created.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file could not be read.
+    Io(PathBuf, io::Error),
+    /// The config file is not valid TOML, or does not match the schema.
+    Parse(PathBuf, toml::de::Error),
+    /// A value of the config file is out of range. Holds a description of the problem.
+    Invalid(PathBuf, String),
+    /// No context could be created for the configured backend.
+    Context(ContextError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, error) => {
+                write!(f, "failed to read config {}: {}", path.display(), error)
+            }
+            Self::Parse(path, error) => {
+                write!(f, "invalid config {}: {}", path.display(), error)
+            }
+            Self::Invalid(path, problem) => {
+                write!(f, "invalid config {}: {}", path.display(), problem)
+            }
+            Self::Context(error) => write!(f, "failed to create context: {}", error),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(_, error) => Some(error),
+            Self::Parse(_, error) => Some(error),
+            Self::Invalid(..) => None,
+            Self::Context(error) => Some(error),
+        }
+    }
+}
+
+impl Default for BenchmarkConfig {
+    /// The embedded config from `benchmarks.toml`.
+    fn default() -> Self {
+        toml::from_str(DEFAULT_CONFIG).expect("embedded benchmarks.toml is invalid")
+    }
+}
+
+impl BenchmarkConfig {
+    /// Load a config from a TOML file, rejecting values which are out of range.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let source =
+            read_to_string(path).map_err(|error| ConfigError::Io(path.to_path_buf(), error))?;
+        let config: Self = toml::from_str(&source)
+            .map_err(|error| ConfigError::Parse(path.to_path_buf(), error))?;
+        config
+            .check()
+            .map_err(|problem| ConfigError::Invalid(path.to_path_buf(), problem))?;
+        Ok(config)
+    }
+
+    /// Check the values of the config are in range, describing the first problem found. Configs
+    /// are checked when loaded, so this is for configs changed afterwards, such as by arguments.
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.target_frame_ms > 0.0 && self.target_frame_ms.is_finite()) {
+            return Err(format!(
+                "target_frame_ms must be a positive number of milliseconds, not {}",
+                self.target_frame_ms
+            ));
+        }
+        check_duration("pace_ms", self.pace_ms, self.pace_ms / 1000.0)?;
+        check_duration("soak_seconds", self.soak_seconds, self.soak_seconds)?;
+        check_duration(
+            "timeout_seconds",
+            self.timeout_seconds,
+            self.timeout_seconds,
+        )?;
+        Ok(())
+    }
+
+    /// Get the options controlling how many times each pass is run.
+    pub fn options(&self) -> BenchmarkOptions {
+        BenchmarkOptions {
+            samples: self.samples,
+            warmup: self.warmup,
+            pace: duration(self.pace_ms / 1000.0),
+            soak: duration(self.soak_seconds),
+            percentiles: self.percentiles.clone(),
+            slowest_samples: self.slowest_samples,
+            max_variation: (self.max_variation > 0.0).then_some(self.max_variation),
+            rerun_noisy: self.rerun_noisy,
+            timeout: duration(self.timeout_seconds),
+            ..Default::default()
+        }
+    }
+
+    /// Get the benchmarks, resolutions and counts to run.
+    pub fn parameters(&self) -> BenchmarkParameters {
+        BenchmarkParameters {
+            benchmarks: self.benchmarks.clone(),
+            resolutions: self.resolutions.clone(),
+            bunny_counts: self.counts.clone(),
+            triangle_counts: self.triangle_counts.clone(),
+            overdraw_layers: self.overdraw_layers.clone(),
+            target_frame_ms: self.target_frame_ms,
+        }
+    }
+
+    /// Get a context builder selecting an adapter from the configured backend.
+    pub fn context_builder(&self) -> BenchmarkContextBuilder {
+        BenchmarkContextBuilder::default().backends(self.backend.into())
+    }
+}
+
+/// Load the config at the given path, create a context for its backend, and run every benchmark
+/// it describes, blocking the current thread until they are complete.
+pub fn run_from_config(path: &Path) -> Result<BenchmarkReport, ConfigError> {
+    let config = BenchmarkConfig::load(path)?;
+    let mut context =
+        BenchmarkContext::new_with_sync(config.context_builder()).map_err(ConfigError::Context)?;
+    Ok(run_all(
+        &mut context,
+        &config.options(),
+        &config.parameters(),
+    ))
+}
+
+/// Private function to check the named value, converted to the given number of seconds, is a
+/// non-negative duration small enough to represent.
+fn check_duration(name: &str, value: f32, seconds: f32) -> Result<(), String> {
+    match Duration::try_from_secs_f32(seconds) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!(
+            "{} must be a finite, non-negative duration, not {}",
+            name, value
+        )),
+    }
+}
+
+/// Private function to convert a number of seconds to a duration, or `None` for 0, or for
+/// numbers out of range in configs which were changed without being checked.
+fn duration(seconds: f32) -> Option<Duration> {
+    Duration::try_from_secs_f32(seconds)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+}
+
+/// Private function to get the benchmarks of the embedded config, for configs missing them.
+fn default_benchmarks() -> Vec<BenchmarkKind> {
+    BenchmarkConfig::default().benchmarks
+}
+
+/// Private function to get the resolutions of the embedded config, for configs missing them.
+fn default_resolutions() -> Vec<Resolution> {
+    BenchmarkConfig::default().resolutions
+}
+
+/// Private function to get the counts of the embedded config, for configs missing them.
+fn default_counts() -> Vec<u32> {
+    BenchmarkConfig::default().counts
+}
+
+/// Private function to get the triangle counts of the embedded config, for configs missing them.
+fn default_triangle_counts() -> Vec<u32> {
+    BenchmarkConfig::default().triangle_counts
+}
+
+/// Private function to get the overdraw layers of the embedded config, for configs missing them.
+fn default_overdraw_layers() -> Vec<u32> {
+    BenchmarkConfig::default().overdraw_layers
+}
+
+/// Private function to get the target frame time of the embedded config, for configs missing it.
+fn default_target_frame_ms() -> f32 {
+    BenchmarkConfig::default().target_frame_ms
+}
+
+/// Private function to get the sample count of the embedded config, for configs missing it.
+fn default_samples() -> usize {
+    BenchmarkConfig::default().samples
+}
+
+/// Private function to get the warmup count of the embedded config, for configs missing it.
+fn default_warmup() -> usize {
+    BenchmarkConfig::default().warmup
+}
+
+/// Private function to get the pacing interval of the embedded config, for configs missing it.
+fn default_pace_ms() -> f32 {
+    BenchmarkConfig::default().pace_ms
+}
+
+/// Private function to get the soak duration of the embedded config, for configs missing it.
+fn default_soak_seconds() -> f32 {
+    BenchmarkConfig::default().soak_seconds
+}
+
+/// Private function to get the percentiles of the embedded config, for configs missing them.
+fn default_percentiles() -> Vec<f64> {
+    BenchmarkConfig::default().percentiles
+}
+
+/// Private function to get the slowest sample count of the embedded config, for configs missing
+/// it.
+fn default_slowest_samples() -> usize {
+    BenchmarkConfig::default().slowest_samples
+}
+
+/// Private function to get the maximum variation of the embedded config, for configs missing it.
+fn default_max_variation() -> f64 {
+    BenchmarkConfig::default().max_variation
+}
+
+/// Private function to get whether the embedded config reruns noisy configurations, for configs
+/// missing it.
+fn default_rerun_noisy() -> bool {
+    BenchmarkConfig::default().rerun_noisy
+}
+
+/// Private function to get the benchmark timeout of the embedded config, for configs missing it.
+fn default_timeout_seconds() -> f32 {
+    BenchmarkConfig::default().timeout_seconds
+}
+
+/// Private function to get the backend of the embedded config, for configs missing it.
+fn default_backend() -> Backend {
+    BenchmarkConfig::default().backend
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    /// Write the given TOML to a file named after the test and load it as a config.
+    fn load(test: &str, source: &str) -> Result<BenchmarkConfig, ConfigError> {
+        let path = std::env::temp_dir().join(format!(
+            "raymarks-config-{}-{}.toml",
+            test,
+            std::process::id()
+        ));
+        write(&path, source).unwrap();
+        BenchmarkConfig::load(&path)
+    }
+
+    #[test]
+    fn embedded_config_is_valid() {
+        BenchmarkConfig::default().check().unwrap();
+    }
+
+    #[test]
+    fn missing_fields_fall_back_to_embedded_config() {
+        let config = load("fallback", "target_frame_ms = 8.3\n").unwrap();
+        assert_eq!(config.target_frame_ms, 8.3);
+        assert_eq!(config.samples, BenchmarkConfig::default().samples);
+    }
+
+    #[test]
+    fn benchmarks_are_named_in_snake_case() {
+        let config = load(
+            "benchmarks",
+            "benchmarks = [\"sdf_comparison\", \"submission_overlap\"]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.benchmarks,
+            [
+                BenchmarkKind::SdfComparison,
+                BenchmarkKind::SubmissionOverlap
+            ]
+        );
+    }
+
+    #[test]
+    fn non_positive_target_frame_times_are_rejected() {
+        for (test, value) in [
+            ("negative", "-16.6"),
+            ("zero", "0.0"),
+            ("nan", "nan"),
+            ("inf", "inf"),
+        ] {
+            match load(test, &format!("target_frame_ms = {}\n", value)) {
+                Err(ConfigError::Invalid(_, problem)) => {
+                    assert!(problem.contains("target_frame_ms"))
+                }
+                result => panic!("expected {} to be rejected, got {:?}", value, result),
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_durations_are_rejected() {
+        for field in ["pace_ms", "soak_seconds", "timeout_seconds"] {
+            for value in ["-1.0", "nan", "inf", "1e30"] {
+                let test = format!("{}-{}", field, value);
+                match load(&test, &format!("{} = {}\n", field, value)) {
+                    Err(ConfigError::Invalid(_, problem)) => assert!(problem.contains(field)),
+                    result => panic!(
+                        "expected {} = {} to be rejected, got {:?}",
+                        field, value, result
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unchecked_durations_out_of_range_are_ignored() {
+        let config = BenchmarkConfig {
+            pace_ms: f32::INFINITY,
+            soak_seconds: f32::NAN,
+            timeout_seconds: -1.0,
+            ..Default::default()
+        };
+        assert!(config.check().is_err());
+        let options = config.options();
+        assert_eq!(options.pace, None);
+        assert_eq!(options.soak, None);
+        assert_eq!(options.timeout, None);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(matches!(
+            load("unknown", "target_fps = 60\n"),
+            Err(ConfigError::Parse(..))
+        ));
+    }
+}
@@ -1,83 +1,4709 @@
-use crate::shaders::Shader;
-use log::info;
+#[cfg(feature = "window")]
+use crate::preview::Preview;
+use crate::{
+    bind_groups::{BindGroupCache, BindGroupCacheStats, BoundResource, LayoutSource},
+    camera::{Camera, CameraBuffer},
+    capture::{CaptureRing, CapturedFrame},
+    color::{
+        f16_to_f32, linear_to_srgb, srgb8_to_linear8, srgb_to_linear, tonemap_to_srgb8, turbo,
+    },
+    diff::{diff_images, load_png_rgba8, CompareError, DiffOptions, DiffResult},
+    mesh::{
+        DrawMode, IndirectBuffer, Instance, InstanceBuffer, InstanceStorage, MeshBuffers, Vertex,
+        VertexPrecision,
+    },
+    pass_parameters::{PassParameters, PassParametersBuffer},
+    pipeline_statistics::{PipelineStatistics, PipelineStatisticsQueries},
+    resolution::Resolution,
+    scene::Scene,
+    shaders::{check_workgroup_size, substitute_constant, validate_wgsl, Shader, ShaderError},
+    texture::{InputTexture, TextureError, TextureId},
+    timestamps::TimestampQueries,
+};
+use flume::{Receiver, TryRecvError};
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    ExtendedColorType, ImageEncoder,
+};
+use log::{debug, error, info, warn};
 use std::{
-    fs::File,
-    io::Write,
+    collections::HashMap,
+    env::var_os,
+    error::Error,
+    fmt,
+    fs::{create_dir_all, remove_file, File},
+    io::{self, BufWriter, Write},
     mem::replace,
+    ops::Range,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 use wgpu::*;
 
-/// Reusable device info and utilities for all benchmarks.
-pub struct BenchmarkContext {
-    device: Device,
-    queue: Queue,
-    commands: CommandEncoder,
-    render_target: Texture,
-    output_staging_buffer: Buffer,
-}
+/// File format of saved images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Lossless PNG.
+    #[default]
+    Png,
+    /// Lossy JPEG with the given quality from 1 to 100. The alpha channel is dropped.
+    Jpeg { quality: u8 },
+    /// Lossless WebP.
+    WebP,
+}
+
+impl ImageFormat {
+    /// Get the file extension of images saved in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg { .. } => "jpg",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+/// Transfer function of the color data written to saved images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorEncoding {
+    /// sRGB-encoded data, as stored in 8-bit render targets, tagged with an sRGB chunk in PNGs.
+    #[default]
+    Srgb,
+    /// Linear data, tagged with a gamma of 1.0 in PNGs. Dark colors lose precision in 8 bits.
+    Linear,
+}
+
+/// Error produced when the render target cannot be resized.
+#[derive(Debug)]
+pub enum ResizeError {
+    /// A side of the requested size exceeds the device's maximum 2D texture dimension.
+    TextureTooLarge {
+        size: (u32, u32),
+        max_dimension: u32,
+    },
+    /// The staging buffer for the requested size, in bytes, exceeds the device's maximum buffer
+    /// size.
+    BufferTooLarge { size: u64, max_size: u64 },
+}
+
+impl fmt::Display for ResizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TextureTooLarge {
+                size,
+                max_dimension,
+            } => write!(
+                f,
+                "render target size {}x{} exceeds the device limit max_texture_dimension_2d of {}",
+                size.0, size.1, max_dimension
+            ),
+            Self::BufferTooLarge { size, max_size } => write!(
+                f,
+                "staging buffer of {} bytes exceeds the device limit max_buffer_size of {} bytes",
+                size, max_size
+            ),
+        }
+    }
+}
+
+impl Error for ResizeError {}
+
+/// Error produced when an image cannot be read back from the GPU or saved to disk.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The image directory or file could not be written.
+    Io(io::Error),
+    /// The image could not be encoded as a PNG.
+    Png(png::EncodingError),
+    /// The image could not be encoded as a JPEG or WebP.
+    Encode(image::ImageError),
+    /// The staging buffer could not be mapped for reading.
+    Map(BufferAsyncError),
+    /// The texture is multisampled, so it cannot be copied to a staging buffer.
+    Multisampled,
+    /// The adapter cannot copy depth textures to buffers, such as on OpenGL.
+    DepthCopyUnsupported,
+    /// There is no color attachment with the given index.
+    NoAttachment(usize),
+    /// The render target has no mip level with the given index.
+    NoMipLevel(u32),
+    /// The render target has no array layer with the given index.
+    NoArrayLayer(u32),
+    /// The region with the given x, y, width and height is empty or extends past the render
+    /// target.
+    RegionOutOfBounds(u32, u32, u32, u32),
+    /// The GPU never reported whether the staging buffer was mapped.
+    MapCallbackDropped,
+    /// The GPU did not finish with the staging buffer, or a submission, within the given time, so
+    /// it may be hung.
+    GpuTimeout(Duration),
+    /// Frames were captured without a capture ring, which is created by `set_capture_depth`.
+    NoCaptureRing,
+    /// Step counts were saved before any raymarch pass recorded them, which is enabled by
+    /// `set_record_steps`.
+    NoStepCounts,
+    /// The shader of a pass needed to read the texture back could not be loaded.
+    Shader(ShaderError),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to write image: {}", error),
+            Self::Png(error) => write!(f, "failed to encode image: {}", error),
+            Self::Encode(error) => write!(f, "failed to encode image: {}", error),
+            Self::Map(error) => write!(f, "failed to map staging buffer: {}", error),
+            Self::MapCallbackDropped => write!(f, "staging buffer map callback was dropped"),
+            Self::GpuTimeout(timeout) => {
+                write!(f, "GPU did not finish within {:?}, it may be hung", timeout)
+            }
+            Self::Multisampled => write!(f, "multisampled textures cannot be read back"),
+            Self::DepthCopyUnsupported => {
+                write!(f, "the adapter cannot copy depth textures to buffers")
+            }
+            Self::NoAttachment(index) => write!(f, "there is no color attachment {}", index),
+            Self::NoMipLevel(level) => write!(f, "the render target has no mip level {}", level),
+            Self::NoArrayLayer(layer) => {
+                write!(f, "the render target has no array layer {}", layer)
+            }
+            Self::RegionOutOfBounds(x, y, width, height) => write!(
+                f,
+                "the region {}x{} at ({}, {}) is empty or extends past the render target",
+                width, height, x, y
+            ),
+            Self::NoCaptureRing => write!(f, "no capture ring has been created"),
+            Self::NoStepCounts => write!(f, "no raymarch pass has recorded step counts"),
+            Self::Shader(error) => write!(f, "failed to read texture back: {}", error),
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Png(error) => Some(error),
+            Self::Encode(error) => Some(error),
+            Self::Map(error) => Some(error),
+            Self::Shader(error) => Some(error),
+            Self::MapCallbackDropped
+            | Self::GpuTimeout(_)
+            | Self::Multisampled
+            | Self::DepthCopyUnsupported
+            | Self::NoAttachment(_)
+            | Self::NoMipLevel(_)
+            | Self::NoArrayLayer(_)
+            | Self::RegionOutOfBounds(..)
+            | Self::NoCaptureRing
+            | Self::NoStepCounts => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ShaderError> for SaveError {
+    fn from(error: ShaderError) -> Self {
+        Self::Shader(error)
+    }
+}
+
+impl From<png::EncodingError> for SaveError {
+    fn from(error: png::EncodingError) -> Self {
+        Self::Png(error)
+    }
+}
+
+impl From<image::ImageError> for SaveError {
+    fn from(error: image::ImageError) -> Self {
+        Self::Encode(error)
+    }
+}
+
+impl From<BufferAsyncError> for SaveError {
+    fn from(error: BufferAsyncError) -> Self {
+        Self::Map(error)
+    }
+}
+
+/// Error produced when a frame of a sequence cannot be rendered or saved.
+#[derive(Debug)]
+pub enum SequenceError {
+    /// The pass rendering a frame failed to build its pipeline.
+    Shader(ShaderError),
+    /// A rendered frame could not be saved.
+    Save(SaveError),
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Shader(error) => write!(f, "failed to render frame: {}", error),
+            Self::Save(error) => write!(f, "failed to save frame: {}", error),
+        }
+    }
+}
+
+impl Error for SequenceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Shader(error) => Some(error),
+            Self::Save(error) => Some(error),
+        }
+    }
+}
+
+impl From<ShaderError> for SequenceError {
+    fn from(error: ShaderError) -> Self {
+        Self::Shader(error)
+    }
+}
+
+impl From<SaveError> for SequenceError {
+    fn from(error: SaveError) -> Self {
+        Self::Save(error)
+    }
+}
+
+/// Error produced when a benchmark context cannot be created.
+#[derive(Debug)]
+pub enum ContextError {
+    /// No adapter matched the builder's options, even when falling back to a software adapter.
+    NoAdapter { preference: PowerPreference },
+    /// The adapter could not provide a device with the requested features and limits.
+    RequestDevice(RequestDeviceError),
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoAdapter { preference } => write!(
+                f,
+                "no adapter found with power preference {:?}, including fallback adapters",
+                preference
+            ),
+            Self::RequestDevice(error) => write!(f, "failed to request device: {}", error),
+        }
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoAdapter { .. } => None,
+            Self::RequestDevice(error) => Some(error),
+        }
+    }
+}
+
+impl From<RequestDeviceError> for ContextError {
+    fn from(error: RequestDeviceError) -> Self {
+        Self::RequestDevice(error)
+    }
+}
+
+/// Problem found by validating the context before a benchmark run.
+#[derive(Debug)]
+pub enum SetupError {
+    /// A built-in shader could not be loaded, or its source is invalid.
+    Shader(Shader, ShaderError),
+    /// Features requested through the builder which the adapter does not support.
+    MissingFeatures(Features),
+    /// The render target format cannot be used with the usages passes need on this adapter.
+    UnsupportedFormat(TextureFormat),
+    /// The render target does not fit within the device's limits.
+    Resize(ResizeError),
+    /// The raymarch workgroup has more invocations than the device's limit.
+    WorkgroupTooLarge {
+        invocations: u32,
+        max_invocations: u32,
+    },
+    /// The image directory could not be created or written to.
+    ImageDirectory(PathBuf, io::Error),
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Shader(shader, error) => write!(
+                f,
+                "shader {} failed to compile: {}",
+                shader.source_file().display(),
+                error
+            ),
+            Self::MissingFeatures(features) => {
+                write!(f, "adapter does not support features {:?}", features)
+            }
+            Self::UnsupportedFormat(format) => write!(
+                f,
+                "render target format {:?} cannot be rendered to and used as storage",
+                format
+            ),
+            Self::Resize(error) => write!(f, "{}", error),
+            Self::WorkgroupTooLarge {
+                invocations,
+                max_invocations,
+            } => write!(
+                f,
+                "raymarch workgroup of {} invocations exceeds the limit of {}",
+                invocations, max_invocations
+            ),
+            Self::ImageDirectory(path, error) => write!(
+                f,
+                "image directory {} is not writable: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+impl Error for SetupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Shader(_, error) => Some(error),
+            Self::Resize(error) => Some(error),
+            Self::ImageDirectory(_, error) => Some(error),
+            Self::MissingFeatures(_)
+            | Self::UnsupportedFormat(_)
+            | Self::WorkgroupTooLarge { .. } => None,
+        }
+    }
+}
+
+/// CPU time taken by the steps of a submission, which together with the GPU time of its passes
+/// break down the latency of a pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubmitTiming {
+    /// Time taken to finish encoding the recorded commands into a command buffer.
+    pub encode: Duration,
+    /// Time taken by the call submitting the command buffer to the queue.
+    pub submit: Duration,
+}
+
+/// A submission of recorded passes returned by `BenchmarkContext::submit`, which
+/// `BenchmarkContext::wait_for_submission` can wait on without blocking indefinitely.
+#[derive(Clone, Debug)]
+pub struct Submission {
+    /// Index of the submission on the queue.
+    pub index: SubmissionIndex,
+    /// Set once the GPU has finished the submission and every one before it.
+    finished: Arc<AtomicBool>,
+}
+
+impl Submission {
+    /// Whether the GPU has finished the submission, as of the last time the device was polled.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+/// Options for selecting the backend, adapter, and features used by a benchmark context.
+#[derive(Clone, Debug)]
+pub struct BenchmarkContextBuilder {
+    backends: Backends,
+    power_preference: PowerPreference,
+    force_fallback: bool,
+    features: Features,
+    target_format: TextureFormat,
+    target_usage: TextureUsages,
+    target_view_formats: Vec<TextureFormat>,
+    image_directory: Option<PathBuf>,
+    reference_directory: Option<PathBuf>,
+    #[cfg(feature = "window")]
+    preview: bool,
+}
+
+impl Default for BenchmarkContextBuilder {
+    fn default() -> Self {
+        Self {
+            backends: Backends::all(),
+            power_preference: PowerPreference::HighPerformance,
+            force_fallback: false,
+            features: BenchmarkContext::DESIRED_FEATURES,
+            target_format: TextureFormat::Rgba8UnormSrgb,
+            target_usage: TextureUsages::empty(),
+            target_view_formats: Vec::new(),
+            image_directory: None,
+            reference_directory: None,
+            #[cfg(feature = "window")]
+            preview: false,
+        }
+    }
+}
+
+impl BenchmarkContextBuilder {
+    /// Restrict the backends (Vulkan, DX12, Metal, etc.) an adapter may be selected from.
+    pub fn backends(mut self, backends: Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    /// Enumerate every adapter available on the builder's backends, so benchmarks can be run on
+    /// each of them with `BenchmarkContext::new_on_adapter`.
+    pub fn enumerate_adapters(&self) -> Vec<Adapter> {
+        let instance = wgpu::Instance::new(InstanceDescriptor {
+            backends: self.backends,
+            ..Default::default()
+        });
+        instance.enumerate_adapters(self.backends)
+    }
+
+    /// Choose between high-performance and low-power adapters.
+    pub fn power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Force the use of a fallback (software) adapter.
+    pub fn force_fallback(mut self, force_fallback: bool) -> Self {
+        self.force_fallback = force_fallback;
+        self
+    }
+
+    /// Set the desired optional features, which are requested if the adapter supports them.
+    pub fn features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Set the format the render target is created with, which must be one of
+    /// `BenchmarkContext::TARGET_FORMATS`. sRGB targets are stored as linear and viewed as sRGB,
+    /// which downlevel adapters without view formats cannot do, so they need a linear format.
+    pub fn target_format(mut self, format: TextureFormat) -> Self {
+        self.target_format = format;
+        self
+    }
+
+    /// Add usages to the render target, on top of those needed by passes and readback. Passes
+    /// already need `RENDER_ATTACHMENT`, `STORAGE_BINDING`, `TEXTURE_BINDING` and `COPY_SRC`.
+    pub fn target_usage(mut self, usage: TextureUsages) -> Self {
+        self.target_usage = usage;
+        self
+    }
+
+    /// Add formats the render target may be viewed as, on top of the target format. These may
+    /// only differ from the target format by their sRGB suffix, such as viewing an sRGB target as
+    /// linear.
+    pub fn target_view_formats(mut self, view_formats: Vec<TextureFormat>) -> Self {
+        self.target_view_formats = view_formats;
+        self
+    }
+
+    /// Set the directory images are saved to, instead of the default image directory.
+    pub fn image_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.image_directory = Some(directory.into());
+        self
+    }
+
+    /// Set the directory reference images are compared against, instead of the default
+    /// reference directory.
+    pub fn reference_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.reference_directory = Some(directory.into());
+        self
+    }
+
+    /// Open a window which the render target is presented to after each submit.
+    #[cfg(feature = "window")]
+    pub fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+}
+
+/// Reusable device info and utilities for all benchmarks.
+///
+/// The async methods are the primitives, and the `_sync` variants block on them with `pollster`.
+/// Inside an existing async runtime, await the async methods directly rather than nesting
+/// `block_on`. The GPU is not polled in the background, so readbacks drive `device.poll`
+/// themselves until the GPU finishes or a timeout passes, blocking the thread running the
+/// future. The context is `Send` and `Sync` unless the `window` feature is enabled, since event
+/// loops must stay on their thread.
+///
+/// Every pass is submitted to the device's single queue, so compute and rasterization passes run
+/// one after another. wgpu exposes one queue per device on every backend, even where the
+/// hardware has separate compute queues, so passes cannot be overlapped on an async compute
+/// queue. Whether a driver overlaps independent passes within a queue is up to the driver, which
+/// `benchmarks::submission_overlap` measures.
+pub struct BenchmarkContext {
+    adapter: Adapter,
+    adapter_info: AdapterInfo,
+    limits: Limits,
+    requested_features: Features,
+    device: Device,
+    device_lost: Arc<Mutex<Option<String>>>,
+    abort: Arc<AtomicBool>,
+    queue: Queue,
+    commands: CommandEncoder,
+    render_target: Texture,
+    multisampled_target: Option<Texture>,
+    depth_texture: Texture,
+    stencil_texture: Option<Texture>,
+    target_format: TextureFormat,
+    target_usage: TextureUsages,
+    target_view_formats: Vec<TextureFormat>,
+    gbuffer_formats: Vec<TextureFormat>,
+    gbuffer_targets: Vec<Texture>,
+    sample_count: u32,
+    supersample: u32,
+    mip_level_count: u32,
+    target_mip_level: u32,
+    layer_count: u32,
+    target_layer: u32,
+    output_staging_buffer: Buffer,
+    capture_ring: Option<CaptureRing>,
+    timestamp_queries: Option<TimestampQueries>,
+    pipeline_statistics_queries: Option<PipelineStatisticsQueries>,
+    scene: Vec<(MeshBuffers, InstanceBuffer)>,
+    indirect_buffer: Option<IndirectBuffer>,
+    generated_indirect_buffer: Option<(u32, IndirectBuffer)>,
+    camera: Camera,
+    camera_buffer: CameraBuffer,
+    pass_parameters: PassParameters,
+    pass_parameters_buffer: PassParametersBuffer,
+    textures: Vec<InputTexture>,
+    texture_filter: FilterMode,
+    stencil_face: StencilFaceState,
+    stencil_reference: u32,
+    clear_color: Color,
+    clear_target: bool,
+    draw_mode: DrawMode,
+    instance_storage: InstanceStorage,
+    vertex_precision: VertexPrecision,
+    half_vertices_supported: Option<bool>,
+    polygon_mode: PolygonMode,
+    topology: PrimitiveTopology,
+    cull_mode: Option<Face>,
+    front_face: FrontFace,
+    scissor_rect: Option<(u32, u32, u32, u32)>,
+    blend_state: Option<BlendState>,
+    opacity: f32,
+    output_encoding: ColorEncoding,
+    image_directory: PathBuf,
+    reference_directory: PathBuf,
+    filename_template: String,
+    last_pass: Option<(Shader, Option<u32>)>,
+    batch_size: u32,
+    pending_passes: u32,
+    debug_label: String,
+    frame: u64,
+    last_submit_timing: Option<SubmitTiming>,
+    rasterization_pipeline: Option<RenderPipeline>,
+    raymarch_pipeline: Option<(bool, ComputePipeline)>,
+    raymarch_shader: Shader,
+    triangles_pipeline: Option<(u32, RenderPipeline)>,
+    gradient_pipeline: Option<((u32, u32), RenderPipeline)>,
+    mipmap_pipeline: Option<RenderPipeline>,
+    postprocess_pipeline: Option<(Shader, ComputePipeline)>,
+    postprocess_input: Option<Texture>,
+    record_steps: bool,
+    step_texture: Option<Texture>,
+    stencil_pipelines: Option<(RenderPipeline, RenderPipeline)>,
+    linear_sampler: Sampler,
+    bind_groups: BindGroupCache,
+    #[cfg(feature = "window")]
+    preview: Option<Preview>,
+}
+
+impl BenchmarkContext {
+    /// Width and height of the workgroups dispatched by the raymarch shader, which must match
+    /// `WORKGROUP_SIZE` in `raymarch.wgsl`, as checked when building the pipeline. Dispatches
+    /// round up, so sizes which are not a multiple of it are fully covered.
+    const RAYMARCH_WORKGROUP_SIZE: u32 = 8;
+
+    /// Width and height of the workgroups dispatched by post-process shaders, which must match
+    /// `WORKGROUP_SIZE` in each of them, as checked when building their pipelines.
+    const POSTPROCESS_WORKGROUP_SIZE: u32 = 8;
+
+    /// Maximum number of color attachments written by rasterization passes, matching the outputs
+    /// of the G-buffer shader: albedo, normal and position.
+    pub const MAX_COLOR_ATTACHMENTS: usize = 3;
+
+    /// Formats the render target can be set to. 8-bit targets are rendered to as sRGB, while
+    /// floating-point targets hold linear HDR colors which are tone-mapped when saved.
+    pub const TARGET_FORMATS: [TextureFormat; 2] =
+        [TextureFormat::Rgba8UnormSrgb, TextureFormat::Rgba16Float];
+
+    /// Format of the depth texture used by rasterization passes.
+    const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+    /// Format of the depth-stencil texture used by stencil passes.
+    const STENCIL_FORMAT: TextureFormat = TextureFormat::Depth24PlusStencil8;
+
+    /// Format of the texture raymarch passes write the step count of each pixel to.
+    const STEP_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+    /// Declaration of the storage texture type compute shaders write the render target with.
+    /// Storage texture formats are part of the shader source, so this declaration is replaced
+    /// with one of the target's format before compiling.
+    const OUTPUT_TEXTURE_DECLARATION: &'static str =
+        "alias OutputTexture = texture_storage_2d<rgba8unorm, write>;";
+
+    /// Maximum number of steps marched along each ray, which must match `MAX_STEPS` in
+    /// `raymarch.wgsl`.
+    pub const RAYMARCH_MAX_STEPS: u32 = 128;
+
+    /// Template of the paths images are saved to, relative to the image directory, when no other
+    /// template is set.
+    pub const DEFAULT_FILENAME_TEMPLATE: &'static str = "{name}_{w}x{h}.{ext}";
+
+    /// Largest uniform binding rasterization shaders read instances from, in bytes. This is the
+    /// limit every adapter supports, and bounds the padding of instance buffers for small scenes.
+    const MAX_UNIFORM_INSTANCE_BYTES: u32 = 64 * 1024;
+
+    /// Number of triangles in the disc drawn as a mask by stencil passes.
+    const STENCIL_MASK_SEGMENTS: u32 = 64;
+
+    /// Size in bytes of the output staging buffer above which PNGs are saved in strips.
+    const STREAMED_SAVE_THRESHOLD: BufferAddress = 64 * 1024 * 1024;
+
+    /// Time to wait for the GPU to finish with a staging buffer before giving up on reading it.
+    const POLL_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Time to sleep between polls of the device while waiting for the GPU.
+    const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+    /// Number of rows read back at a time when saving PNGs in strips.
+    const STREAMED_ROWS_PER_STRIP: u32 = 256;
+
+    /// Optional features used by benchmarks when the adapter supports them.
+    pub const DESIRED_FEATURES: Features = Features::TIMESTAMP_QUERY
+        .union(Features::PIPELINE_STATISTICS_QUERY)
+        .union(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        .union(Features::POLYGON_MODE_LINE)
+        .union(Features::PUSH_CONSTANTS)
+        .union(Features::INDIRECT_FIRST_INSTANCE);
+
+    /// Create a new benchmark context, requesting a high-perfomance device which has all of the
+    /// `DESIRED_FEATURES` supported by the adapter.
+    pub async fn new() -> Result<Self, ContextError> {
+        Self::with_features(Self::DESIRED_FEATURES).await
+    }
+
+    /// Create a new benchmark context, requesting a high-performance device which has the
+    /// intersection of the desired features and the features supported by the adapter.
+    pub async fn with_features(desired_features: Features) -> Result<Self, ContextError> {
+        Self::new_with(BenchmarkContextBuilder::default().features(desired_features)).await
+    }
+
+    /// Create a new benchmark context, requesting a device from an adapter matching the builder's
+    /// options. If no adapter matches, a fallback (software) adapter is requested instead, so
+    /// machines without a suitable GPU can still run benchmarks.
+    pub async fn new_with(builder: BenchmarkContextBuilder) -> Result<Self, ContextError> {
+        let instance = wgpu::Instance::new(InstanceDescriptor {
+            backends: builder.backends,
+            ..Default::default()
+        });
+
+        // The preview window is optional, so continue headless if it cannot be opened
+        #[cfg(feature = "window")]
+        let preview = builder.preview.then(|| Preview::new(&instance)).flatten();
+        #[cfg(feature = "window")]
+        let compatible_surface = preview.as_ref().map(Preview::surface);
+        #[cfg(not(feature = "window"))]
+        let compatible_surface = None;
+
+        let mut options = RequestAdapterOptions {
+            power_preference: builder.power_preference,
+            compatible_surface,
+            force_fallback_adapter: builder.force_fallback,
+        };
+        let mut adapter = instance.request_adapter(&options).await;
+        if adapter.is_none() && !options.force_fallback_adapter {
+            warn!("No adapter matched the requested options, trying a fallback adapter.");
+            options.force_fallback_adapter = true;
+            adapter = instance.request_adapter(&options).await;
+        }
+        let adapter = adapter.ok_or(ContextError::NoAdapter {
+            preference: builder.power_preference,
+        })?;
+        let context = Self::from_adapter(adapter, builder, options.force_fallback_adapter).await?;
+        #[cfg(feature = "window")]
+        let context = context.with_preview(preview);
+        Ok(context)
+    }
+
+    /// Private method to attach the given preview window, if any, which rendered frames are
+    /// presented to on submission.
+    #[cfg(feature = "window")]
+    fn with_preview(mut self, preview: Option<Preview>) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    /// Create a new benchmark context on the given adapter, such as one of those enumerated by
+    /// `BenchmarkContextBuilder::enumerate_adapters`, with the builder's features and target
+    /// options. The builder's adapter selection is ignored, and no preview window is opened.
+    pub async fn new_on_adapter(
+        adapter: Adapter,
+        builder: BenchmarkContextBuilder,
+    ) -> Result<Self, ContextError> {
+        let fallback = adapter.get_info().device_type == DeviceType::Cpu;
+        Self::from_adapter(adapter, builder, fallback).await
+    }
+
+    /// Create a new benchmark context on the given adapter, blocking the current thread until the
+    /// GPU is ready.
+    pub fn new_on_adapter_sync(
+        adapter: Adapter,
+        builder: BenchmarkContextBuilder,
+    ) -> Result<Self, ContextError> {
+        pollster::block_on(Self::new_on_adapter(adapter, builder))
+    }
+
+    /// Private function to request a device from the given adapter and create the context's
+    /// resources on it, given whether the adapter is a fallback (software) adapter. The context
+    /// has no preview window.
+    async fn from_adapter(
+        adapter: Adapter,
+        builder: BenchmarkContextBuilder,
+        fallback: bool,
+    ) -> Result<Self, ContextError> {
+        // Desired features are optional, so only request those the adapter supports
+        let mut required_features = adapter.features() & builder.features;
+        // Push constants are only useful if the pass parameters fit in them
+        if adapter.limits().max_push_constant_size < PassParametersBuffer::SIZE {
+            required_features.remove(Features::PUSH_CONSTANTS);
+        }
+        let max_push_constant_size = if required_features.contains(Features::PUSH_CONSTANTS) {
+            PassParametersBuffer::SIZE
+        } else {
+            0
+        };
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("raymarks.device"),
+                    required_features,
+                    // Request everything the adapter offers, so large targets are not refused by
+                    // the conservative default limits
+                    required_limits: Limits {
+                        max_push_constant_size,
+                        ..adapter.limits()
+                    },
+                    ..Default::default()
+                },
+                None,
+            )
+            .await?;
+
+        // Record why the device was lost, so benchmarks can stop instead of timing nothing
+        let device_lost = Arc::new(Mutex::new(None));
+        let lost = device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // The device is also reported lost when the context drops it
+            if matches!(reason, DeviceLostReason::Dropped) {
+                return;
+            }
+            error!("Device lost ({:?}): {}", reason, message);
+            *lost.lock().unwrap() = Some(format!("{:?}: {}", reason, message));
+        });
+
+        let commands = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some(&Self::encoder_label("", 0)),
+        });
+
+        // Create default render target of size 1024x1024
+        let target_format = if Self::TARGET_FORMATS.contains(&builder.target_format) {
+            builder.target_format
+        } else {
+            warn!(
+                "Render target format {:?} is unsupported, using {:?} instead.",
+                builder.target_format,
+                Self::TARGET_FORMATS[0]
+            );
+            Self::TARGET_FORMATS[0]
+        };
+        let render_target = Self::render_target(
+            &device,
+            "render_target",
+            Extent3d {
+                width: 1024,
+                height: 1024,
+                depth_or_array_layers: 1,
+            },
+            target_format,
+            1,
+            builder.target_usage,
+            &builder.target_view_formats,
+        );
+        let depth_texture = Self::depth_texture(&device, (1024, 1024), 1, 1);
+        let output_staging_buffer = Self::output_staging_buffer(
+            &device,
+            (1024, 1024),
+            Self::bytes_per_texel(target_format),
+            1,
+        );
+        let timestamp_queries = TimestampQueries::new(&device);
+        let pipeline_statistics_queries = PipelineStatisticsQueries::new(&device);
+
+        let camera = Camera::default();
+        let camera_buffer = CameraBuffer::new(&device, &camera);
+        let pass_parameters = PassParameters::default();
+        let pass_parameters_buffer = PassParametersBuffer::new(&device, &pass_parameters);
+        // Shared by passes which downsample or filter the render target
+        let linear_sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("raymarks.linear_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let adapter_info = adapter.get_info();
+        let adapter_kind = if fallback { "fallback" } else { "hardware" };
+        info!(
+            "Context initialized on {} adapter {}. GPU adapter info: {:?}",
+            adapter_kind, adapter_info.name, adapter_info
+        );
+        info!("Enabled device features: {:?}", device.features());
+        let limits = device.limits();
+        info!(
+            "Device limits: max_texture_dimension_2d {}, max_buffer_size {}.",
+            limits.max_texture_dimension_2d, limits.max_buffer_size
+        );
+
+        Ok(Self {
+            adapter,
+            adapter_info,
+            limits,
+            requested_features: builder.features,
+            device,
+            device_lost,
+            abort: Arc::new(AtomicBool::new(false)),
+            queue,
+            commands,
+            render_target,
+            multisampled_target: None,
+            depth_texture,
+            stencil_texture: None,
+            target_format,
+            target_usage: builder.target_usage,
+            target_view_formats: builder.target_view_formats,
+            gbuffer_formats: Vec::new(),
+            gbuffer_targets: Vec::new(),
+            sample_count: 1,
+            supersample: 1,
+            mip_level_count: 1,
+            target_mip_level: 0,
+            layer_count: 1,
+            target_layer: 0,
+            output_staging_buffer,
+            capture_ring: None,
+            timestamp_queries,
+            pipeline_statistics_queries,
+            scene: Vec::new(),
+            indirect_buffer: None,
+            generated_indirect_buffer: None,
+            camera,
+            camera_buffer,
+            pass_parameters,
+            pass_parameters_buffer,
+            textures: Vec::new(),
+            texture_filter: FilterMode::Linear,
+            stencil_face: StencilFaceState {
+                compare: CompareFunction::Equal,
+                fail_op: StencilOperation::Keep,
+                depth_fail_op: StencilOperation::Keep,
+                pass_op: StencilOperation::Keep,
+            },
+            stencil_reference: 1,
+            clear_color: Color::BLACK,
+            clear_target: true,
+            draw_mode: DrawMode::Instanced,
+            instance_storage: InstanceStorage::Vertex,
+            vertex_precision: VertexPrecision::Single,
+            half_vertices_supported: None,
+            polygon_mode: PolygonMode::Fill,
+            topology: PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            front_face: FrontFace::Ccw,
+            scissor_rect: None,
+            blend_state: None,
+            opacity: 1.0,
+            output_encoding: ColorEncoding::Srgb,
+            image_directory: builder
+                .image_directory
+                .unwrap_or_else(Self::default_image_directory),
+            reference_directory: builder
+                .reference_directory
+                .unwrap_or_else(Self::default_reference_directory),
+            filename_template: Self::DEFAULT_FILENAME_TEMPLATE.to_string(),
+            last_pass: None,
+            batch_size: 1,
+            pending_passes: 0,
+            debug_label: String::new(),
+            frame: 0,
+            last_submit_timing: None,
+            rasterization_pipeline: None,
+            raymarch_pipeline: None,
+            raymarch_shader: Shader::Raymarch,
+            triangles_pipeline: None,
+            gradient_pipeline: None,
+            mipmap_pipeline: None,
+            postprocess_pipeline: None,
+            postprocess_input: None,
+            record_steps: false,
+            step_texture: None,
+            stencil_pipelines: None,
+            linear_sampler,
+            bind_groups: BindGroupCache::default(),
+            #[cfg(feature = "window")]
+            preview: None,
+        })
+    }
+
+    /// Create a new benchmark context, blocking the current thread until the GPU is ready.
+    pub fn new_sync() -> Result<Self, ContextError> {
+        pollster::block_on(Self::new())
+    }
+
+    /// Create a new benchmark context from the builder's options, blocking the current thread
+    /// until the GPU is ready.
+    pub fn new_with_sync(builder: BenchmarkContextBuilder) -> Result<Self, ContextError> {
+        pollster::block_on(Self::new_with(builder))
+    }
+
+    /// Get the name, backend, driver and type of the adapter the device was created on.
+    pub fn adapter_info(&self) -> &AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// Get the limits of the device, which bound the sizes of render targets and buffers.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Get the number of bytes allocated by the context for render targets, the depth texture,
+    /// staging buffers, and scene and camera buffers. This is computed from the sizes of
+    /// the resources, so it does not include driver overhead or alignment.
+    pub fn allocated_bytes(&self) -> u64 {
+        let texture_bytes = [Some(&self.render_target), self.multisampled_target.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(&self.gbuffer_targets)
+            .chain([&self.depth_texture])
+            .chain(&self.stencil_texture)
+            .chain(&self.postprocess_input)
+            .chain(&self.step_texture)
+            .map(Self::texture_bytes)
+            .sum::<u64>();
+        let buffer_bytes = self.output_staging_buffer.size()
+            + self
+                .scene
+                .iter()
+                .map(|(mesh, instances)| mesh.size() + instances.size())
+                .sum::<u64>()
+            + self
+                .indirect_buffer
+                .iter()
+                .chain(
+                    self.generated_indirect_buffer
+                        .as_ref()
+                        .map(|(_, buffer)| buffer),
+                )
+                .map(IndirectBuffer::size)
+                .sum::<u64>()
+            + self.camera_buffer.size()
+            + self.pass_parameters_buffer.size();
+        let input_texture_bytes = self.textures.iter().map(InputTexture::size).sum::<u64>();
+        texture_bytes + buffer_bytes + input_texture_bytes
+    }
+
+    /// Get the device's own report of its memory allocations as the total allocated and reserved
+    /// bytes, where the backend supports it.
+    #[cfg(feature = "allocator-report")]
+    pub fn allocator_report(&self) -> Option<(u64, u64)> {
+        self.device
+            .generate_allocator_report()
+            .map(|report| (report.total_allocated_bytes, report.total_reserved_bytes))
+    }
+
+    /// Get the optional features enabled on the device, so benchmarks can branch on availability.
+    pub fn features(&self) -> Features {
+        self.device.features()
+    }
+
+    /// Get whether passes are timed on the GPU, which needs timestamp queries. Measurements of
+    /// passes which are not timed have no GPU statistics.
+    pub fn times_passes(&self) -> bool {
+        self.timestamp_queries.is_some()
+    }
+
+    /// Check that a benchmark run can succeed without rendering anything. Every built-in shader
+    /// is compiled with naga, the requested features, target format and limits are checked
+    /// against the adapter, and the image directory is created and written to. Returns every
+    /// problem found, rather than only the first.
+    pub fn validate(&self) -> Result<(), Vec<SetupError>> {
+        let mut errors = Vec::new();
+
+        for shader in Shader::BUILT_IN {
+            let result = shader
+                .load_source_preprocessed()
+                .and_then(|source| validate_wgsl(&source, self.features()));
+            if let Err(error) = result {
+                errors.push(SetupError::Shader(shader, error));
+            }
+        }
+
+        let missing_features = self.requested_features - self.features();
+        if !missing_features.is_empty() {
+            errors.push(SetupError::MissingFeatures(missing_features));
+        }
+
+        let required_usages = TextureUsages::RENDER_ATTACHMENT
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC
+            | self.target_usage;
+        let storage_format = self.target_format.remove_srgb_suffix();
+        if !self
+            .adapter
+            .get_texture_format_features(storage_format)
+            .allowed_usages
+            .contains(required_usages)
+        {
+            errors.push(SetupError::UnsupportedFormat(self.target_format));
+        }
+
+        let size = (self.render_target.width(), self.render_target.height());
+        if let Err(error) = self.check_target_size(size, self.target_format, self.layer_count) {
+            errors.push(SetupError::Resize(error));
+        }
+
+        let invocations = Self::RAYMARCH_WORKGROUP_SIZE * Self::RAYMARCH_WORKGROUP_SIZE;
+        let max_invocations = self.limits.max_compute_invocations_per_workgroup;
+        if invocations > max_invocations {
+            errors.push(SetupError::WorkgroupTooLarge {
+                invocations,
+                max_invocations,
+            });
+        }
+
+        // Write and remove a file, since directory permissions alone do not guarantee writes
+        let directory = self.image_directory.clone();
+        let probe = directory.join(".raymarks_check");
+        let writable = create_dir_all(&directory)
+            .and_then(|()| File::create(&probe))
+            .and_then(|_| remove_file(&probe));
+        if let Err(error) = writable {
+            errors.push(SetupError::ImageDirectory(directory, error));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Load a shader from the shader directory, resolving includes and validating it before
+    /// it is compiled.
+    pub fn load_shader(&self, shader: Shader) -> Result<ShaderModule, ShaderError> {
+        debug!("Loading shader {}.", shader.source_path().display());
+        self.labelled_shader_module(
+            &shader,
+            self.pass_parameters_buffer
+                .adapt_source(shader.load_source_preprocessed()?),
+        )
+    }
+
+    /// Validate WGSL source and compile it into a shader module.
+    pub fn create_shader_module(&self, source: String) -> Result<ShaderModule, ShaderError> {
+        self.shader_module(None, source)
+    }
+
+    /// Private method to validate WGSL source adapted from the given shader and compile it into a
+    /// shader module labelled with the shader's source file.
+    fn labelled_shader_module(
+        &self,
+        shader: &Shader,
+        source: String,
+    ) -> Result<ShaderModule, ShaderError> {
+        let label = format!("raymarks.{}", shader.source_file().display());
+        self.shader_module(Some(&label), source)
+    }
+
+    /// Private method to validate WGSL source and compile it into a shader module with the given
+    /// label.
+    fn shader_module(
+        &self,
+        label: Option<&str>,
+        source: String,
+    ) -> Result<ShaderModule, ShaderError> {
+        validate_wgsl(&source, self.features())?;
+        Ok(self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label,
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            }))
+    }
+
+    /// Create a new rasterization pipeline. Passes cache the pipeline they use, so this is only
+    /// needed to build a pipeline outside of a pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn rasterization_pipeline(&self) -> Result<RenderPipeline, ShaderError> {
+        debug!(
+            "Creating rasterization pipeline for {:?} with {} samples and {} G-buffer attachments.",
+            self.target_format,
+            self.sample_count,
+            self.gbuffer_formats.len()
+        );
+        // With extra G-buffer attachments, the G-buffer shader writes surface attributes instead
+        let (shader, fragment_entry_point) = if self.gbuffer_formats.is_empty() {
+            (Shader::Rasterization, "fragment_shader")
+        } else {
+            (Shader::GBuffer, "gbuffer_fragment_shader")
+        };
+        // Uniform arrays have a fixed size, so substitute the number of instances which fit
+        let source = substitute_constant(
+            &shader.load_source_preprocessed()?,
+            "MAX_UNIFORM_INSTANCES",
+            &format!("{}u", self.uniform_instance_capacity()),
+        )?;
+        let shader =
+            self.labelled_shader_module(&shader, self.pass_parameters_buffer.adapt_source(source))?;
+        let targets: Vec<Option<ColorTargetState>> = std::iter::once(Some(ColorTargetState {
+            format: self.target_format,
+            blend: self.blend_state,
+            write_mask: ColorWrites::ALL,
+        }))
+        .chain(
+            self.gbuffer_formats
+                .iter()
+                .map(|&format| Some(format.into())),
+        )
+        .collect();
+        // Opacity only affects blending, so it is built into the shader
+        let constants = HashMap::from([("OPACITY".to_string(), self.opacity as f64)]);
+
+        // Instances read from a uniform or storage buffer are bound alongside the camera, while
+        // those read from a vertex buffer follow the vertices
+        let (vertex_entry_point, instance_binding) = match self.instance_storage {
+            InstanceStorage::Vertex => ("vertex_shader", None),
+            InstanceStorage::Uniform => ("uniform_vertex_shader", Some(BufferBindingType::Uniform)),
+            InstanceStorage::Storage => (
+                "storage_vertex_shader",
+                Some(BufferBindingType::Storage { read_only: true }),
+            ),
+        };
+        let buffer_entry = |binding: u32, ty: BufferBindingType| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::VERTEX,
+            ty: BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let instance_layout = instance_binding.map(|ty| {
+            self.device
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("raymarks.rasterization_bind_group_layout"),
+                    entries: &[
+                        buffer_entry(0, BufferBindingType::Uniform),
+                        buffer_entry(Self::instance_binding(self.instance_storage), ty),
+                    ],
+                })
+        });
+        let mut vertex_buffers = vec![self.vertex_precision.layout()];
+        if instance_layout.is_none() {
+            vertex_buffers.push(Instance::layout());
+        }
+        let bind_group_layouts: Vec<&BindGroupLayout> = std::iter::once(
+            instance_layout
+                .as_ref()
+                .unwrap_or(self.camera_buffer.bind_group_layout()),
+        )
+        .chain(self.pass_parameters_buffer.bind_group_layouts())
+        .collect();
+        let layout = self
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("raymarks.rasterization_pipeline_layout"),
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &self.pass_parameters_buffer.render_push_constant_ranges(),
+            });
+
+        Ok(self
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("raymarks.rasterization_pipeline"),
+                layout: Some(&layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some(vertex_entry_point),
+                    compilation_options: Default::default(),
+                    buffers: &vertex_buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(fragment_entry_point),
+                    compilation_options: PipelineCompilationOptions {
+                        constants: &constants,
+                        ..Default::default()
+                    },
+                    targets: &targets,
+                }),
+                primitive: PrimitiveState {
+                    topology: self.topology,
+                    // Meshes are drawn with 32-bit indices, which strips must be told to expect
+                    strip_index_format: self.topology.is_strip().then_some(IndexFormat::Uint32),
+                    front_face: self.front_face,
+                    cull_mode: self.cull_mode,
+                    polygon_mode: self.polygon_mode,
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: Self::DEPTH_FORMAT,
+                    // Translucent objects behind each other must all be drawn
+                    depth_write_enabled: self.blend_state.is_none(),
+                    depth_compare: CompareFunction::Less,
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: self.sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+            }))
+    }
+
+    /// Discard cached pipelines, so they are rebuilt on their next use. This must be called
+    /// whenever a change would make pipelines incompatible, such as a change of target format.
+    pub fn invalidate_pipelines(&mut self) {
+        self.rasterization_pipeline = None;
+        self.raymarch_pipeline = None;
+        self.triangles_pipeline = None;
+        self.gradient_pipeline = None;
+        self.mipmap_pipeline = None;
+        self.postprocess_pipeline = None;
+        self.stencil_pipelines = None;
+        // Bind groups are keyed by the pipelines whose layouts they were created with
+        self.bind_groups.clear();
+    }
+
+    /// Reload shaders from the shader directory, by discarding cached pipelines so they are
+    /// rebuilt from the current shader sources on their next use.
+    pub fn reload_shaders(&mut self) {
+        self.invalidate_pipelines();
+    }
+
+    /// Get the number of samples per pixel used by rasterization passes.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Set the number of samples per pixel used by rasterization passes, rendering to a
+    /// multisampled target which is resolved into the render target when the count is above 1.
+    /// Returns `false` and leaves the count unchanged if the count is unsupported by the render
+    /// target or depth formats, or if G-buffer attachments, mip levels or array layers are set,
+    /// since they are not multisampled.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> bool {
+        if !self.sample_count_supported(self.target_format, sample_count)
+            || !self.sample_count_supported(Self::DEPTH_FORMAT, sample_count)
+            || (sample_count > 1
+                && (!self.gbuffer_formats.is_empty()
+                    || self.mip_level_count > 1
+                    || self.layer_count > 1))
+        {
+            return false;
+        }
+
+        self.sample_count = sample_count;
+        self.invalidate_pipelines();
+        self.recreate_render_target((self.render_target.width(), self.render_target.height()));
+        true
+    }
+
+    /// Get the format rendered to by passes.
+    pub fn target_format(&self) -> TextureFormat {
+        self.target_format
+    }
+
+    /// Set the format rendered to by passes, which must be one of `TARGET_FORMATS`. Returns
+    /// `false` and leaves the format unchanged if the format is unsupported, including if it does
+    /// not support the current sample count or its staging buffer would exceed device limits.
+    pub fn set_target_format(&mut self, format: TextureFormat) -> bool {
+        let size = (self.render_target.width(), self.render_target.height());
+        if !Self::TARGET_FORMATS.contains(&format)
+            || !self.sample_count_supported(format, self.sample_count)
+            || self
+                .check_target_size(size, format, self.layer_count)
+                .is_err()
+        {
+            return false;
+        }
+
+        self.target_format = format;
+        self.invalidate_pipelines();
+        self.recreate_render_target(size);
+        true
+    }
+
+    /// Get the number of color attachments written by rasterization passes: the render target,
+    /// followed by any G-buffer attachments.
+    pub fn color_attachment_count(&self) -> usize {
+        1 + self.gbuffer_formats.len()
+    }
+
+    /// Set the formats of extra color attachments written by rasterization passes after the render
+    /// target, for deferred rendering benchmarks. With any extra attachments, passes use the
+    /// G-buffer shader, writing albedo to the render target, then normals and positions to the
+    /// extra attachments in order. An empty list restores the single render target. Returns
+    /// `false` and leaves the attachments unchanged if there are more than
+    /// `MAX_COLOR_ATTACHMENTS` in total, a format is not one of `TARGET_FORMATS`, or the sample
+    /// count is above 1.
+    pub fn set_gbuffer_formats(&mut self, formats: &[TextureFormat]) -> bool {
+        if 1 + formats.len() > Self::MAX_COLOR_ATTACHMENTS
+            || formats
+                .iter()
+                .any(|format| !Self::TARGET_FORMATS.contains(format))
+            || (!formats.is_empty() && self.sample_count > 1)
+        {
+            return false;
+        }
+
+        self.gbuffer_formats = formats.to_vec();
+        self.invalidate_pipelines();
+        self.recreate_render_target((self.render_target.width(), self.render_target.height()));
+        true
+    }
+
+    /// Get the number of mip levels of the render target, depth texture and G-buffer attachments.
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    /// Set the number of mip levels of the render target, depth texture and G-buffer attachments,
+    /// for texture LOD benchmarks. Returns `false` and leaves the count unchanged if it is zero,
+    /// exceeds the full mip chain of the render target, or the sample count is above 1. Resizing
+    /// the render target shortens the mip chain if it no longer fits.
+    pub fn set_mip_level_count(&mut self, mip_level_count: u32) -> bool {
+        let size = (self.render_target.width(), self.render_target.height());
+        if mip_level_count == 0
+            || mip_level_count > Self::max_mip_level_count(size)
+            || (mip_level_count > 1 && self.sample_count > 1)
+        {
+            return false;
+        }
+
+        self.mip_level_count = mip_level_count;
+        self.target_mip_level = self.target_mip_level.min(mip_level_count - 1);
+        self.recreate_render_target(size);
+        true
+    }
+
+    /// Get the mip level passes render into, and which is copied to the output staging buffer.
+    pub fn target_mip_level(&self) -> u32 {
+        self.target_mip_level
+    }
+
+    /// Set the mip level passes render into, and which is copied to the output staging buffer.
+    /// Saved images and reference comparisons are at the size of this level. Returns `false` and
+    /// leaves the level unchanged if the render target has no such level.
+    pub fn set_target_mip_level(&mut self, mip_level: u32) -> bool {
+        if mip_level >= self.mip_level_count {
+            return false;
+        }
+
+        self.target_mip_level = mip_level;
+        true
+    }
+
+    /// Get the number of array layers of the render target.
+    pub fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+
+    /// Set the number of array layers of the render target, for layered rendering such as the six
+    /// faces of a cube map or an array of shadow maps. Passes render into the target layer, while
+    /// every layer is copied to the output staging buffer, so its size scales with the count.
+    /// Returns `false` and leaves the count unchanged if it is zero, exceeds the device's array
+    /// layer limit, the staging buffer would exceed device limits, or the sample count is above 1.
+    pub fn set_layer_count(&mut self, layer_count: u32) -> bool {
+        let size = (self.render_target.width(), self.render_target.height());
+        if layer_count == 0
+            || layer_count > self.limits.max_texture_array_layers
+            || (layer_count > 1 && self.sample_count > 1)
+            || self
+                .check_target_size(size, self.target_format, layer_count)
+                .is_err()
+        {
+            return false;
+        }
+
+        self.layer_count = layer_count;
+        self.target_layer = self.target_layer.min(layer_count - 1);
+        self.recreate_render_target(size);
+        true
+    }
+
+    /// Get the array layer passes render into.
+    pub fn target_layer(&self) -> u32 {
+        self.target_layer
+    }
+
+    /// Set the array layer passes render into. Saved images and reference comparisons read this
+    /// layer, unless saved with `save_render_target_layer`. Returns `false` and leaves the layer
+    /// unchanged if the render target has no such layer.
+    pub fn set_target_layer(&mut self, array_layer: u32) -> bool {
+        if array_layer >= self.layer_count {
+            return false;
+        }
+
+        self.target_layer = array_layer;
+        true
+    }
+
+    /// Get the size of the given mip level of the render target, with each side halved per level
+    /// down to a minimum of 1.
+    pub fn mip_level_size(&self, mip_level: u32) -> (u32, u32) {
+        (
+            (self.render_target.width() >> mip_level).max(1),
+            (self.render_target.height() >> mip_level).max(1),
+        )
+    }
+
+    /// Record passes which fill every mip level of the target layer after the first by
+    /// downsampling the level before it with linear filtering. The passes are not timed, and are
+    /// submitted along with the next submission.
+    pub fn generate_mipmaps(&mut self) -> Result<(), ShaderError> {
+        // Build the pipeline on first use, and reuse it for later calls
+        if self.mipmap_pipeline.is_none() {
+            self.mipmap_pipeline = Some(self.mipmap_pipeline()?);
+            self.bind_groups.clear();
+        }
+        let pipeline = self.mipmap_pipeline.as_ref().unwrap();
+
+        let mip_view = |mip_level: u32| {
+            Self::layer_view(
+                &self.render_target,
+                self.target_format,
+                mip_level,
+                self.target_layer,
+            )
+        };
+        for mip_level in 1..self.mip_level_count {
+            let resources = [
+                BoundResource::RenderTarget {
+                    format: self.target_format,
+                    mip_level: mip_level - 1,
+                    array_layer: self.target_layer,
+                },
+                BoundResource::LinearSampler,
+            ];
+            let bind_group =
+                self.bind_groups
+                    .get_or_create(LayoutSource::Mipmap, &resources, || {
+                        self.device.create_bind_group(&BindGroupDescriptor {
+                            label: Some("raymarks.mipmap_bind_group"),
+                            layout: &pipeline.get_bind_group_layout(0),
+                            entries: &[
+                                BindGroupEntry {
+                                    binding: 0,
+                                    resource: BindingResource::TextureView(&mip_view(
+                                        mip_level - 1,
+                                    )),
+                                },
+                                BindGroupEntry {
+                                    binding: 1,
+                                    resource: BindingResource::Sampler(&self.linear_sampler),
+                                },
+                            ],
+                        })
+                    });
+            let mut render_pass = self.commands.begin_render_pass(&RenderPassDescriptor {
+                label: Some("raymarks.mipmap_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &mip_view(mip_level),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        Ok(())
+    }
+
+    /// Private method to create a pipeline which downsamples one mip level of the render target
+    /// into the next, by drawing it over the whole level.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    fn mipmap_pipeline(&self) -> Result<RenderPipeline, ShaderError> {
+        debug!("Creating mipmap pipeline for {:?}.", self.target_format);
+        let shader = self.load_shader(Shader::Blit)?;
+        Ok(self
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("raymarks.mipmap_pipeline"),
+                layout: None,
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some("vertex_shader"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragment_shader"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(self.target_format.into())],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            }))
+    }
+
+    /// Private method to get the number of mip levels in a full mip chain of the given size.
+    fn max_mip_level_count(size: (u32, u32)) -> u32 {
+        u32::BITS - size.0.max(size.1).leading_zeros()
+    }
+
+    /// Private method to get the size of the mip level passes render into.
+    fn target_size(&self) -> (u32, u32) {
+        self.mip_level_size(self.target_mip_level)
+    }
+
+    /// Resize the render target, multisampled target, depth texture, and output staging buffer to
+    /// the given size, multiplied by the supersampling factor. Returns an error and leaves the
+    /// targets unchanged if the size exceeds the device's texture dimension or buffer size limits.
+    pub fn resize_render_target(&mut self, size: impl Into<Resolution>) -> Result<(), ResizeError> {
+        let Resolution { width, height } = size.into();
+        let size = (
+            width.saturating_mul(self.supersample),
+            height.saturating_mul(self.supersample),
+        );
+        self.check_target_size(size, self.target_format, self.layer_count)?;
+        self.recreate_render_target(size);
+        Ok(())
+    }
+
+    /// Get the factor by which each side of the render target is larger than the size it was
+    /// resized to.
+    pub fn supersample(&self) -> u32 {
+        self.supersample
+    }
+
+    /// Render at the given factor times each side of the size the render target is resized to,
+    /// and box-filter images back down to that size when they are read back, for supersampled
+    /// anti-aliasing. Passes are timed at the larger size, so the cost of supersampling can be
+    /// compared with multisampling. Scissor rectangles and saved regions are in pixels of the
+    /// larger target. The render target is recreated at the new size. Returns `false` and leaves
+    /// the factor unchanged if it is zero or the larger target exceeds the device's limits.
+    /// Defaults to 1.
+    pub fn set_supersample(&mut self, supersample: u32) -> bool {
+        if supersample == 0 {
+            return false;
+        }
+        let size = (
+            (self.render_target.width() / self.supersample).saturating_mul(supersample),
+            (self.render_target.height() / self.supersample).saturating_mul(supersample),
+        );
+        if let Err(resize_error) =
+            self.check_target_size(size, self.target_format, self.layer_count)
+        {
+            warn!(
+                "Supersampling by {} is unsupported: {}",
+                supersample, resize_error
+            );
+            return false;
+        }
+
+        self.supersample = supersample;
+        self.recreate_render_target(size);
+        true
+    }
+
+    /// Private method to check that targets of the given size, format and number of array layers
+    /// fit within the device's limits.
+    fn check_target_size(
+        &self,
+        size: (u32, u32),
+        format: TextureFormat,
+        layer_count: u32,
+    ) -> Result<(), ResizeError> {
+        let limits = &self.limits;
+        if size.0 > limits.max_texture_dimension_2d || size.1 > limits.max_texture_dimension_2d {
+            return Err(ResizeError::TextureTooLarge {
+                size,
+                max_dimension: limits.max_texture_dimension_2d,
+            });
+        }
+
+        let buffer_size = Self::padded_bytes_per_row(size.0, Self::bytes_per_texel(format)) as u64
+            * size.1 as u64
+            * layer_count as u64;
+        if buffer_size > limits.max_buffer_size {
+            return Err(ResizeError::BufferTooLarge {
+                size: buffer_size,
+                max_size: limits.max_buffer_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Private method to recreate the render target, multisampled target, depth texture, and
+    /// output staging buffer at the given size, which must fit within the device's limits.
+    fn recreate_render_target(&mut self, size: (u32, u32)) {
+        // Cached bind groups may refer to the old targets
+        self.bind_groups.clear();
+        self.mip_level_count = self.mip_level_count.min(Self::max_mip_level_count(size));
+        self.target_mip_level = self.target_mip_level.min(self.mip_level_count - 1);
+        self.render_target.destroy();
+        self.render_target = Self::render_target(
+            &self.device,
+            "render_target",
+            Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: self.layer_count,
+            },
+            self.target_format,
+            self.mip_level_count,
+            self.target_usage,
+            &self.target_view_formats,
+        );
+        if let Some(multisampled_target) = &self.multisampled_target {
+            multisampled_target.destroy();
+        }
+        self.multisampled_target =
+            Self::multisampled_target(&self.device, size, self.target_format, self.sample_count);
+        for gbuffer_target in &self.gbuffer_targets {
+            gbuffer_target.destroy();
+        }
+        self.gbuffer_targets = self
+            .gbuffer_formats
+            .iter()
+            .map(|&format| {
+                Self::render_target(
+                    &self.device,
+                    "gbuffer_target",
+                    Extent3d {
+                        width: size.0,
+                        height: size.1,
+                        depth_or_array_layers: 1,
+                    },
+                    format,
+                    self.mip_level_count,
+                    TextureUsages::empty(),
+                    &[],
+                )
+            })
+            .collect();
+        self.depth_texture.destroy();
+        self.depth_texture =
+            Self::depth_texture(&self.device, size, self.mip_level_count, self.sample_count);
+        // Reads cancel their own mappings when they fail, so the buffer is never left mapped
+        self.output_staging_buffer.destroy();
+        self.output_staging_buffer = Self::output_staging_buffer(
+            &self.device,
+            size,
+            Self::bytes_per_texel(self.target_format),
+            self.layer_count,
+        );
+        // Frames still in flight are dropped along with the old ring
+        if let Some(capture_ring) = self.capture_ring.take() {
+            capture_ring.destroy();
+            self.capture_ring = Some(CaptureRing::new(
+                &self.device,
+                capture_ring.depth(),
+                size,
+                Self::bytes_per_texel(self.target_format),
+            ));
+        }
+
+        debug!(
+            "Recreated render target at {}x{} with {} layers: staging buffer of {} bytes, {} bytes \
+             allocated.",
+            size.0,
+            size.1,
+            self.layer_count,
+            self.output_staging_buffer.size(),
+            self.allocated_bytes()
+        );
+
+        // Keep the camera's aspect ratio matching the render target
+        self.camera.aspect = size.0 as f32 / size.1 as f32;
+        self.camera_buffer.write(&self.queue, &self.camera);
+    }
+
+    /// Get the camera from which rasterization passes are rendered.
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    /// Set the camera from which rasterization passes are rendered. The aspect ratio is replaced
+    /// by that of the render target.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = Camera {
+            aspect: self.render_target.width() as f32 / self.render_target.height() as f32,
+            ..camera
+        };
+        self.camera_buffer.write(&self.queue, &self.camera);
+    }
+
+    /// Get the parameters passed to shaders by each pass.
+    pub fn pass_parameters(&self) -> PassParameters {
+        self.pass_parameters
+    }
+
+    /// Set the parameters passed to shaders by each pass, such as the time and the position the
+    /// raymarch shader casts rays from. They are passed as push constants where supported, which
+    /// is cheaper than writing a uniform buffer, and through a uniform buffer otherwise.
+    pub fn set_pass_parameters(&mut self, parameters: PassParameters) {
+        self.pass_parameters = parameters;
+        self.pass_parameters_buffer
+            .write(&self.queue, &self.pass_parameters);
+    }
+
+    /// Get the filtering of samplers created for textures loaded by `load_texture`.
+    pub fn texture_filter(&self) -> FilterMode {
+        self.texture_filter
+    }
+
+    /// Set the filtering of samplers created for textures loaded by `load_texture` from now on.
+    /// Textures which are already loaded keep their samplers.
+    pub fn set_texture_filter(&mut self, filter: FilterMode) {
+        self.texture_filter = filter;
+    }
+
+    /// Decode a PNG, JPEG or WebP image and upload it to a new texture, along with a sampler using
+    /// the texture filter. Returns a handle for getting its view and sampler for bind groups.
+    pub fn load_texture(&mut self, path: &Path) -> Result<TextureId, TextureError> {
+        let image = image::open(path)?.into_rgba8();
+        let max_dimension = self.limits.max_texture_dimension_2d;
+        if image.width() > max_dimension || image.height() > max_dimension {
+            return Err(TextureError::TooLarge {
+                width: image.width(),
+                height: image.height(),
+                max_dimension,
+            });
+        }
+        self.textures.push(InputTexture::new(
+            &self.device,
+            &self.queue,
+            &image,
+            self.texture_filter,
+        ));
+        Ok(TextureId(self.textures.len() - 1))
+    }
+
+    /// Get the view of a loaded texture, for binding as a sampled texture.
+    pub fn texture_view(&self, id: TextureId) -> &TextureView {
+        self.textures[id.0].view()
+    }
+
+    /// Get the sampler of a loaded texture, for binding alongside its view.
+    pub fn texture_sampler(&self, id: TextureId) -> &Sampler {
+        self.textures[id.0].sampler()
+    }
+
+    /// Get the stencil test and operations applied to both faces by the fill of stencil passes.
+    pub fn stencil_face(&self) -> StencilFaceState {
+        self.stencil_face
+    }
+
+    /// Set the stencil test and operations applied to both faces by the fill of stencil passes.
+    /// The default only fills where the mask was drawn, by comparing for equality with the
+    /// reference.
+    pub fn set_stencil_face(&mut self, face: StencilFaceState) {
+        self.stencil_face = face;
+        self.stencil_pipelines = None;
+    }
+
+    /// Get the stencil reference value written by the mask of stencil passes, and compared against
+    /// by their fill.
+    pub fn stencil_reference(&self) -> u32 {
+        self.stencil_reference
+    }
+
+    /// Set the stencil reference value written by the mask of stencil passes, and compared against
+    /// by their fill.
+    pub fn set_stencil_reference(&mut self, reference: u32) {
+        self.stencil_reference = reference;
+    }
+
+    /// Get the color the render target is cleared to before rasterization passes.
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    /// Set the color the render target is cleared to before rasterization passes. Defaults to
+    /// black.
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    /// Get whether rasterization passes clear the render target before drawing, or load its
+    /// previous contents.
+    pub fn clears_target(&self) -> bool {
+        self.clear_target
+    }
+
+    /// Set whether rasterization passes clear the render target to the clear color before drawing,
+    /// or load its previous contents and draw over them. The depth buffer is cleared either way.
+    /// Defaults to clearing.
+    pub fn set_clears_target(&mut self, clear_target: bool) {
+        self.clear_target = clear_target;
+    }
+
+    /// Private method to get the load operation of the render target in rasterization passes.
+    fn target_load_op(&self) -> LoadOp<Color> {
+        if self.clear_target {
+            LoadOp::Clear(self.clear_color)
+        } else {
+            LoadOp::Load
+        }
+    }
+
+    /// Get how rasterization passes draw the instances of the mesh.
+    pub fn draw_mode(&self) -> DrawMode {
+        self.draw_mode
+    }
+
+    /// Set how rasterization passes draw the instances of the mesh: with one instanced draw call,
+    /// a draw call per instance, or an indirect draw call per object. Defaults to instanced.
+    pub fn set_draw_mode(&mut self, draw_mode: DrawMode) {
+        self.draw_mode = draw_mode;
+    }
+
+    /// Get where rasterization passes read instances from.
+    pub fn instance_storage(&self) -> InstanceStorage {
+        self.instance_storage
+    }
+
+    /// Set where rasterization passes read instances from: a vertex buffer, a uniform buffer or a
+    /// storage buffer. Instances beyond `max_instances` of the storage are not drawn. Defaults to
+    /// a vertex buffer.
+    pub fn set_instance_storage(&mut self, storage: InstanceStorage) {
+        if storage != self.instance_storage {
+            self.instance_storage = storage;
+            self.rasterization_pipeline = None;
+        }
+    }
+
+    /// Get the precision of the vertex attributes of meshes uploaded for rasterization passes.
+    pub fn vertex_precision(&self) -> VertexPrecision {
+        self.vertex_precision
+    }
+
+    /// Set the precision of the vertex attributes of meshes uploaded for rasterization passes,
+    /// to compare the memory bandwidth of single- and half-precision vertex buffers. Meshes
+    /// already uploaded keep their precision, so the mesh or scene must be set again before the
+    /// next pass. Precisions the device cannot fetch, as found by `supports_vertex_precision`,
+    /// fall back to single precision and return `false`. Defaults to single precision.
+    pub fn set_vertex_precision(&mut self, precision: VertexPrecision) -> bool {
+        let supported = self.supports_vertex_precision(precision);
+        let precision = if supported {
+            precision
+        } else {
+            warn!(
+                "{:?} vertex precision is unsupported, so meshes will be uploaded in single \
+                 precision.",
+                precision
+            );
+            VertexPrecision::Single
+        };
+        if precision != self.vertex_precision {
+            self.vertex_precision = precision;
+            self.rasterization_pipeline = None;
+        }
+        supported
+    }
+
+    /// Get whether the device can fetch vertex attributes of the given precision. Half-precision
+    /// vertex formats are converted to single precision as they are fetched, so unlike
+    /// half-precision shader arithmetic no feature covers them. Instead, the first check creates
+    /// a pipeline fetching them, which devices without them reject, and remembers the result.
+    pub fn supports_vertex_precision(&mut self, precision: VertexPrecision) -> bool {
+        match precision {
+            VertexPrecision::Single => true,
+            VertexPrecision::Half => {
+                let supported = self
+                    .half_vertices_supported
+                    .unwrap_or_else(|| self.probe_vertex_precision(precision));
+                self.half_vertices_supported = Some(supported);
+                supported
+            }
+        }
+    }
+
+    /// Private method to check the device can fetch vertex attributes of the given precision, by
+    /// creating a pipeline fetching each of them and catching any error.
+    fn probe_vertex_precision(&self, precision: VertexPrecision) -> bool {
+        let device = &self.device;
+        let shader = match self.load_shader(Shader::VertexProbe) {
+            Ok(shader) => shader,
+            Err(error) => {
+                warn!("Failed to load the vertex probe shader: {}", error);
+                return false;
+            }
+        };
+        // Drivers may reject formats when the pipeline is compiled, which is an internal error
+        device.push_error_scope(ErrorFilter::Validation);
+        device.push_error_scope(ErrorFilter::Internal);
+        let _pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("raymarks.vertex_probe_pipeline"),
+            layout: None,
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vertex_shader"),
+                compilation_options: Default::default(),
+                buffers: &[precision.layout()],
+            },
+            fragment: None,
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let internal_error = pollster::block_on(device.pop_error_scope());
+        let validation_error = pollster::block_on(device.pop_error_scope());
+        match internal_error.or(validation_error) {
+            Some(error) => {
+                debug!("{:?} vertex precision was rejected: {}", precision, error);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Get the largest number of instances of an object the device's limits allow rasterization
+    /// passes to draw from the given storage.
+    pub fn max_instances(&self, storage: InstanceStorage) -> u64 {
+        match storage {
+            InstanceStorage::Vertex => self.limits.max_buffer_size / Instance::STRIDE,
+            InstanceStorage::Uniform => self.uniform_instance_capacity() as u64,
+            InstanceStorage::Storage => {
+                self.limits.max_storage_buffer_binding_size as u64 / Instance::STRIDE
+            }
+        }
+    }
+
+    /// Private method to get the number of instances in the uniform array read by rasterization
+    /// shaders, which fills the largest uniform binding up to `MAX_UNIFORM_INSTANCE_BYTES`.
+    fn uniform_instance_capacity(&self) -> u32 {
+        let bytes = self
+            .limits
+            .max_uniform_buffer_binding_size
+            .min(Self::MAX_UNIFORM_INSTANCE_BYTES);
+        (bytes as u64 / Instance::STRIDE) as u32
+    }
+
+    /// Private function to get the binding in group 0 from which rasterization shaders read
+    /// instances stored in a uniform or storage buffer, matching `rasterization.wgsl`.
+    fn instance_binding(storage: InstanceStorage) -> u32 {
+        match storage {
+            InstanceStorage::Uniform => 1,
+            InstanceStorage::Vertex | InstanceStorage::Storage => 2,
+        }
+    }
+
+    /// Get whether rasterization passes fill triangles or draw their edges.
+    pub fn polygon_mode(&self) -> PolygonMode {
+        self.polygon_mode
+    }
+
+    /// Set whether rasterization passes fill triangles or draw their edges as a wireframe.
+    /// Wireframes need the `POLYGON_MODE_LINE` feature, so without it triangles stay filled and
+    /// `false` is returned. Point mode is unsupported.
+    pub fn set_polygon_mode(&mut self, polygon_mode: PolygonMode) -> bool {
+        let supported = match polygon_mode {
+            PolygonMode::Fill => true,
+            PolygonMode::Line => self.features().contains(Features::POLYGON_MODE_LINE),
+            PolygonMode::Point => false,
+        };
+        if !supported {
+            warn!(
+                "{:?} polygon mode is unsupported, so triangles will be filled.",
+                polygon_mode
+            );
+            return false;
+        }
+
+        self.polygon_mode = polygon_mode;
+        self.invalidate_pipelines();
+        true
+    }
+
+    /// Get how rasterization passes assemble the vertices of meshes into primitives.
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    /// Set how rasterization passes assemble the vertices of meshes into primitives, such as
+    /// `PointList` to draw every vertex as a point, which benchmarks vertex throughput with
+    /// little fragment work. Lines and points are never culled. Defaults to `TriangleList`.
+    pub fn set_topology(&mut self, topology: PrimitiveTopology) {
+        self.topology = topology;
+        self.invalidate_pipelines();
+    }
+
+    /// Get which faces rasterization passes cull, if any.
+    pub fn cull_mode(&self) -> Option<Face> {
+        self.cull_mode
+    }
+
+    /// Set which faces rasterization passes cull, if any. Culling back faces of closed meshes
+    /// skips shading fragments which are never visible. Defaults to no culling.
+    pub fn set_cull_mode(&mut self, cull_mode: Option<Face>) {
+        self.cull_mode = cull_mode;
+        self.invalidate_pipelines();
+    }
+
+    /// Get the winding order of front-facing triangles.
+    pub fn front_face(&self) -> FrontFace {
+        self.front_face
+    }
+
+    /// Set the winding order of front-facing triangles, which must match the mesh for culling to
+    /// remove the right faces. Defaults to counter-clockwise, as in OBJ files.
+    pub fn set_front_face(&mut self, front_face: FrontFace) {
+        self.front_face = front_face;
+        self.invalidate_pipelines();
+    }
+
+    /// Get the rectangle rasterization passes are restricted to, as x, y, width and height in
+    /// pixels from the top left of the render target, or `None` if they cover the whole target.
+    pub fn scissor_rect(&self) -> Option<(u32, u32, u32, u32)> {
+        self.scissor_rect
+    }
+
+    /// Restrict rasterization passes to the given rectangle of the render target, in pixels from
+    /// its top left, for benchmarking partial-screen or tiled rendering. Fragments outside it are
+    /// discarded before shading, though passes still clear the whole target. Returns `false` and
+    /// leaves the rectangle unchanged if it is empty or extends past the render target. If the
+    /// target is later resized, the rectangle is clamped to it.
+    pub fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) -> bool {
+        let (target_width, target_height) = self.target_size();
+        if width == 0
+            || height == 0
+            || x.checked_add(width)
+                .is_none_or(|right| right > target_width)
+            || y.checked_add(height)
+                .is_none_or(|bottom| bottom > target_height)
+        {
+            return false;
+        }
+
+        self.scissor_rect = Some((x, y, width, height));
+        true
+    }
+
+    /// Remove the scissor rectangle, so rasterization passes cover the whole render target.
+    pub fn clear_scissor_rect(&mut self) {
+        self.scissor_rect = None;
+    }
+
+    /// Private method to get the scissor rectangle clamped to the current size of the render
+    /// target, which may have been resized since the rectangle was set.
+    fn clamped_scissor_rect(&self) -> Option<(u32, u32, u32, u32)> {
+        let (target_width, target_height) = self.target_size();
+        self.scissor_rect.map(|(x, y, width, height)| {
+            let x = x.min(target_width);
+            let y = y.min(target_height);
+            (
+                x,
+                y,
+                width.min(target_width - x),
+                height.min(target_height - y),
+            )
+        })
+    }
+
+    /// Get how rasterization passes blend fragments into the render target, if at all.
+    pub fn blend_state(&self) -> Option<BlendState> {
+        self.blend_state
+    }
+
+    /// Set how rasterization passes blend fragments into the render target, such as
+    /// `BlendState::ALPHA_BLENDING` for translucent objects, which should be drawn back to front.
+    /// While blending, depth is tested but not written, so every layer is shaded. G-buffer
+    /// attachments are never blended. Defaults to `None`, replacing colors as opaque objects.
+    pub fn set_blend_state(&mut self, blend_state: Option<BlendState>) {
+        self.blend_state = blend_state;
+        self.invalidate_pipelines();
+    }
+
+    /// Get the alpha of fragments drawn by rasterization passes.
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Set the alpha of fragments drawn by rasterization passes, which is only visible with a
+    /// blend state. Defaults to 1.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+        self.invalidate_pipelines();
+    }
+
+    /// Get the transfer function of color data written to saved images.
+    pub fn output_encoding(&self) -> ColorEncoding {
+        self.output_encoding
+    }
+
+    /// Set the transfer function of color data written to saved images. Defaults to sRGB, which
+    /// matches the render target's encoding so colors look right in browsers and image editors.
+    pub fn set_output_encoding(&mut self, encoding: ColorEncoding) {
+        self.output_encoding = encoding;
+    }
+
+    /// Get whether raymarch passes record the number of steps each pixel's ray took.
+    pub fn record_steps(&self) -> bool {
+        self.record_steps
+    }
+
+    /// Set whether raymarch passes record the number of steps each pixel's ray took, to be saved
+    /// with `save_step_heatmap`. Recording writes a second storage texture, so it adds to the
+    /// duration of the passes, and is disabled by default.
+    pub fn set_record_steps(&mut self, record_steps: bool) {
+        self.record_steps = record_steps;
+    }
+
+    /// Get the shader raymarch passes run, which is `Shader::Raymarch` unless another is set.
+    pub fn raymarch_shader(&self) -> &Shader {
+        &self.raymarch_shader
+    }
+
+    /// Set the shader raymarch passes run, such as a custom shader loaded with
+    /// `Shader::from_path`. It must declare the same bindings and entry points as
+    /// `raymarch.wgsl`, and its storage texture format is substituted the same way. Images saved
+    /// after its passes are named after its file, through the `{shader}` template placeholder.
+    pub fn set_raymarch_shader(&mut self, shader: Shader) {
+        self.raymarch_shader = shader;
+        self.invalidate_pipelines();
+    }
+
+    /// Upload the objects of the given scene to the GPU, replacing the scene drawn by
+    /// rasterization passes.
+    pub fn set_scene(&mut self, scene: &Scene) {
+        self.clear_generated_indirect_buffer();
+        // Cached bind groups may refer to the old instance buffers
+        self.bind_groups.clear();
+        for (mesh, instances) in self.scene.drain(..) {
+            mesh.destroy();
+            instances.destroy();
+        }
+        let capacity = self.uniform_instance_capacity();
+        for object in &scene.objects {
+            let instances: Vec<Instance> = object
+                .transforms
+                .iter()
+                .map(|&transform| Instance {
+                    material: object.material.0,
+                    ..transform
+                })
+                .collect();
+            self.scene.push((
+                MeshBuffers::new(
+                    &self.device,
+                    &object.mesh.vertices,
+                    &object.mesh.indices,
+                    self.vertex_precision,
+                ),
+                InstanceBuffer::new(&self.device, &instances, capacity),
+            ));
+        }
+        debug!(
+            "Uploaded scene of {} objects: {} bytes of mesh and instance buffers.",
+            self.scene.len(),
+            self.scene
+                .iter()
+                .map(|(mesh, instances)| mesh.size() + instances.size())
+                .sum::<u64>()
+        );
+    }
+
+    /// Upload the given mesh to the GPU, replacing the mesh of the first object in the scene. If
+    /// the scene is empty, an object with a single untransformed instance is added.
+    pub fn set_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) {
+        self.clear_generated_indirect_buffer();
+        let capacity = self.uniform_instance_capacity();
+        let mesh = MeshBuffers::new(&self.device, vertices, indices, self.vertex_precision);
+        debug!(
+            "Uploaded mesh of {} vertices and {} indices: {} bytes.",
+            vertices.len(),
+            indices.len(),
+            mesh.size()
+        );
+        match self.scene.first_mut() {
+            Some((old_mesh, _)) => replace(old_mesh, mesh).destroy(),
+            None => self.scene.push((
+                mesh,
+                InstanceBuffer::new(&self.device, &[Instance::IDENTITY], capacity),
+            )),
+        }
+    }
+
+    /// Upload the given instances to the GPU, replacing those of the first object in the scene.
+    /// Has no effect if the scene is empty, so a mesh must be set first.
+    pub fn set_instances(&mut self, instances: &[Instance]) {
+        self.clear_generated_indirect_buffer();
+        // Cached bind groups may refer to the old instance buffer
+        self.bind_groups.clear();
+        let capacity = self.uniform_instance_capacity();
+        if let Some((_, old_instances)) = self.scene.first_mut() {
+            let new_instances = InstanceBuffer::new(&self.device, instances, capacity);
+            debug!(
+                "Uploaded {} instances: {} bytes.",
+                instances.len(),
+                new_instances.size()
+            );
+            replace(old_instances, new_instances).destroy();
+        }
+    }
+
+    /// Get indirect draw parameters equivalent to instanced draws of the given number of
+    /// instances of each object in the scene, one entry per object, clamped to the number of
+    /// instances of each object.
+    pub fn scene_indirect_args(&self, instance_count: u32) -> Vec<util::DrawIndexedIndirectArgs> {
+        self.scene
+            .iter()
+            .map(|(mesh, instances)| util::DrawIndexedIndirectArgs {
+                index_count: mesh.index_count(),
+                instance_count: instance_count.min(instances.count()),
+                first_index: 0,
+                base_vertex: 0,
+                first_instance: 0,
+            })
+            .collect()
+    }
+
+    /// Upload the draw parameters read by rasterization passes in `DrawMode::Indirect`, one entry
+    /// per object in the scene, in order. Objects without an entry are not drawn. Until this is
+    /// called, passes draw with parameters from `scene_indirect_args`, uploaded once per instance
+    /// count.
+    ///
+    /// A non-zero `first_instance` needs the `INDIRECT_FIRST_INSTANCE` feature, without which it
+    /// may be ignored.
+    pub fn set_indirect_buffer(&mut self, args: &[util::DrawIndexedIndirectArgs]) {
+        if !self.features().contains(Features::INDIRECT_FIRST_INSTANCE)
+            && args.iter().any(|args| args.first_instance != 0)
+        {
+            warn!("Indirect draws start at non-zero instances, which the device does not support.");
+        }
+        let indirect_buffer = IndirectBuffer::new(&self.device, args);
+        debug!(
+            "Uploaded indirect draw parameters for {} objects: {} bytes.",
+            args.len(),
+            indirect_buffer.size()
+        );
+        if let Some(old_indirect_buffer) = self.indirect_buffer.replace(indirect_buffer) {
+            old_indirect_buffer.destroy();
+        }
+    }
+
+    /// Remove the draw parameters uploaded by `set_indirect_buffer`, so indirect draws go back to
+    /// parameters from `scene_indirect_args`.
+    pub fn clear_indirect_buffer(&mut self) {
+        if let Some(indirect_buffer) = self.indirect_buffer.take() {
+            indirect_buffer.destroy();
+        }
+    }
+
+    /// Private method to free indirect draw parameters generated for an outdated scene.
+    fn clear_generated_indirect_buffer(&mut self) {
+        if let Some((_, indirect_buffer)) = self.generated_indirect_buffer.take() {
+            indirect_buffer.destroy();
+        }
+    }
+
+    /// Create a new rasterization pass, drawing the given number of instances of each object in
+    /// the scene. The count is clamped to the number of instances of each object. In
+    /// `DrawMode::Indirect`, the parameters set by `set_indirect_buffer` take precedence over the
+    /// count.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(
+                instance_count = instance_count,
+                width = self.target_size().0,
+                height = self.target_size().1,
+            )
+        )
+    )]
+    pub fn rasterization_pass(&mut self, instance_count: u32) -> Result<(), ShaderError> {
+        // Build the pipeline on first use, and reuse it for later passes
+        if self.rasterization_pipeline.is_none() {
+            self.rasterization_pipeline = Some(self.rasterization_pipeline()?);
+            self.bind_groups.clear();
+        }
+        let shader = if self.gbuffer_formats.is_empty() {
+            Shader::Rasterization
+        } else {
+            Shader::GBuffer
+        };
+        self.last_pass = Some((shader, Some(instance_count)));
+        // Without parameters of their own, indirect draws cover the same instances as instanced
+        // draws, uploaded before the pass so the upload is not measured with it
+        if self.draw_mode == DrawMode::Indirect
+            && self.indirect_buffer.is_none()
+            && self
+                .generated_indirect_buffer
+                .as_ref()
+                .is_none_or(|(count, _)| *count != instance_count)
+        {
+            self.clear_generated_indirect_buffer();
+            let indirect_buffer =
+                IndirectBuffer::new(&self.device, &self.scene_indirect_args(instance_count));
+            self.generated_indirect_buffer = Some((instance_count, indirect_buffer));
+        }
+        let scissor_rect = self.clamped_scissor_rect();
+        let target_load_op = self.target_load_op();
+        let pipeline = self.rasterization_pipeline.as_ref().unwrap();
+        let instance_storage = self.instance_storage;
+        let uniform_instance_capacity = self.uniform_instance_capacity();
+
+        // When multisampling, render to the multisampled target and resolve into the render target
+        let target_view = Self::layer_view(
+            &self.render_target,
+            self.target_format,
+            self.target_mip_level,
+            self.target_layer,
+        );
+        let multisampled_view = self
+            .multisampled_target
+            .as_ref()
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+        let (view, resolve_target) = match &multisampled_view {
+            Some(multisampled_view) => (multisampled_view, Some(&target_view)),
+            None => (&target_view, None),
+        };
+        let gbuffer_views: Vec<TextureView> = self
+            .gbuffer_targets
+            .iter()
+            .zip(&self.gbuffer_formats)
+            .map(|(texture, &format)| {
+                texture.create_view(&TextureViewDescriptor {
+                    format: Some(format),
+                    base_mip_level: self.target_mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let color_attachments: Vec<Option<RenderPassColorAttachment>> =
+            std::iter::once(Some(RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: Operations {
+                    load: target_load_op,
+                    store: StoreOp::Store,
+                },
+            }))
+            .chain(gbuffer_views.iter().map(|view| {
+                Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })
+            }))
+            .collect();
+
+        // First, render to the render target
+        {
+            let mut render_pass = self.commands.begin_render_pass(&RenderPassDescriptor {
+                label: Some("raymarks.rasterization_pass"),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.create_view(&TextureViewDescriptor {
+                        base_mip_level: self.target_mip_level,
+                        mip_level_count: Some(1),
+                        ..Default::default()
+                    }),
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: self
+                    .timestamp_queries
+                    .as_ref()
+                    .map(TimestampQueries::render_pass_writes),
+            });
+            if let Some(queries) = &self.pipeline_statistics_queries {
+                queries.begin_render_pass(&mut render_pass);
+            }
+            render_pass.set_pipeline(pipeline);
+            if let Some((x, y, width, height)) = scissor_rect {
+                render_pass.set_scissor_rect(x, y, width, height);
+            }
+            render_pass.set_bind_group(0, self.camera_buffer.bind_group(), &[]);
+            self.pass_parameters_buffer
+                .set_render_pass(&mut render_pass);
+            let indirect_buffer = self.indirect_buffer.as_ref().or(self
+                .generated_indirect_buffer
+                .as_ref()
+                .map(|(_, indirect_buffer)| indirect_buffer));
+            for (index, (mesh, instances)) in self.scene.iter().enumerate() {
+                let mut instance_count = instance_count.min(instances.count());
+                if instance_storage != InstanceStorage::Vertex {
+                    // Instances are uploaded again whenever they change, clearing the cache
+                    let resources = [BoundResource::CameraBuffer, BoundResource::Instances(index)];
+                    let bind_group = self.bind_groups.get_or_create(
+                        LayoutSource::Rasterization,
+                        &resources,
+                        || {
+                            // Uniform bindings must match the size of the array in the shader
+                            let size = (instance_storage == InstanceStorage::Uniform).then(|| {
+                                BufferSize::new(uniform_instance_capacity as u64 * Instance::STRIDE)
+                                    .unwrap()
+                            });
+                            self.device.create_bind_group(&BindGroupDescriptor {
+                                label: Some("raymarks.instances_bind_group"),
+                                layout: &pipeline.get_bind_group_layout(0),
+                                entries: &[
+                                    BindGroupEntry {
+                                        binding: 0,
+                                        resource: self.camera_buffer.buffer().as_entire_binding(),
+                                    },
+                                    BindGroupEntry {
+                                        binding: Self::instance_binding(instance_storage),
+                                        resource: BindingResource::Buffer(BufferBinding {
+                                            buffer: instances.buffer(),
+                                            offset: 0,
+                                            size,
+                                        }),
+                                    },
+                                ],
+                            })
+                        },
+                    );
+                    render_pass.set_bind_group(0, bind_group, &[]);
+                }
+                if instance_storage == InstanceStorage::Uniform {
+                    instance_count = instance_count.min(uniform_instance_capacity);
+                }
+                mesh.draw(
+                    &mut render_pass,
+                    instances,
+                    instance_count,
+                    self.draw_mode,
+                    instance_storage,
+                    indirect_buffer.map(|indirect_buffer| (indirect_buffer, index as u32)),
+                );
+            }
+            if self.pipeline_statistics_queries.is_some() {
+                render_pass.end_pipeline_statistics_query();
+            }
+        }
+
+        // Then, resolve timestamps and copy the render target to the output staging buffer
+        self.finish_pass();
+        Ok(())
+    }
+
+    /// Create a new compute pipeline which raymarches a signed distance field into a storage
+    /// texture, also writing step counts to a second storage texture if `record_steps` is set.
+    /// Passes cache the pipeline they use, so this is only needed to build a pipeline outside of
+    /// a pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn raymarch_pipeline(&self) -> Result<ComputePipeline, ShaderError> {
+        debug!(
+            "Creating raymarch pipeline for {:?}, recording steps: {}.",
+            self.target_format, self.record_steps
+        );
+        let entry_point = if self.record_steps {
+            "raymarch_steps_shader"
+        } else {
+            "raymarch_shader"
+        };
+        let source = self
+            .pass_parameters_buffer
+            .adapt_source(Self::adapt_output_texture(
+                self.raymarch_shader.load_source_preprocessed()?,
+                self.target_format,
+            ));
+        check_workgroup_size(&source, entry_point, Self::RAYMARCH_WORKGROUP_SIZE)?;
+        let shader = self.labelled_shader_module(&self.raymarch_shader, source)?;
+
+        // The layout is explicit, since derived layouts cannot hold push constants
+        let storage_entry = |binding: u32, format: TextureFormat| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::WriteOnly,
+                format,
+                view_dimension: TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let mut output_entries = vec![storage_entry(0, self.target_format.remove_srgb_suffix())];
+        if self.record_steps {
+            output_entries.push(storage_entry(1, Self::STEP_FORMAT));
+        }
+        let output_layout = self
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("raymarks.raymarch_bind_group_layout"),
+                entries: &output_entries,
+            });
+        let bind_group_layouts: Vec<&BindGroupLayout> = std::iter::once(&output_layout)
+            .chain(self.pass_parameters_buffer.bind_group_layouts())
+            .collect();
+        let layout = self
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("raymarks.raymarch_pipeline_layout"),
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &self.pass_parameters_buffer.compute_push_constant_ranges(),
+            });
+
+        // Storage textures cannot be sRGB, so the shader encodes sRGB itself when needed
+        let constants = HashMap::from([(
+            "ENCODE_SRGB".to_string(),
+            self.target_format.is_srgb() as u32 as f64,
+        )]);
+
+        Ok(self
+            .device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("raymarks.raymarch_pipeline"),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: PipelineCompilationOptions {
+                    constants: &constants,
+                    ..Default::default()
+                },
+                cache: None,
+            }))
+    }
+
+    /// Create a new raymarching pass, writing the render target directly from a compute shader.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(width = self.target_size().0, height = self.target_size().1)
+        )
+    )]
+    pub fn raymarch_pass(&mut self) -> Result<(), ShaderError> {
+        // Build the pipeline on first use, and reuse it for later passes recording steps the same
+        if !matches!(&self.raymarch_pipeline, Some((built, _)) if *built == self.record_steps) {
+            let pipeline = self.raymarch_pipeline()?;
+            self.raymarch_pipeline = Some((self.record_steps, pipeline));
+            self.bind_groups.clear();
+        }
+        self.last_pass = Some((self.raymarch_shader.clone(), None));
+        if self.record_steps {
+            self.prepare_step_texture();
+        }
+        let (_, pipeline) = self.raymarch_pipeline.as_ref().unwrap();
+        let step_texture = self.step_texture.as_ref().filter(|_| self.record_steps);
+        let size = self.target_size();
+
+        // Storage textures cannot be sRGB, so the shader writes through a linear view
+        let mut resources = vec![BoundResource::RenderTarget {
+            format: self.target_format.remove_srgb_suffix(),
+            mip_level: self.target_mip_level,
+            array_layer: self.target_layer,
+        }];
+        // The step texture is recreated whenever its size changes, clearing the cache
+        resources.extend(step_texture.map(|_| BoundResource::StepTexture));
+        let bind_group = self
+            .bind_groups
+            .get_or_create(LayoutSource::Raymarch, &resources, || {
+                let output_view = Self::layer_view(
+                    &self.render_target,
+                    self.target_format.remove_srgb_suffix(),
+                    self.target_mip_level,
+                    self.target_layer,
+                );
+                let step_view = step_texture
+                    .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+                let mut entries = vec![BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&output_view),
+                }];
+                if let Some(step_view) = &step_view {
+                    entries.push(BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(step_view),
+                    });
+                }
+                self.device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("raymarks.raymarch_bind_group"),
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &entries,
+                })
+            });
+
+        // First, dispatch enough workgroups to cover the whole render target
+        {
+            let mut compute_pass = self.commands.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("raymarks.raymarch_pass"),
+                timestamp_writes: self
+                    .timestamp_queries
+                    .as_ref()
+                    .map(TimestampQueries::compute_pass_writes),
+            });
+            if let Some(queries) = &self.pipeline_statistics_queries {
+                queries.begin_compute_pass(&mut compute_pass);
+            }
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            self.pass_parameters_buffer
+                .set_compute_pass(&mut compute_pass);
+            compute_pass.dispatch_workgroups(
+                size.0.div_ceil(Self::RAYMARCH_WORKGROUP_SIZE),
+                size.1.div_ceil(Self::RAYMARCH_WORKGROUP_SIZE),
+                1,
+            );
+            if self.pipeline_statistics_queries.is_some() {
+                compute_pass.end_pipeline_statistics_query();
+            }
+        }
+
+        // Then, resolve timestamps and copy the render target to the output staging buffer
+        self.finish_pass();
+        Ok(())
+    }
+
+    /// Private method to create the texture raymarch passes write step counts to, unless one of
+    /// the size of the render target already exists.
+    fn prepare_step_texture(&mut self) {
+        let (width, height) = self.target_size();
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        if self
+            .step_texture
+            .as_ref()
+            .is_some_and(|texture| texture.size() == size)
+        {
+            return;
+        }
+        if let Some(step_texture) = self.step_texture.take() {
+            step_texture.destroy();
+            self.bind_groups.clear();
+        }
+        self.step_texture = Some(self.device.create_texture(&TextureDescriptor {
+            label: Some(&format!("raymarks.raymarch_steps.{}x{}", width, height)),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::STEP_FORMAT,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        }));
+    }
+
+    /// Create a new pipeline running the given compute shader as a post-process over the render
+    /// target. The shader reads the rendered image as a sampled texture at binding 0 with a
+    /// sampler at binding 1, writes a storage texture at binding 2, and has a
+    /// `postprocess_shader` entry point. The storage texture's type should be declared as
+    /// `alias OutputTexture = texture_storage_2d<rgba8unorm, write>;`, which is replaced to match
+    /// the target's format. Passes cache the pipeline they use, so this is only
+    /// needed to build a pipeline outside of a pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub fn postprocess_pipeline(&self, shader: Shader) -> Result<ComputePipeline, ShaderError> {
+        debug!(
+            "Creating post-process pipeline for {} and {:?}.",
+            shader.source_file().display(),
+            self.target_format
+        );
+        let source = self
+            .pass_parameters_buffer
+            .adapt_source(Self::adapt_output_texture(
+                shader.load_source_preprocessed()?,
+                self.target_format,
+            ));
+        check_workgroup_size(
+            &source,
+            "postprocess_shader",
+            Self::POSTPROCESS_WORKGROUP_SIZE,
+        )?;
+        let shader = self.labelled_shader_module(&shader, source)?;
+
+        // The layout is explicit, since derived layouts cannot hold push constants
+        let image_layout = self
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("raymarks.postprocess_bind_group_layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::StorageTexture {
+                            access: StorageTextureAccess::WriteOnly,
+                            format: self.target_format.remove_srgb_suffix(),
+                            view_dimension: TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let bind_group_layouts: Vec<&BindGroupLayout> = std::iter::once(&image_layout)
+            .chain(self.pass_parameters_buffer.bind_group_layouts())
+            .collect();
+        let layout = self
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("raymarks.postprocess_pipeline_layout"),
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &self.pass_parameters_buffer.compute_push_constant_ranges(),
+            });
+
+        // Storage textures cannot be sRGB, so the shader encodes sRGB itself when needed
+        let constants = HashMap::from([(
+            "ENCODE_SRGB".to_string(),
+            self.target_format.is_srgb() as u32 as f64,
+        )]);
+
+        Ok(self
+            .device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("raymarks.postprocess_pipeline"),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: Some("postprocess_shader"),
+                compilation_options: PipelineCompilationOptions {
+                    constants: &constants,
+                    ..Default::default()
+                },
+                cache: None,
+            }))
+    }
+
+    /// Create a new post-process pass, running the given compute shader over the render target,
+    /// such as `Shader::Blur`. The pass is timed on its own, separately from the pass which
+    /// rendered the image.
+    pub fn compute_postprocess(&mut self, shader: Shader) -> Result<(), ShaderError> {
+        // Build the pipeline on first use, and reuse it for later passes with the same shader
+        if !matches!(&self.postprocess_pipeline, Some((built, _)) if *built == shader) {
+            let pipeline = self.postprocess_pipeline(shader.clone())?;
+            self.postprocess_pipeline = Some((shader, pipeline));
+            self.bind_groups.clear();
+        }
+
+        // A texture cannot be sampled and written as storage in the same pass, so the shader
+        // reads from a copy of the render target, which is kept until the target changes
+        let (width, height) = self.target_size();
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let stale = self
+            .postprocess_input
+            .as_ref()
+            .is_none_or(|input| input.size() != size || input.format() != self.target_format);
+        if stale {
+            if let Some(input) = self.postprocess_input.take() {
+                input.destroy();
+                self.bind_groups.clear();
+            }
+            self.postprocess_input = Some(self.device.create_texture(&TextureDescriptor {
+                label: Some(&format!("raymarks.postprocess_input.{}x{}", width, height)),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: self.target_format,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            }));
+        }
+        let input = self.postprocess_input.as_ref().unwrap();
+        let (_, pipeline) = self.postprocess_pipeline.as_ref().unwrap();
+
+        // The input is recreated whenever its size or format changes, clearing the cache
+        let resources = [
+            BoundResource::PostprocessInput,
+            BoundResource::LinearSampler,
+            BoundResource::RenderTarget {
+                format: self.target_format.remove_srgb_suffix(),
+                mip_level: self.target_mip_level,
+                array_layer: self.target_layer,
+            },
+        ];
+        let bind_group =
+            self.bind_groups
+                .get_or_create(LayoutSource::Postprocess, &resources, || {
+                    self.device.create_bind_group(&BindGroupDescriptor {
+                        label: Some("raymarks.postprocess_bind_group"),
+                        layout: &pipeline.get_bind_group_layout(0),
+                        entries: &[
+                            BindGroupEntry {
+                                binding: 0,
+                                resource: BindingResource::TextureView(
+                                    &input.create_view(&TextureViewDescriptor::default()),
+                                ),
+                            },
+                            BindGroupEntry {
+                                binding: 1,
+                                resource: BindingResource::Sampler(&self.linear_sampler),
+                            },
+                            BindGroupEntry {
+                                binding: 2,
+                                resource: BindingResource::TextureView(&Self::layer_view(
+                                    &self.render_target,
+                                    self.target_format.remove_srgb_suffix(),
+                                    self.target_mip_level,
+                                    self.target_layer,
+                                )),
+                            },
+                        ],
+                    })
+                });
+
+        // First, copy the render target to the input texture
+        self.commands.copy_texture_to_texture(
+            ImageCopyTexture {
+                texture: &self.render_target,
+                mip_level: self.target_mip_level,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: self.target_layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            ImageCopyTexture {
+                texture: input,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            size,
+        );
+
+        // Then, dispatch enough workgroups to cover the whole render target
+        {
+            let mut compute_pass = self.commands.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("raymarks.postprocess_pass"),
+                timestamp_writes: self
+                    .timestamp_queries
+                    .as_ref()
+                    .map(TimestampQueries::compute_pass_writes),
+            });
+            if let Some(queries) = &self.pipeline_statistics_queries {
+                queries.begin_compute_pass(&mut compute_pass);
+            }
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            self.pass_parameters_buffer
+                .set_compute_pass(&mut compute_pass);
+            compute_pass.dispatch_workgroups(
+                width.div_ceil(Self::POSTPROCESS_WORKGROUP_SIZE),
+                height.div_ceil(Self::POSTPROCESS_WORKGROUP_SIZE),
+                1,
+            );
+            if self.pipeline_statistics_queries.is_some() {
+                compute_pass.end_pipeline_statistics_query();
+            }
+        }
+
+        // Finally, resolve timestamps and copy the render target to the output staging buffer
+        self.finish_pass();
+        Ok(())
+    }
+
+    /// Create a new pipeline drawing the given number of triangles tiled over the render target,
+    /// with vertices generated in the vertex shader rather than read from a vertex buffer. Passes
+    /// cache the pipeline they use, so this is only needed to build a pipeline outside of a pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub fn triangles_pipeline(&self, triangle_count: u32) -> Result<RenderPipeline, ShaderError> {
+        debug!(
+            "Creating triangles pipeline for {} triangles and {:?}.",
+            triangle_count, self.target_format
+        );
+        let shader = self.load_shader(Shader::Triangles)?;
+        // The count decides the grid the triangles are tiled over, so it is built into the shader
+        let constants = HashMap::from([("TRIANGLE_COUNT".to_string(), triangle_count as f64)]);
+
+        Ok(self
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("raymarks.triangles_pipeline"),
+                layout: None,
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some("vertex_shader"),
+                    compilation_options: PipelineCompilationOptions {
+                        constants: &constants,
+                        ..Default::default()
+                    },
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragment_shader"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(self.target_format.into())],
+                }),
+                primitive: PrimitiveState {
+                    polygon_mode: self.polygon_mode,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: self.sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+            }))
+    }
+
+    /// Create a new pass drawing the given number of procedurally generated triangles, tiled in
+    /// pairs over a square grid covering the render target. Without vertex buffers or depth
+    /// testing, this stresses the rasterizer's vertex throughput and fill rate. G-buffer
+    /// attachments are not written.
+    pub fn triangles_pass(&mut self, triangle_count: u32) -> Result<(), ShaderError> {
+        // Build the pipeline on first use, and reuse it for later passes with the same count
+        if !matches!(self.triangles_pipeline, Some((count, _)) if count == triangle_count) {
+            self.triangles_pipeline =
+                Some((triangle_count, self.triangles_pipeline(triangle_count)?));
+        }
+        self.last_pass = Some((Shader::Triangles, Some(triangle_count)));
+        let (_, pipeline) = self.triangles_pipeline.as_ref().unwrap();
+
+        // When multisampling, render to the multisampled target and resolve into the render target
+        let target_view = Self::layer_view(
+            &self.render_target,
+            self.target_format,
+            self.target_mip_level,
+            self.target_layer,
+        );
+        let multisampled_view = self
+            .multisampled_target
+            .as_ref()
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+        let (view, resolve_target) = match &multisampled_view {
+            Some(multisampled_view) => (multisampled_view, Some(&target_view)),
+            None => (&target_view, None),
+        };
+
+        // First, render to the render target
+        {
+            let mut render_pass = self.commands.begin_render_pass(&RenderPassDescriptor {
+                label: Some("raymarks.triangles_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.clear_color),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: self
+                    .timestamp_queries
+                    .as_ref()
+                    .map(TimestampQueries::render_pass_writes),
+            });
+            if let Some(queries) = &self.pipeline_statistics_queries {
+                queries.begin_render_pass(&mut render_pass);
+            }
+            render_pass.set_pipeline(pipeline);
+            render_pass.draw(0..triangle_count * 3, 0..1);
+            if self.pipeline_statistics_queries.is_some() {
+                render_pass.end_pipeline_statistics_query();
+            }
+        }
+
+        // Then, resolve timestamps and copy the render target to the output staging buffer
+        self.finish_pass();
+        Ok(())
+    }
+
+    /// Create a new pipeline drawing a gradient over the render target at the given size, with a
+    /// known color at each corner. Passes cache the pipeline they use, so this is only needed to
+    /// build a pipeline outside of a pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub fn gradient_pipeline(&self, size: (u32, u32)) -> Result<RenderPipeline, ShaderError> {
+        debug!(
+            "Creating gradient pipeline for {}x{} and {:?}.",
+            size.0, size.1, self.target_format
+        );
+        let shader = self.load_shader(Shader::Gradient)?;
+        // The gradient is scaled to the size, so the edge pixels are exactly 0 and 1
+        let constants = HashMap::from([
+            ("WIDTH".to_string(), size.0 as f64),
+            ("HEIGHT".to_string(), size.1 as f64),
+        ]);
+
+        Ok(self
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("raymarks.gradient_pipeline"),
+                layout: None,
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some("vertex_shader"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragment_shader"),
+                    compilation_options: PipelineCompilationOptions {
+                        constants: &constants,
+                        ..Default::default()
+                    },
+                    targets: &[Some(self.target_format.into())],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: self.sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+            }))
+    }
+
+    /// Create a new pass drawing a gradient over the render target, whose red channel increases
+    /// from 0 at the left edge to 1 at the right, green from 0 at the top to 1 at the bottom, and
+    /// blue from 1 at the left to 0 at the right, with an opaque alpha. Reading back the corners
+    /// checks the whole path from shader to saved image, independent of geometry and assets.
+    pub fn gradient_pass(&mut self) -> Result<(), ShaderError> {
+        // Build the pipeline on first use, and reuse it for later passes at the same size
+        let size = self.target_size();
+        if !matches!(self.gradient_pipeline, Some((built, _)) if built == size) {
+            self.gradient_pipeline = Some((size, self.gradient_pipeline(size)?));
+        }
+        self.last_pass = Some((Shader::Gradient, None));
+        let (_, pipeline) = self.gradient_pipeline.as_ref().unwrap();
+
+        // When multisampling, render to the multisampled target and resolve into the render target
+        let target_view = Self::layer_view(
+            &self.render_target,
+            self.target_format,
+            self.target_mip_level,
+            self.target_layer,
+        );
+        let multisampled_view = self
+            .multisampled_target
+            .as_ref()
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+        let (view, resolve_target) = match &multisampled_view {
+            Some(multisampled_view) => (multisampled_view, Some(&target_view)),
+            None => (&target_view, None),
+        };
+
+        // First, render to the render target
+        {
+            let mut render_pass = self.commands.begin_render_pass(&RenderPassDescriptor {
+                label: Some("raymarks.gradient_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.clear_color),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: self
+                    .timestamp_queries
+                    .as_ref()
+                    .map(TimestampQueries::render_pass_writes),
+            });
+            if let Some(queries) = &self.pipeline_statistics_queries {
+                queries.begin_render_pass(&mut render_pass);
+            }
+            render_pass.set_pipeline(pipeline);
+            render_pass.draw(0..3, 0..1);
+            if self.pipeline_statistics_queries.is_some() {
+                render_pass.end_pipeline_statistics_query();
+            }
+        }
+
+        // Then, resolve timestamps and copy the render target to the output staging buffer
+        self.finish_pass();
+        Ok(())
+    }
+
+    /// Create a new pass which clears the render target to the clear color without drawing
+    /// anything, to measure the cost of the clear alone.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(width = self.target_size().0, height = self.target_size().1)
+        )
+    )]
+    pub fn clear_pass(&mut self) {
+        // No shader runs, so images saved after the pass have no shader to be named after
+        self.last_pass = None;
+
+        // When multisampling, clear the multisampled target and resolve into the render target
+        let target_view = Self::layer_view(
+            &self.render_target,
+            self.target_format,
+            self.target_mip_level,
+            self.target_layer,
+        );
+        let multisampled_view = self
+            .multisampled_target
+            .as_ref()
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+        let (view, resolve_target) = match &multisampled_view {
+            Some(multisampled_view) => (multisampled_view, Some(&target_view)),
+            None => (&target_view, None),
+        };
+
+        // First, clear the render target
+        {
+            let mut render_pass = self.commands.begin_render_pass(&RenderPassDescriptor {
+                label: Some("raymarks.clear_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.clear_color),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: self
+                    .timestamp_queries
+                    .as_ref()
+                    .map(TimestampQueries::render_pass_writes),
+            });
+            // Statistics are resolved by every pass, so they are recorded even though nothing runs
+            if let Some(queries) = &self.pipeline_statistics_queries {
+                queries.begin_render_pass(&mut render_pass);
+                render_pass.end_pipeline_statistics_query();
+            }
+        }
+
+        // Then, resolve timestamps and copy the render target to the output staging buffer
+        self.finish_pass();
+    }
+
+    /// Create new pipelines drawing the mask and the fill of stencil passes. The mask writes the
+    /// stencil reference without writing colors, and the fill tests the stencil with the stencil
+    /// face state. Passes cache the pipelines they use, so this is only needed to build them
+    /// outside of a pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn stencil_pipelines(&self) -> Result<(RenderPipeline, RenderPipeline), ShaderError> {
+        debug!(
+            "Creating stencil pipelines for {:?} with {} samples.",
+            self.target_format, self.sample_count
+        );
+        let shader = self.load_shader(Shader::Stencil)?;
+        let constants = HashMap::from([(
+            "MASK_SEGMENTS".to_string(),
+            Self::STENCIL_MASK_SEGMENTS as f64,
+        )]);
+        let pipeline = |label: &str,
+                        vertex_entry_point: &str,
+                        fragment_entry_point: &str,
+                        write_mask: ColorWrites,
+                        face: StencilFaceState| {
+            self.device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: None,
+                    vertex: VertexState {
+                        module: &shader,
+                        entry_point: Some(vertex_entry_point),
+                        compilation_options: PipelineCompilationOptions {
+                            constants: &constants,
+                            ..Default::default()
+                        },
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some(fragment_entry_point),
+                        compilation_options: Default::default(),
+                        targets: &[Some(ColorTargetState {
+                            format: self.target_format,
+                            blend: None,
+                            write_mask,
+                        })],
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: Some(DepthStencilState {
+                        format: Self::STENCIL_FORMAT,
+                        depth_write_enabled: false,
+                        depth_compare: CompareFunction::Always,
+                        stencil: StencilState {
+                            front: face,
+                            back: face,
+                            read_mask: !0,
+                            write_mask: !0,
+                        },
+                        bias: DepthBiasState::default(),
+                    }),
+                    multisample: MultisampleState {
+                        count: self.sample_count,
+                        ..Default::default()
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+        };
+
+        // The mask always passes, replacing the stencil with the reference wherever it is drawn
+        let mask = pipeline(
+            "raymarks.stencil_mask_pipeline",
+            "mask_vertex_shader",
+            "mask_fragment_shader",
+            ColorWrites::empty(),
+            StencilFaceState {
+                compare: CompareFunction::Always,
+                fail_op: StencilOperation::Keep,
+                depth_fail_op: StencilOperation::Keep,
+                pass_op: StencilOperation::Replace,
+            },
+        );
+        let fill = pipeline(
+            "raymarks.stencil_fill_pipeline",
+            "fill_vertex_shader",
+            "fill_fragment_shader",
+            ColorWrites::ALL,
+            self.stencil_face,
+        );
+        Ok((mask, fill))
+    }
+
+    /// Create a new pass drawing a disc into the stencil buffer as a mask, then filling the whole
+    /// render target wherever the stencil test passes. The stencil is cleared to 0 first, so by
+    /// default only the disc is filled.
+    pub fn stencil_pass(&mut self) -> Result<(), ShaderError> {
+        // Build the pipelines on first use, and reuse them for later passes
+        if self.stencil_pipelines.is_none() {
+            self.stencil_pipelines = Some(self.stencil_pipelines()?);
+        }
+        self.last_pass = Some((Shader::Stencil, None));
+
+        // The depth-stencil texture is kept until the target's size or sample count changes
+        let (width, height) = self.target_size();
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let stale = self.stencil_texture.as_ref().is_none_or(|texture| {
+            texture.size() != size || texture.sample_count() != self.sample_count
+        });
+        if stale {
+            if let Some(texture) = self.stencil_texture.take() {
+                texture.destroy();
+            }
+            self.stencil_texture = Some(self.device.create_texture(&TextureDescriptor {
+                label: Some(&format!("raymarks.stencil_texture.{}x{}", width, height)),
+                size,
+                mip_level_count: 1,
+                sample_count: self.sample_count,
+                dimension: TextureDimension::D2,
+                format: Self::STENCIL_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            }));
+        }
+        let stencil_view = self
+            .stencil_texture
+            .as_ref()
+            .unwrap()
+            .create_view(&TextureViewDescriptor::default());
+        let (mask_pipeline, fill_pipeline) = self.stencil_pipelines.as_ref().unwrap();
+
+        // When multisampling, render to the multisampled target and resolve into the render target
+        let target_view = Self::layer_view(
+            &self.render_target,
+            self.target_format,
+            self.target_mip_level,
+            self.target_layer,
+        );
+        let multisampled_view = self
+            .multisampled_target
+            .as_ref()
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+        let (view, resolve_target) = match &multisampled_view {
+            Some(multisampled_view) => (multisampled_view, Some(&target_view)),
+            None => (&target_view, None),
+        };
+
+        // First, draw the mask and then the fill to the render target
+        {
+            let mut render_pass = self.commands.begin_render_pass(&RenderPassDescriptor {
+                label: Some("raymarks.stencil_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.clear_color),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &stencil_view,
+                    depth_ops: None,
+                    stencil_ops: Some(Operations {
+                        load: LoadOp::Clear(0),
+                        store: StoreOp::Discard,
+                    }),
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: self
+                    .timestamp_queries
+                    .as_ref()
+                    .map(TimestampQueries::render_pass_writes),
+            });
+            if let Some(queries) = &self.pipeline_statistics_queries {
+                queries.begin_render_pass(&mut render_pass);
+            }
+            render_pass.set_stencil_reference(self.stencil_reference);
+            render_pass.set_pipeline(mask_pipeline);
+            render_pass.draw(0..Self::STENCIL_MASK_SEGMENTS * 3, 0..1);
+            render_pass.set_pipeline(fill_pipeline);
+            render_pass.draw(0..3, 0..1);
+            if self.pipeline_statistics_queries.is_some() {
+                render_pass.end_pipeline_statistics_query();
+            }
+        }
+
+        // Then, resolve timestamps and copy the render target to the output staging buffer
+        self.finish_pass();
+        Ok(())
+    }
+
+    /// Private method to record commands shared by the end of every pass: resolving timestamps and
+    /// pipeline statistics written during the pass, if supported, and copying the render target
+    /// to the output staging buffer.
+    fn finish_pass(&mut self) {
+        self.pending_passes += 1;
+        if let Some(timestamp_queries) = &self.timestamp_queries {
+            debug!("Resolving timestamp queries.");
+            timestamp_queries.resolve(&mut self.commands);
+        }
+        if let Some(queries) = &self.pipeline_statistics_queries {
+            debug!("Resolving pipeline statistics queries.");
+            queries.resolve(&mut self.commands);
+        }
+        debug!(
+            "Copying mip level {} of {} render target layers to the staging buffer.",
+            self.target_mip_level, self.layer_count
+        );
+
+        Self::copy_to_staging_buffer(
+            &mut self.commands,
+            &self.render_target,
+            TextureAspect::All,
+            self.target_mip_level,
+            0..self.layer_count,
+            &self.output_staging_buffer,
+        );
+    }
+
+    /// To queue all written commands and passes, we swap the old command encoder with a new one, and submit the old one.
+    /// The time taken to finish and submit the old encoder is stored, to be read with `last_submit_timing`.
+    /// If there is a preview window, the render target is then presented to it.
+    /// Returns the submission, which `wait_for_submission` can wait on instead of waiting for the
+    /// device to be idle.
+    ///
+    /// Finished command encoders cannot be reset, so they are not pooled, and a new encoder is
+    /// created for each submission. Encoders are labelled with the debug label and frame number,
+    /// so submissions are identifiable in frame captures.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(pending_passes = self.pending_passes))
+    )]
+    pub fn submit(&mut self) -> Submission {
+        self.frame += 1;
+        let label = Self::encoder_label(&self.debug_label, self.frame);
+        let old_commands = replace(
+            &mut self.commands,
+            self.device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some(&label),
+                }),
+        );
+        let start = Instant::now();
+        let command_buffer = old_commands.finish();
+        let encoded = Instant::now();
+        let index = self.queue.submit(Some(command_buffer));
+        // Registered right after the submission, so the callback covers it and nothing after it
+        let finished = Arc::new(AtomicBool::new(false));
+        let callback_finished = finished.clone();
+        self.queue.on_submitted_work_done(move || {
+            callback_finished.store(true, Ordering::Release);
+        });
+        let submission = Submission { index, finished };
+        self.last_submit_timing = Some(SubmitTiming {
+            encode: encoded - start,
+            submit: encoded.elapsed(),
+        });
+        self.pending_passes = 0;
+
+        #[cfg(feature = "window")]
+        if let Some(preview) = &mut self.preview {
+            let view = Self::layer_view(
+                &self.render_target,
+                self.target_format,
+                self.target_mip_level,
+                self.target_layer,
+            );
+            preview.present(&self.adapter, &self.device, &self.queue, &view);
+        }
+        submission
+    }
+
+    /// Get the label included in the labels of command encoders recording passes.
+    pub fn debug_label(&self) -> &str {
+        &self.debug_label
+    }
+
+    /// Set the label included in the labels of command encoders recording passes, such as the
+    /// name of the running benchmark, so frame captures in tools like RenderDoc show which
+    /// benchmark and frame each submission belongs to. This takes effect from the encoder created
+    /// by the next submission.
+    pub fn set_debug_label(&mut self, label: &str) {
+        self.debug_label = label.to_string();
+    }
+
+    /// Private function to get the label of the command encoder recording the given frame's
+    /// passes, such as `raymarks.bunny_rasterization.frame12`.
+    fn encoder_label(debug_label: &str, frame: u64) -> String {
+        if debug_label.is_empty() {
+            format!("raymarks.frame{}", frame)
+        } else {
+            format!("raymarks.{}.frame{}", debug_label, frame)
+        }
+    }
+
+    /// Block the current thread until the GPU has finished the given submission, without waiting
+    /// for work submitted after it. Polls the device for at most `POLL_TIMEOUT`, so a hung GPU
+    /// returns an error instead of blocking forever.
+    pub fn wait_for_submission(&self, submission: &Submission) -> Result<(), SaveError> {
+        let deadline = Instant::now() + Self::POLL_TIMEOUT;
+        loop {
+            self.device.poll(Maintain::Poll);
+            if submission.is_finished() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(SaveError::GpuTimeout(Self::POLL_TIMEOUT));
+            }
+            // Yield rather than sleep, so waiting adds little latency to wall-clock timings
+            std::thread::yield_now();
+        }
+    }
+
+    /// Submit any recorded passes, then poll the device until the GPU has finished all submitted
+    /// work, for at most `POLL_TIMEOUT`, and release every buffer and texture of the context.
+    ///
+    /// Dropping the context releases its resources in the same order, but discards passes which
+    /// have been recorded and not submitted.
+    pub fn shutdown(mut self) {
+        if self.pending_passes > 0 {
+            self.submit();
+        }
+        if self.wait_until_idle() {
+            debug!("The device is idle, releasing resources.");
+        }
+        // Resources are released when the context is dropped
+    }
+
+    /// Private method to poll the device until it has finished all submitted work, giving up
+    /// after `POLL_TIMEOUT` so a hung GPU cannot hang teardown. Returns whether the device became
+    /// idle.
+    fn wait_until_idle(&self) -> bool {
+        let deadline = Instant::now() + Self::POLL_TIMEOUT;
+        while !self.device.poll(Maintain::Poll).is_queue_empty() {
+            if Instant::now() >= deadline {
+                warn!(
+                    "The GPU did not finish its work within {:?}, so resources are released while \
+                     it may still be using them.",
+                    Self::POLL_TIMEOUT
+                );
+                return false;
+            }
+            std::thread::sleep(Self::POLL_INTERVAL);
+        }
+        true
+    }
+
+    /// Private method to wait until the device is idle, then unmap any mapped buffers and destroy
+    /// buffers and textures. Destroying is idempotent, so this is safe to call more than once.
+    fn release_resources(&mut self) {
+        // Nothing may be destroyed while the GPU could still be using it
+        self.wait_until_idle();
+
+        // Interrupted reads can leave buffers mapped, or with a mapping pending. Reads of the
+        // output staging buffer cancel their own mappings when they fail
+        if let Some(capture_ring) = &self.capture_ring {
+            capture_ring.destroy();
+        }
+        if let Some(timestamp_queries) = &self.timestamp_queries {
+            timestamp_queries.destroy();
+        }
+        if let Some(queries) = &self.pipeline_statistics_queries {
+            queries.destroy();
+        }
+        self.output_staging_buffer.destroy();
+
+        // Inputs read by passes go before the targets they are drawn to
+        for (mesh, instances) in &self.scene {
+            mesh.destroy();
+            instances.destroy();
+        }
+        if let Some(indirect_buffer) = &self.indirect_buffer {
+            indirect_buffer.destroy();
+        }
+        self.clear_generated_indirect_buffer();
+        self.camera_buffer.destroy();
+        self.pass_parameters_buffer.destroy();
+        for texture in &self.textures {
+            texture.destroy();
+        }
+        if let Some(input) = &self.postprocess_input {
+            input.destroy();
+        }
+        if let Some(step_texture) = &self.step_texture {
+            step_texture.destroy();
+        }
+        if let Some(stencil_texture) = &self.stencil_texture {
+            stencil_texture.destroy();
+        }
+        for gbuffer_target in &self.gbuffer_targets {
+            gbuffer_target.destroy();
+        }
+        self.depth_texture.destroy();
+        if let Some(multisampled_target) = &self.multisampled_target {
+            multisampled_target.destroy();
+        }
+        self.render_target.destroy();
+        let stats = self.bind_groups.stats();
+        debug!(
+            "Released the resources of the benchmark context. Bind group cache: {} hits, {} misses.",
+            stats.hits, stats.misses
+        );
+    }
+
+    /// Get the numbers of bind group lookups by passes which reused a cached bind group, and which
+    /// created one, since the context was created.
+    pub fn bind_group_cache_stats(&self) -> BindGroupCacheStats {
+        self.bind_groups.stats()
+    }
+
+    /// Start capturing validation errors raised by the device, such as from recording or
+    /// submitting passes, until the matching `pop_error_scope`. Every scope must be popped, or
+    /// later errors are captured by it instead of the scope they belong to.
+    pub fn push_error_scope(&self) {
+        self.device.push_error_scope(ErrorFilter::Validation);
+    }
+
+    /// Stop capturing validation errors, returning the first one raised since the matching
+    /// `push_error_scope`, if any. Submissions are validated too, so this should be awaited after
+    /// `submit`.
+    pub async fn pop_error_scope(&self) -> Option<wgpu::Error> {
+        self.device.pop_error_scope().await
+    }
+
+    /// Stop capturing validation errors, blocking the current thread until the first one raised
+    /// since the matching `push_error_scope`, if any, is known.
+    pub fn pop_error_scope_sync(&self) -> Option<wgpu::Error> {
+        pollster::block_on(self.pop_error_scope())
+    }
+
+    /// Get the reason the device was lost, such as from a driver crash or reset, or `None` if it
+    /// is still usable. Nothing submitted to a lost device runs, so benchmarks must stop.
+    pub fn device_lost(&self) -> Option<String> {
+        self.device_lost.lock().unwrap().clone()
+    }
+
+    /// Get a flag which, once set from any thread, makes measurements stop at their next
+    /// submission, such as when a watchdog finds a benchmark has run for too long. The flag stays
+    /// set until it is cleared.
+    pub fn abort_handle(&self) -> Arc<AtomicBool> {
+        self.abort.clone()
+    }
+
+    /// Get whether the flag of `abort_handle` is set.
+    pub fn aborted(&self) -> bool {
+        self.abort.load(Ordering::Relaxed)
+    }
+
+    /// Get the CPU time taken by the steps of the last submission, or `None` if nothing has been
+    /// submitted yet.
+    pub fn last_submit_timing(&self) -> Option<SubmitTiming> {
+        self.last_submit_timing
+    }
+
+    /// Get the number of passes recorded per submission by `flush_if_needed`.
+    pub fn batch_size(&self) -> u32 {
+        self.batch_size
+    }
+
+    /// Set the number of passes recorded per submission by `flush_if_needed`, at least 1.
+    ///
+    /// Submitting after every pass lets each pass be timed, since timestamps are only read back
+    /// after a submission. Batching many passes into one submission reduces the CPU and driver
+    /// overhead between passes, which better reflects throughput, but only the last pass of each
+    /// batch is timed because every pass reuses the same timestamp queries.
+    pub fn set_batch_size(&mut self, batch_size: u32) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Get the number of passes recorded since the last submission.
+    pub fn pending_passes(&self) -> u32 {
+        self.pending_passes
+    }
+
+    /// Submit recorded passes once a full batch has been recorded, as set by `set_batch_size`.
+    pub fn flush_if_needed(&mut self) {
+        if self.pending_passes >= self.batch_size {
+            self.submit();
+        }
+    }
+
+    /// Get the GPU time taken by the last submitted pass, blocking the current thread until the
+    /// timestamps have been read from the GPU, for at most `POLL_TIMEOUT`. Returns `None` if the
+    /// adapter does not support timestamp queries, or an error if the timestamps could not be
+    /// read back, such as when the GPU hangs or the device is lost.
+    pub fn last_pass_duration(&self) -> Result<Option<Duration>, SaveError> {
+        self.timestamp_queries
+            .as_ref()
+            .map(|timestamp_queries| timestamp_queries.duration(&self.device, &self.queue))
+            .transpose()
+    }
+
+    /// Get the pipeline statistics of the last submitted pass, blocking the current thread until
+    /// they have been read from the GPU, for at most `POLL_TIMEOUT`. Returns `None` if the adapter
+    /// does not support pipeline statistics queries, or if they could not be read back, which is
+    /// logged as a warning.
+    pub fn last_pass_statistics(&self) -> Option<PipelineStatistics> {
+        let queries = self.pipeline_statistics_queries.as_ref()?;
+        match queries.statistics(&self.device) {
+            Ok(statistics) => Some(statistics),
+            Err(error) => {
+                warn!("Failed to read back pipeline statistics: {}", error);
+                None
+            }
+        }
+    }
+
+    /// Save the target layer of the current render target to a PNG file, returning the path of
+    /// the file. HDR targets are tone-mapped to 8-bit sRGB.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(
+                filename = filename,
+                width = self.target_size().0,
+                height = self.target_size().1,
+            )
+        )
+    )]
+    pub async fn save_render_target(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        self.save_render_target_as(filename, ImageFormat::Png).await
+    }
+
+    /// Save the current render target to an image file of the given format in the image
+    /// directory. The file extension follows the format.
+    pub async fn save_render_target_as(
+        &self,
+        filename: &str,
+        format: ImageFormat,
+    ) -> Result<PathBuf, SaveError> {
+        self.write_render_target(filename, self.target_layer, format)
+            .await
+    }
+
+    /// Save the given array layer of the current render target to a PNG file, returning the path
+    /// of the file. Every layer is copied to the output staging buffer by each pass, so layers
+    /// rendered one after another can be saved after a single submission.
+    pub async fn save_render_target_layer(
+        &self,
+        filename: &str,
+        array_layer: u32,
+    ) -> Result<PathBuf, SaveError> {
+        if array_layer >= self.layer_count {
+            return Err(SaveError::NoArrayLayer(array_layer));
+        }
+
+        self.write_render_target(filename, array_layer, ImageFormat::Png)
+            .await
+    }
+
+    /// Save the given array layer of the current render target to a PNG file, blocking the
+    /// current thread until the data has been read from the GPU.
+    pub fn save_render_target_layer_sync(
+        &self,
+        filename: &str,
+        array_layer: u32,
+    ) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_render_target_layer(filename, array_layer))
+    }
+
+    /// Private method to save an array layer of the current render target to an image file of
+    /// the given format in the image directory.
+    async fn write_render_target(
+        &self,
+        filename: &str,
+        array_layer: u32,
+        format: ImageFormat,
+    ) -> Result<PathBuf, SaveError> {
+        // Large PNGs are encoded in strips as they are read back, to bound memory use, unless they
+        // must be downsampled as a whole
+        if format == ImageFormat::Png
+            && self.supersample == 1
+            && self.output_staging_buffer.size() / self.layer_count as u64
+                > Self::STREAMED_SAVE_THRESHOLD
+        {
+            return self
+                .write_render_target_streamed(filename, array_layer, Self::STREAMED_ROWS_PER_STRIP)
+                .await;
+        }
+
+        let texture_data = self.read_render_target(array_layer).await?;
+        let size = self.output_size();
+        match format {
+            ImageFormat::Png => self.write_png(
+                filename,
+                size,
+                png::ColorType::Rgba,
+                self.output_encoding,
+                &texture_data,
+            ),
+            _ => self.write_image(filename, size, format, &texture_data),
+        }
+    }
+
+    /// Save the target layer of the current render target to a PNG file, reading back and
+    /// encoding the given number of rows at a time so that only one strip of the image is held in
+    /// memory. This is used automatically by `save_render_target` for large render targets.
+    pub async fn save_render_target_streamed(
+        &self,
+        filename: &str,
+        rows_per_strip: u32,
+    ) -> Result<PathBuf, SaveError> {
+        self.write_render_target_streamed(filename, self.target_layer, rows_per_strip)
+            .await
+    }
+
+    /// Private method to save an array layer of the current render target to a PNG file in
+    /// strips of the given number of rows.
+    async fn write_render_target_streamed(
+        &self,
+        filename: &str,
+        array_layer: u32,
+        rows_per_strip: u32,
+    ) -> Result<PathBuf, SaveError> {
+        let (width, height) = self.target_size();
+        let bytes_per_texel = Self::bytes_per_texel(self.target_format);
+        let unpadded_bytes_per_row = (width * bytes_per_texel) as usize;
+        let padded_bytes_per_row = Self::padded_bytes_per_row(width, bytes_per_texel) as u64;
+        // Layers are copied one after another, so rows are offset by the layers before
+        let layer_offset = array_layer as u64 * padded_bytes_per_row * height as u64;
+
+        let path = self.image_path(filename, (width, height), ImageFormat::Png);
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(&path)?), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        Self::set_png_encoding(&mut encoder, self.output_encoding);
+        let mut png_writer = encoder.write_header()?;
+        {
+            let mut stream_writer = png_writer.stream_writer()?;
+            for first_row in (0..height).step_by(rows_per_strip.max(1) as usize) {
+                let rows = rows_per_strip.max(1).min(height - first_row);
+                let buffer_slice = self.output_staging_buffer.slice(
+                    layer_offset + first_row as u64 * padded_bytes_per_row
+                        ..layer_offset + (first_row + rows) as u64 * padded_bytes_per_row,
+                );
+                self.map_for_reading(&self.output_staging_buffer, buffer_slice)
+                    .await?;
+                let mut strip_data =
+                    Vec::<u8>::with_capacity(unpadded_bytes_per_row * rows as usize);
+                Self::unpad_rows(
+                    &buffer_slice.get_mapped_range(),
+                    padded_bytes_per_row as usize,
+                    unpadded_bytes_per_row,
+                    &mut strip_data,
+                );
+                self.output_staging_buffer.unmap();
+                stream_writer.write_all(&Self::convert_to_rgba8(
+                    self.target_format,
+                    self.output_encoding,
+                    strip_data,
+                ))?;
+            }
+            stream_writer.finish()?;
+        }
+        png_writer.finish()?;
+        Ok(path)
+    }
+
+    /// Save the current render target to a PNG file in strips, blocking the current thread until
+    /// the data has been read from the GPU.
+    pub fn save_render_target_streamed_sync(
+        &self,
+        filename: &str,
+        rows_per_strip: u32,
+    ) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_render_target_streamed(filename, rows_per_strip))
+    }
+
+    /// Save the current render target to an image file of the given format, blocking the current
+    /// thread until the data has been read from the GPU.
+    pub fn save_render_target_as_sync(
+        &self,
+        filename: &str,
+        format: ImageFormat,
+    ) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_render_target_as(filename, format))
+    }
+
+    /// Save the current render target to a PNG file, blocking the current thread until the data has been read from the GPU.
+    pub fn save_render_target_sync(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_render_target(filename))
+    }
+
+    /// Save 8-bit RGBA data of the given size to a PNG file in the image directory, tagged with the
+    /// output encoding, such as images composed from `read_render_target_into` data on the CPU.
+    /// Returns the path of the file.
+    pub fn save_rgba8(
+        &self,
+        filename: &str,
+        size: (u32, u32),
+        data: &[u8],
+    ) -> Result<PathBuf, SaveError> {
+        self.write_png(
+            filename,
+            size,
+            png::ColorType::Rgba,
+            self.output_encoding,
+            data,
+        )
+    }
+
+    /// Render and save the given number of frames of an animation, advancing the time of the pass
+    /// parameters by `dt` seconds before each frame. Each frame is submitted and saved as
+    /// `frame_0000`, `frame_0001` and so on, followed by the size of the render target as for
+    /// `save_render_target`. Returns the paths of the saved frames.
+    ///
+    /// With a capture ring, as set by `set_capture_depth`, frames are read back through it instead,
+    /// so saving each frame overlaps rendering the following ones.
+    pub async fn render_sequence(
+        &mut self,
+        frames: usize,
+        dt: f32,
+        mut pass: impl FnMut(&mut Self) -> Result<(), ShaderError>,
+    ) -> Result<Vec<PathBuf>, SequenceError> {
+        let mut paths = Vec::with_capacity(frames);
+        for frame in 0..frames {
+            self.set_pass_parameters(PassParameters {
+                time: self.pass_parameters.time + dt,
+                ..self.pass_parameters
+            });
+            pass(self)?;
+            self.submit();
+            if self.capture_ring.is_some() {
+                // Frames come back from the ring in order, so far behind the frame just rendered
+                if let Some(captured) = self.capture()? {
+                    paths.push(
+                        self.save_captured_frame(&format!("frame_{:04}", paths.len()), captured)?,
+                    );
+                }
+            } else {
+                paths.push(
+                    self.save_render_target(&format!("frame_{:04}", frame))
+                        .await?,
+                );
+            }
+        }
+        if self.capture_ring.is_some() {
+            for captured in self.finish_captures()? {
+                paths.push(
+                    self.save_captured_frame(&format!("frame_{:04}", paths.len()), captured)?,
+                );
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Get the number of staging buffers frames are captured into before being read back, or 0 if
+    /// there is no capture ring.
+    pub fn capture_depth(&self) -> u32 {
+        self.capture_ring.as_ref().map_or(0, CaptureRing::depth)
+    }
+
+    /// Set the number of staging buffers frames are captured into before being read back,
+    /// replacing any capture ring and dropping the frames in flight in it. A depth of 0 removes
+    /// the ring, so `render_sequence` reads back each frame before rendering the next.
+    ///
+    /// With a ring of depth N, `capture` reads back the frame captured N calls earlier, so the
+    /// GPU copy of each frame overlaps rendering the next ones instead of stalling the CPU.
+    pub fn set_capture_depth(&mut self, depth: u32) {
+        if let Some(capture_ring) = self.capture_ring.take() {
+            capture_ring.destroy();
+        }
+        if depth > 0 {
+            self.capture_ring = Some(CaptureRing::new(
+                &self.device,
+                depth,
+                self.mip_level_size(0),
+                Self::bytes_per_texel(self.target_format),
+            ));
+        }
+    }
+
+    /// Copy the current mip level of the render target into the next buffer of the capture ring
+    /// and submit the copy without waiting for it. Returns the frame previously copied into that
+    /// buffer, which is only waited for if the GPU has not finished copying it yet, or `None`
+    /// until the ring has been filled.
+    pub fn capture(&mut self) -> Result<Option<CapturedFrame>, SaveError> {
+        let size = self.mip_level_size(self.target_mip_level);
+        let capture_ring = self.capture_ring.as_mut().ok_or(SaveError::NoCaptureRing)?;
+        let oldest = capture_ring.take_oldest(&self.device)?;
+
+        let mut commands = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("raymarks.capture"),
+            });
+        Self::copy_to_staging_buffer(
+            &mut commands,
+            &self.render_target,
+            TextureAspect::All,
+            self.target_mip_level,
+            self.target_layer..self.target_layer + 1,
+            capture_ring.next_buffer(),
+        );
+        self.queue.submit(Some(commands.finish()));
+        capture_ring.begin_read(size);
+        Ok(oldest)
+    }
+
+    /// Read back every frame still in flight in the capture ring, oldest first, blocking the
+    /// current thread until the GPU has finished copying them.
+    pub fn finish_captures(&mut self) -> Result<Vec<CapturedFrame>, SaveError> {
+        self.capture_ring
+            .as_mut()
+            .ok_or(SaveError::NoCaptureRing)?
+            .drain(&self.device)
+    }
+
+    /// Save a frame returned by `capture` or `finish_captures` to a PNG file, returning the path
+    /// of the file. HDR targets are tone-mapped to 8-bit sRGB.
+    pub fn save_captured_frame(
+        &self,
+        filename: &str,
+        captured: CapturedFrame,
+    ) -> Result<PathBuf, SaveError> {
+        let texture_data =
+            Self::convert_to_rgba8(self.target_format, self.output_encoding, captured.data);
+        self.write_png(
+            filename,
+            captured.size,
+            png::ColorType::Rgba,
+            self.output_encoding,
+            &texture_data,
+        )
+    }
+
+    /// Render and save the given number of frames of an animation, blocking the current thread
+    /// until every frame has been saved.
+    pub fn render_sequence_sync(
+        &mut self,
+        frames: usize,
+        dt: f32,
+        pass: impl FnMut(&mut Self) -> Result<(), ShaderError>,
+    ) -> Result<Vec<PathBuf>, SequenceError> {
+        pollster::block_on(self.render_sequence(frames, dt, pass))
+    }
+
+    /// Save the given mip level of the render target to a PNG file, returning the path of the
+    /// file, whose name includes the size of the level. HDR targets are tone-mapped to 8-bit
+    /// sRGB.
+    pub async fn save_render_target_mip(
+        &self,
+        filename: &str,
+        mip_level: u32,
+    ) -> Result<PathBuf, SaveError> {
+        if mip_level >= self.mip_level_count {
+            return Err(SaveError::NoMipLevel(mip_level));
+        }
+
+        let size = self.mip_level_size(mip_level);
+        let bytes_per_texel = Self::bytes_per_texel(self.target_format);
+        let staging_buffer = Self::output_staging_buffer(&self.device, size, bytes_per_texel, 1);
+        let mut commands = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("raymarks.save_render_target_mip"),
+            });
+        Self::copy_to_staging_buffer(
+            &mut commands,
+            &self.render_target,
+            TextureAspect::All,
+            mip_level,
+            self.target_layer..self.target_layer + 1,
+            &staging_buffer,
+        );
+        self.queue.submit(Some(commands.finish()));
+
+        let texture_data = self
+            .read_staging_buffer(&staging_buffer, size, bytes_per_texel)
+            .await;
+        staging_buffer.destroy();
+        let texture_data =
+            Self::convert_to_rgba8(self.target_format, self.output_encoding, texture_data?);
+        self.write_png(
+            filename,
+            size,
+            png::ColorType::Rgba,
+            self.output_encoding,
+            &texture_data,
+        )
+    }
+
+    /// Save the given mip level of the render target to a PNG file, blocking the current thread
+    /// until the data has been read from the GPU.
+    pub fn save_render_target_mip_sync(
+        &self,
+        filename: &str,
+        mip_level: u32,
+    ) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_render_target_mip(filename, mip_level))
+    }
+
+    /// Save the region of the target layer of the render target with the given top-left corner
+    /// and size to a PNG file, returning the path of the file, whose name includes the size of
+    /// the region. Only the region is copied back from the GPU, so crops of large render targets
+    /// are quick to save. HDR targets are tone-mapped to 8-bit sRGB.
+    pub async fn save_region(
+        &self,
+        filename: &str,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<PathBuf, SaveError> {
+        let (target_width, target_height) = self.target_size();
+        if width == 0
+            || height == 0
+            || x.checked_add(width)
+                .is_none_or(|right| right > target_width)
+            || y.checked_add(height)
+                .is_none_or(|bottom| bottom > target_height)
+        {
+            return Err(SaveError::RegionOutOfBounds(x, y, width, height));
+        }
+
+        // Rows of the region are padded like rows of the whole target, but to its narrower width
+        let size = (width, height);
+        let bytes_per_texel = Self::bytes_per_texel(self.target_format);
+        let staging_buffer = Self::output_staging_buffer(&self.device, size, bytes_per_texel, 1);
+        let mut commands = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("raymarks.save_region"),
+            });
+        commands.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &self.render_target,
+                mip_level: self.target_mip_level,
+                origin: Origin3d {
+                    x,
+                    y,
+                    z: self.target_layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &staging_buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(Self::padded_bytes_per_row(width, bytes_per_texel)),
+                    rows_per_image: Some(height),
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(Some(commands.finish()));
+
+        let texture_data = self
+            .read_staging_buffer(&staging_buffer, size, bytes_per_texel)
+            .await;
+        staging_buffer.destroy();
+        let texture_data =
+            Self::convert_to_rgba8(self.target_format, self.output_encoding, texture_data?);
+        self.write_png(
+            filename,
+            size,
+            png::ColorType::Rgba,
+            self.output_encoding,
+            &texture_data,
+        )
+    }
+
+    /// Save the region of the render target with the given top-left corner and size to a PNG
+    /// file, blocking the current thread until the data has been read from the GPU.
+    pub fn save_region_sync(
+        &self,
+        filename: &str,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_region(filename, x, y, width, height))
+    }
 
-impl BenchmarkContext {
-    /// Create a new benchmark context, requesting a high-perfomance device which has all features
-    /// required for all benchmarks.
-    pub async fn new() -> Self {
-        let instance: Instance = Instance::default();
-
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::HighPerformance,
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+    /// Compare the current render target against a reference PNG in the reference directory,
+    /// at the path the render would be saved to within the image directory, with the default
+    /// tolerances. Blocks the current thread until the data has been read from the GPU.
+    pub fn compare_to_reference(&self, filename: &str) -> Result<DiffResult, CompareError> {
+        self.compare_to_reference_with(filename, &DiffOptions::default())
+    }
 
-        let (device, queue) = adapter
-            .request_device(&DeviceDescriptor::default(), None)
+    /// Compare the current render target against a reference PNG in the reference directory,
+    /// at the path the render would be saved to within the image directory. On mismatch, a
+    /// `*_diff` heatmap may be written to the image directory.
+    pub fn compare_to_reference_with(
+        &self,
+        filename: &str,
+        options: &DiffOptions,
+    ) -> Result<DiffResult, CompareError> {
+        let size = self.output_size();
+        let (reference, reference_size) = load_png_rgba8(&self.reference_path(filename, size))?;
+        if reference_size != size {
+            return Err(CompareError::SizeMismatch {
+                expected: size,
+                actual: reference_size,
+            });
+        }
+
+        let rendered = pollster::block_on(self.read_render_target(self.target_layer))?;
+        let (mut result, heatmap) = diff_images(&rendered, &reference, options);
+        for &channel in &result.failed_channels {
+            warn!(
+                "The {} channel of {} differs from its reference by up to {}, beyond the tolerance \
+                 of {}.",
+                channel,
+                filename,
+                result.channel_max_differences[channel as usize],
+                options.max_difference.channel(channel)
+            );
+        }
+        if !result.passed && options.write_diff_image {
+            result.diff_image = Some(self.write_png(
+                &format!("{}_diff", filename),
+                size,
+                png::ColorType::Rgba,
+                ColorEncoding::Srgb,
+                &heatmap,
+            )?);
+        }
+        Ok(result)
+    }
+
+    /// Read back the target layer of the render target as 8-bit RGBA data into the given buffer,
+    /// replacing its contents but reusing its allocation, so capturing many frames does not
+    /// allocate for each. The data is tone-mapped, encoded and downsampled by the supersampling
+    /// factor as it would be when saved, without encoding an image.
+    pub async fn read_render_target_into(&self, buffer: &mut Vec<u8>) -> Result<(), SaveError> {
+        self.read_render_target_layer_into(self.target_layer, buffer)
             .await
-            .unwrap();
+    }
 
-        let commands = device.create_command_encoder(&CommandEncoderDescriptor::default());
+    /// Private method to read back an array layer of the render target as 8-bit RGBA data into
+    /// the given buffer, replacing its contents.
+    async fn read_render_target_layer_into(
+        &self,
+        array_layer: u32,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SaveError> {
+        self.read_staging_buffer_into(
+            &self.output_staging_buffer,
+            self.target_size(),
+            Self::bytes_per_texel(self.target_format),
+            array_layer,
+            buffer,
+        )
+        .await?;
+        Self::convert_to_rgba8_in_place(self.target_format, self.output_encoding, buffer);
+        if self.supersample > 1 {
+            *buffer = Self::downsample_rgba8(
+                buffer,
+                self.target_size(),
+                self.output_size(),
+                self.output_encoding,
+            );
+        }
+        Ok(())
+    }
 
-        // Create default render target of size 1024x1024
-        let render_target = Self::render_target(&device, (1024, 1024));
-        let output_staging_buffer = Self::output_staging_buffer(&device, (1024, 1024));
+    /// Read back the render target as 8-bit RGBA data into the given buffer, blocking the current
+    /// thread until the data has been read from the GPU.
+    pub fn read_render_target_into_sync(&self, buffer: &mut Vec<u8>) -> Result<(), SaveError> {
+        pollster::block_on(self.read_render_target_into(buffer))
+    }
 
-        info!(
-            "Context initialized. GPU adapter info: {:?}",
-            adapter.get_info()
+    /// Private method to read back an array layer of the render target as 8-bit RGBA data into a
+    /// new buffer, tone-mapping HDR targets.
+    async fn read_render_target(&self, array_layer: u32) -> Result<Vec<u8>, SaveError> {
+        let mut texture_data = Vec::new();
+        self.read_render_target_layer_into(array_layer, &mut texture_data)
+            .await?;
+        Ok(texture_data)
+    }
+
+    /// Get the size of images read back or saved from the mip level passes render into, which is
+    /// the size of the level divided by the supersampling factor.
+    pub fn output_size(&self) -> (u32, u32) {
+        let (width, height) = self.target_size();
+        (
+            (width / self.supersample).max(1),
+            (height / self.supersample).max(1),
+        )
+    }
+
+    /// Private method to box-filter 8-bit RGBA data of the given size down to the given smaller
+    /// size, averaging each block of pixels. sRGB colors are averaged in linear space, so edges
+    /// are not darkened.
+    fn downsample_rgba8(
+        data: &[u8],
+        size: (u32, u32),
+        output_size: (u32, u32),
+        encoding: ColorEncoding,
+    ) -> Vec<u8> {
+        let (width, height) = size;
+        let (output_width, output_height) = output_size;
+        let to_linear = |value: u8, channel: usize| match encoding {
+            ColorEncoding::Srgb if channel != 3 => srgb_to_linear(value as f32 / 255.0),
+            _ => value as f32 / 255.0,
+        };
+        let from_linear = |value: f32, channel: usize| {
+            let value = match encoding {
+                ColorEncoding::Srgb if channel != 3 => linear_to_srgb(value),
+                _ => value,
+            };
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+
+        let mut output = Vec::with_capacity((output_width * output_height * 4) as usize);
+        for output_y in 0..output_height {
+            // Blocks cover the whole target, even when its size is not a multiple of the output's
+            let rows = output_y * height / output_height..(output_y + 1) * height / output_height;
+            for output_x in 0..output_width {
+                let columns =
+                    output_x * width / output_width..(output_x + 1) * width / output_width;
+                let mut sum = [0.0f32; 4];
+                for y in rows.clone() {
+                    for x in columns.clone() {
+                        let pixel = ((y * width + x) * 4) as usize;
+                        for (channel, total) in sum.iter_mut().enumerate() {
+                            *total += to_linear(data[pixel + channel], channel);
+                        }
+                    }
+                }
+                let count = (rows.len() * columns.len()).max(1) as f32;
+                for (channel, total) in sum.into_iter().enumerate() {
+                    output.push(from_linear(total / count, channel));
+                }
+            }
+        }
+        output
+    }
+
+    /// Private method to convert unpadded texture data of the given target format to 8-bit RGBA
+    /// data with the given encoding, tone-mapping HDR formats.
+    fn convert_to_rgba8(
+        format: TextureFormat,
+        encoding: ColorEncoding,
+        mut texture_data: Vec<u8>,
+    ) -> Vec<u8> {
+        Self::convert_to_rgba8_in_place(format, encoding, &mut texture_data);
+        texture_data
+    }
+
+    /// Private method to convert unpadded texture data of the given target format to 8-bit RGBA
+    /// data with the given encoding in place, tone-mapping HDR formats.
+    fn convert_to_rgba8_in_place(
+        format: TextureFormat,
+        encoding: ColorEncoding,
+        texture_data: &mut Vec<u8>,
+    ) {
+        if format == TextureFormat::Rgba16Float {
+            // Each 8-bit value is written at or before the 16-bit value it is read from
+            let values = texture_data.len() / 2;
+            for index in 0..values {
+                let bits = [texture_data[index * 2], texture_data[index * 2 + 1]];
+                let value = f16_to_f32(u16::from_le_bytes(bits));
+                // Alpha is linear, so it is only clamped
+                texture_data[index] = if index % 4 == 3 {
+                    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+                } else {
+                    tonemap_to_srgb8(value)
+                };
+            }
+            texture_data.truncate(values);
+        }
+        if encoding == ColorEncoding::Linear {
+            // Alpha is linear, so it is left unchanged
+            for (index, value) in texture_data.iter_mut().enumerate() {
+                if index % 4 != 3 {
+                    *value = srgb8_to_linear8(*value);
+                }
+            }
+        }
+    }
+
+    /// Save a color attachment of the last rasterization pass to a PNG file, returning the path of
+    /// the file. Index 0 is the render target, and later indices are G-buffer attachments. HDR
+    /// attachments are tone-mapped to 8-bit sRGB.
+    pub async fn save_color_attachment(
+        &self,
+        filename: &str,
+        index: usize,
+    ) -> Result<PathBuf, SaveError> {
+        let Some(gbuffer_index) = index.checked_sub(1) else {
+            return self.save_render_target(filename).await;
+        };
+        let (Some(texture), Some(&format)) = (
+            self.gbuffer_targets.get(gbuffer_index),
+            self.gbuffer_formats.get(gbuffer_index),
+        ) else {
+            return Err(SaveError::NoAttachment(index));
+        };
+
+        let size = self.target_size();
+        let bytes_per_texel = Self::bytes_per_texel(format);
+        let staging_buffer = Self::output_staging_buffer(&self.device, size, bytes_per_texel, 1);
+        let mut commands = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("raymarks.save_color_attachment"),
+            });
+        Self::copy_to_staging_buffer(
+            &mut commands,
+            texture,
+            TextureAspect::All,
+            self.target_mip_level,
+            0..1,
+            &staging_buffer,
         );
+        self.queue.submit(Some(commands.finish()));
 
-        Self {
-            device,
-            queue,
-            commands,
-            render_target,
-            output_staging_buffer,
+        let texture_data = self
+            .read_staging_buffer(&staging_buffer, size, bytes_per_texel)
+            .await;
+        staging_buffer.destroy();
+        let texture_data = Self::convert_to_rgba8(format, self.output_encoding, texture_data?);
+        self.write_png(
+            filename,
+            size,
+            png::ColorType::Rgba,
+            self.output_encoding,
+            &texture_data,
+        )
+    }
+
+    /// Save a color attachment of the last rasterization pass to a PNG file, blocking the current
+    /// thread until the data has been read from the GPU.
+    pub fn save_color_attachment_sync(
+        &self,
+        filename: &str,
+        index: usize,
+    ) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_color_attachment(filename, index))
+    }
+
+    /// Private method to check the adapter can copy depth textures to buffers, which downlevel
+    /// adapters such as OpenGL cannot.
+    fn check_depth_copies(&self) -> Result<(), SaveError> {
+        if self
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::DEPTH_TEXTURE_AND_BUFFER_COPIES)
+        {
+            Ok(())
+        } else {
+            Err(SaveError::DepthCopyUnsupported)
         }
     }
 
-    /// Create a new benchmark context, blocking the current thread until the GPU is ready.
-    pub fn new_sync() -> Self {
-        pollster::block_on(Self::new())
+    /// Save the depth buffer of the last rasterization pass to a grayscale PNG file, for debugging
+    /// occlusion, returning the path of the file. Nearer surfaces are darker.
+    pub async fn save_depth_buffer(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        if self.sample_count > 1 {
+            return Err(SaveError::Multisampled);
+        }
+        self.check_depth_copies()?;
+
+        let size = self.target_size();
+        let bytes_per_texel = Self::bytes_per_texel(Self::DEPTH_FORMAT);
+        let staging_buffer = Self::output_staging_buffer(&self.device, size, bytes_per_texel, 1);
+        let mut commands = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("raymarks.save_depth_buffer"),
+            });
+        Self::copy_to_staging_buffer(
+            &mut commands,
+            &self.depth_texture,
+            TextureAspect::DepthOnly,
+            self.target_mip_level,
+            0..1,
+            &staging_buffer,
+        );
+        self.queue.submit(Some(commands.finish()));
+
+        let depth_data = self
+            .read_staging_buffer(&staging_buffer, size, bytes_per_texel)
+            .await;
+        staging_buffer.destroy();
+        let depth_data = depth_data?;
+        let grayscale_data = depth_data
+            .chunks_exact(4)
+            .map(|depth| {
+                let depth = f32::from_le_bytes(depth.try_into().unwrap());
+                (depth.clamp(0.0, 1.0) * 255.0) as u8
+            })
+            .collect::<Vec<_>>();
+        // Depth values are linear
+        self.write_png(
+            filename,
+            size,
+            png::ColorType::Grayscale,
+            ColorEncoding::Linear,
+            &grayscale_data,
+        )
     }
 
-    /// Load a shader from the `src/shaders` directory.
-    pub fn load_shader(&self, shader: Shader) -> ShaderModule {
-        self.device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(shader.load_source().into()),
+    /// Save the depth buffer of the last rasterization pass to a grayscale PNG file, blocking the
+    /// current thread until the data has been read from the GPU.
+    pub fn save_depth_buffer_sync(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_depth_buffer(filename))
+    }
+
+    /// Save the depth buffer of the last rasterization pass to a PNG heatmap, for debugging
+    /// occlusion, returning the path of the file. Depth is linearized with the camera's near and
+    /// far planes, normalized between the nearest and farthest pixels drawn to, and colored on the
+    /// turbo ramp from blue for near to red for far. Pixels nothing was drawn to are black.
+    ///
+    /// Multisampled depth cannot be copied to a buffer, so its first sample is resolved into a
+    /// color target by a small pass first.
+    pub async fn save_depth_target(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        if self.sample_count == 1 {
+            self.check_depth_copies()?;
+        }
+        let size = self.target_size();
+        let bytes_per_texel = Self::bytes_per_texel(Self::DEPTH_FORMAT);
+        let staging_buffer = Self::output_staging_buffer(&self.device, size, bytes_per_texel, 1);
+        let mut commands = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("raymarks.save_depth_target"),
+            });
+        let resolved_depth = if self.sample_count > 1 {
+            let resolved_depth = self.resolve_depth(&mut commands, size)?;
+            Self::copy_to_staging_buffer(
+                &mut commands,
+                &resolved_depth,
+                TextureAspect::All,
+                0,
+                0..1,
+                &staging_buffer,
+            );
+            Some(resolved_depth)
+        } else {
+            Self::copy_to_staging_buffer(
+                &mut commands,
+                &self.depth_texture,
+                TextureAspect::DepthOnly,
+                self.target_mip_level,
+                0..1,
+                &staging_buffer,
+            );
+            None
+        };
+        self.queue.submit(Some(commands.finish()));
+
+        let depth_data = self
+            .read_staging_buffer(&staging_buffer, size, bytes_per_texel)
+            .await;
+        staging_buffer.destroy();
+        if let Some(resolved_depth) = resolved_depth {
+            resolved_depth.destroy();
+        }
+
+        // Pixels still at the cleared depth of 1 were not drawn to, so are left out of the range
+        let distances = depth_data?
+            .chunks_exact(4)
+            .map(|depth| {
+                let depth = f32::from_le_bytes(depth.try_into().unwrap());
+                (depth < 1.0).then(|| self.camera.linear_depth(depth))
+            })
+            .collect::<Vec<_>>();
+        let (nearest, farthest) = distances.iter().flatten().fold(
+            (f32::INFINITY, f32::NEG_INFINITY),
+            |(nearest, farthest), &distance| (nearest.min(distance), farthest.max(distance)),
+        );
+        let range = (farthest - nearest).max(f32::EPSILON);
+        let heatmap_data = distances
+            .iter()
+            .flat_map(|distance| match distance {
+                Some(distance) => turbo((distance - nearest) / range),
+                None => [0; 3],
+            })
+            .collect::<Vec<_>>();
+        self.write_png(
+            filename,
+            size,
+            png::ColorType::Rgb,
+            ColorEncoding::Srgb,
+            &heatmap_data,
+        )
+    }
+
+    /// Save the depth buffer of the last rasterization pass to a PNG heatmap, blocking the current
+    /// thread until the data has been read from the GPU.
+    pub fn save_depth_target_sync(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_depth_target(filename))
+    }
+
+    /// Save the number of steps each pixel's ray took in the last raymarch pass recording steps to
+    /// a PNG heatmap, returning the path of the file. Step counts are colored on the turbo ramp
+    /// from blue for a single step to red for `RAYMARCH_MAX_STEPS`, so heatmaps of different
+    /// passes share a scale. The mean and maximum step counts are logged, to quantify the cost
+    /// of the pass.
+    ///
+    /// Steps are only recorded while `set_record_steps` is enabled, so this returns
+    /// `SaveError::NoStepCounts` if no pass has recorded them yet.
+    pub async fn save_step_heatmap(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        let step_texture = self.step_texture.as_ref().ok_or(SaveError::NoStepCounts)?;
+        let size = (step_texture.width(), step_texture.height());
+        let bytes_per_texel = Self::bytes_per_texel(Self::STEP_FORMAT);
+        let staging_buffer = Self::output_staging_buffer(&self.device, size, bytes_per_texel, 1);
+        let mut commands = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("raymarks.save_step_heatmap"),
+            });
+        Self::copy_to_staging_buffer(
+            &mut commands,
+            step_texture,
+            TextureAspect::All,
+            0,
+            0..1,
+            &staging_buffer,
+        );
+        self.queue.submit(Some(commands.finish()));
+
+        let step_data = self
+            .read_staging_buffer(&staging_buffer, size, bytes_per_texel)
+            .await;
+        staging_buffer.destroy();
+        let steps = step_data?
+            .chunks_exact(4)
+            .map(|steps| u32::from_le_bytes(steps.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let mean = steps.iter().map(|&steps| steps as f64).sum::<f64>() / steps.len() as f64;
+        info!(
+            "Raymarch steps per pixel at {}x{}: mean {:.1}, max {} of {}.",
+            size.0,
+            size.1,
+            mean,
+            steps.iter().max().unwrap_or(&0),
+            Self::RAYMARCH_MAX_STEPS
+        );
+
+        let heatmap_data = steps
+            .iter()
+            .flat_map(|&steps| {
+                turbo(steps.saturating_sub(1) as f32 / (Self::RAYMARCH_MAX_STEPS - 1) as f32)
             })
+            .collect::<Vec<_>>();
+        self.write_png(
+            filename,
+            size,
+            png::ColorType::Rgb,
+            ColorEncoding::Srgb,
+            &heatmap_data,
+        )
     }
 
-    /// Create a new rasterization pipeline.
-    pub fn rasterization_pipeline(&self) -> RenderPipeline {
-        let shader = self.load_shader(Shader::Rasterization);
+    /// Save the number of steps each pixel's ray took in the last raymarch pass recording steps to
+    /// a PNG heatmap, blocking the current thread until the data has been read from the GPU.
+    pub fn save_step_heatmap_sync(&self, filename: &str) -> Result<PathBuf, SaveError> {
+        pollster::block_on(self.save_step_heatmap(filename))
+    }
 
-        self.device
+    /// Private method to record a pass copying the first sample of the multisampled depth buffer
+    /// into a new single-sampled `R32Float` texture of the given size, which can be copied to a
+    /// buffer.
+    fn resolve_depth(
+        &self,
+        commands: &mut CommandEncoder,
+        size: (u32, u32),
+    ) -> Result<Texture, ShaderError> {
+        debug!("Resolving the multisampled depth buffer for readback.");
+        let shader = self.load_shader(Shader::DepthResolve)?;
+        let pipeline = self
+            .device
             .create_render_pipeline(&RenderPipelineDescriptor {
-                label: None,
+                label: Some("raymarks.depth_resolve_pipeline"),
                 layout: None,
                 vertex: VertexState {
                     module: &shader,
@@ -89,150 +4715,711 @@ impl BenchmarkContext {
                     module: &shader,
                     entry_point: Some("fragment_shader"),
                     compilation_options: Default::default(),
-                    targets: &[Some(TextureFormat::Rgba8UnormSrgb.into())],
+                    targets: &[Some(TextureFormat::R32Float.into())],
                 }),
                 primitive: PrimitiveState::default(),
                 depth_stencil: None,
                 multisample: MultisampleState::default(),
                 multiview: None,
                 cache: None,
-            })
+            });
+        let resolved_depth = Self::render_target(
+            &self.device,
+            "resolved_depth",
+            Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            TextureFormat::R32Float,
+            1,
+            TextureUsages::empty(),
+            &[],
+        );
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("raymarks.depth_resolve_bind_group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&self.depth_texture.create_view(
+                    &TextureViewDescriptor {
+                        aspect: TextureAspect::DepthOnly,
+                        ..Default::default()
+                    },
+                )),
+            }],
+        });
+
+        let mut render_pass = commands.begin_render_pass(&RenderPassDescriptor {
+            label: Some("raymarks.depth_resolve_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &resolved_depth.create_view(&TextureViewDescriptor::default()),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::WHITE),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        drop(render_pass);
+        Ok(resolved_depth)
     }
 
-    /// Resize the render target and output staging buffer to the given size.
-    pub fn resize_render_target(&mut self, size: (u32, u32)) {
-        self.render_target.destroy();
-        self.render_target = Self::render_target(&self.device, size);
-        self.output_staging_buffer.destroy();
-        self.output_staging_buffer = Self::output_staging_buffer(&self.device, size);
+    /// Environment variable overriding the default directory images are saved to.
+    pub const IMAGE_DIRECTORY_VARIABLE: &str = "RAYMARKS_IMAGE_DIR";
+
+    /// Get the directory images are saved to when the builder does not set one. This is the
+    /// directory named by the `RAYMARKS_IMAGE_DIR` environment variable if it is set, then the
+    /// crate's `images` directory when running from the source tree, and otherwise `images` in
+    /// the current directory.
+    pub fn default_image_directory() -> PathBuf {
+        if let Some(directory) = var_os(Self::IMAGE_DIRECTORY_VARIABLE) {
+            return PathBuf::from(directory);
+        }
+        let manifest_directory = Path::new(env!("CARGO_MANIFEST_DIR"));
+        if manifest_directory.is_dir() {
+            manifest_directory.join("images")
+        } else {
+            PathBuf::from("images")
+        }
+    }
+
+    /// Get the directory containing the images saved by the benchmark.
+    pub fn image_directory(&self) -> &Path {
+        &self.image_directory
     }
 
-    /// Create a new rasterization pass.
-    pub fn rasterization_pass(&mut self) {
-        let pipeline = self.rasterization_pipeline();
+    /// Environment variable overriding the default directory reference images are read from.
+    pub const REFERENCE_DIRECTORY_VARIABLE: &str = "RAYMARKS_REFERENCE_DIR";
 
-        // First, render to the render target
+    /// Get the directory reference images are read from when the builder does not set one. This
+    /// is the directory named by the `RAYMARKS_REFERENCE_DIR` environment variable if it is set,
+    /// then the crate's `references` directory when running from the source tree, and otherwise
+    /// `references` in the current directory. References are kept apart from saved images, so
+    /// saving a render cannot overwrite the reference it is compared against.
+    pub fn default_reference_directory() -> PathBuf {
+        if let Some(directory) = var_os(Self::REFERENCE_DIRECTORY_VARIABLE) {
+            return PathBuf::from(directory);
+        }
+        let manifest_directory = Path::new(env!("CARGO_MANIFEST_DIR"));
+        if manifest_directory.is_dir() {
+            manifest_directory.join("references")
+        } else {
+            PathBuf::from("references")
+        }
+    }
+
+    /// Get the directory reference images are read from.
+    pub fn reference_directory(&self) -> &Path {
+        &self.reference_directory
+    }
+
+    /// Get the template of the paths images are saved to, relative to the image directory.
+    pub fn filename_template(&self) -> &str {
+        &self.filename_template
+    }
+
+    /// Set the template of the paths images are saved to, relative to the image directory, such
+    /// as `{backend}/{shader}/{name}_{w}x{h}_{count}.{ext}`. Subdirectories are created as
+    /// needed. The tokens substituted are:
+    ///
+    /// - `{name}`: the filename given when saving
+    /// - `{w}` and `{h}`: the width and height of the image
+    /// - `{ext}`: the extension of the image format
+    /// - `{backend}`: the adapter's backend, such as `vulkan`
+    /// - `{adapter}`: the adapter's name
+    /// - `{shader}`: the source file name of the last pass's shader, without its extension
+    /// - `{count}`: the number of instances or triangles drawn by the last pass
+    ///
+    /// `{shader}` and `{count}` are empty when no pass has run, or the last pass drew no count.
+    pub fn set_filename_template(&mut self, template: &str) {
+        self.filename_template = template.to_string();
+    }
+
+    /// Get the render target texture, for use with the usages and view formats added through the
+    /// builder.
+    pub fn render_target_texture(&self) -> &Texture {
+        &self.render_target
+    }
+
+    /// Private method to create a render target texture of the given size and format, with the
+    /// given additional usages and view formats, labelled with the given name and its size. sRGB
+    /// formats are stored as linear so they can be used as storage textures, but rendered to
+    /// through sRGB views.
+    fn render_target(
+        device: &Device,
+        name: &str,
+        size: Extent3d,
+        format: TextureFormat,
+        mip_level_count: u32,
+        usage: TextureUsages,
+        view_formats: &[TextureFormat],
+    ) -> Texture {
+        let mut all_view_formats = vec![format];
+        all_view_formats.extend(
+            view_formats
+                .iter()
+                .filter(|&&view_format| view_format != format),
+        );
+
+        device.create_texture(&TextureDescriptor {
+            label: Some(&format!("raymarks.{}.{}x{}", name, size.width, size.height)),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: format.remove_srgb_suffix(),
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::STORAGE_BINDING
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | usage,
+            view_formats: &all_view_formats,
+        })
+    }
+
+    /// Private method to create a two-dimensional view of a single mip level and array layer of a
+    /// texture, in the given format.
+    fn layer_view(
+        texture: &Texture,
+        format: TextureFormat,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> TextureView {
+        texture.create_view(&TextureViewDescriptor {
+            format: Some(format),
+            dimension: Some(TextureViewDimension::D2),
+            base_mip_level: mip_level,
+            mip_level_count: Some(1),
+            base_array_layer: array_layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        })
+    }
+
+    /// Private method to compute the size in bytes of a texture, including all of its mip levels.
+    fn texture_bytes(texture: &Texture) -> u64 {
+        (0..texture.mip_level_count())
+            .map(|mip_level| {
+                let size = texture
+                    .size()
+                    .mip_level_size(mip_level, texture.dimension());
+                size.width as u64
+                    * size.height as u64
+                    * size.depth_or_array_layers as u64
+                    * texture.sample_count() as u64
+                    * Self::allocated_bytes_per_texel(texture.format()) as u64
+            })
+            .sum()
+    }
+
+    /// Private method to check whether a texture format supports the given sample count on this
+    /// device. Without adapter-specific format features, only the guaranteed counts are allowed.
+    fn sample_count_supported(&self, format: TextureFormat, sample_count: u32) -> bool {
+        let format_features = if self
+            .features()
+            .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
         {
-            let mut render_pass = self.commands.begin_render_pass(&RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &self
-                        .render_target
-                        .create_view(&TextureViewDescriptor::default()),
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color::BLACK),
-                        store: StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-            render_pass.set_pipeline(&pipeline);
-            render_pass.draw(0..3, 0..1);
+            self.adapter.get_texture_format_features(format)
+        } else {
+            format.guaranteed_format_features(self.features())
+        };
+        format_features.flags.sample_count_supported(sample_count)
+    }
+
+    /// Private method to create a multisampled render target, or `None` if not multisampling.
+    fn multisampled_target(
+        device: &Device,
+        size: (u32, u32),
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> Option<Texture> {
+        if sample_count <= 1 {
+            return None;
         }
 
-        // Then, copy the render target to the output staging buffer
-        self.commands.copy_texture_to_buffer(
+        Some(device.create_texture(&TextureDescriptor {
+            label: Some(&format!(
+                "raymarks.multisampled_target.{}x{}",
+                size.0, size.1
+            )),
+            size: Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }))
+    }
+
+    /// Private method to create a depth texture.
+    fn depth_texture(
+        device: &Device,
+        size: (u32, u32),
+        mip_level_count: u32,
+        sample_count: u32,
+    ) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some(&format!("raymarks.depth_texture.{}x{}", size.0, size.1)),
+            size: Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            // Multisampled depth is read by a resolve pass, since it cannot be copied
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::COPY_SRC
+                | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    /// Private method to record a copy of one aspect of a mip level of a range of array layers of
+    /// a texture into a staging buffer, one image after another.
+    fn copy_to_staging_buffer(
+        commands: &mut CommandEncoder,
+        texture: &Texture,
+        aspect: TextureAspect,
+        mip_level: u32,
+        array_layers: Range<u32>,
+        buffer: &Buffer,
+    ) {
+        let size = texture
+            .size()
+            .mip_level_size(mip_level, texture.dimension());
+        commands.copy_texture_to_buffer(
             ImageCopyTexture {
-                texture: &self.render_target,
-                mip_level: 0,
-                origin: Origin3d::ZERO,
-                aspect: TextureAspect::All,
+                texture,
+                mip_level,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: array_layers.start,
+                },
+                aspect,
             },
             ImageCopyBuffer {
-                buffer: &self.output_staging_buffer,
+                buffer,
                 layout: ImageDataLayout {
                     offset: 0,
-                    // This needs to be a multiple of 256. Normally we would need to pad
-                    // it but we here know it will work out anyways.
-                    bytes_per_row: Some(self.render_target.width() * 4),
-                    rows_per_image: Some(self.render_target.height()),
+                    // This needs to be a multiple of 256, so rows are padded
+                    bytes_per_row: Some(Self::padded_bytes_per_row(
+                        size.width,
+                        texture.format().block_copy_size(Some(aspect)).unwrap(),
+                    )),
+                    rows_per_image: Some(size.height),
                 },
             },
             Extent3d {
-                width: self.render_target.width(),
-                height: self.render_target.height(),
-                depth_or_array_layers: 1,
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: array_layers.len() as u32,
             },
         );
     }
 
-    /// To queue all written commands and passes, we swap the old command encoder with a new one, and submit the old one.
-    pub fn submit(&mut self) {
-        let old_commands = replace(
-            &mut self.commands,
-            self.device
-                .create_command_encoder(&CommandEncoderDescriptor::default()),
+    /// Private method to read back an image of the given size from a staging buffer into a new
+    /// buffer, stripping the padding from the end of each row.
+    async fn read_staging_buffer(
+        &self,
+        buffer: &Buffer,
+        size: (u32, u32),
+        bytes_per_texel: u32,
+    ) -> Result<Vec<u8>, SaveError> {
+        let mut texture_data = Vec::new();
+        self.read_staging_buffer_into(buffer, size, bytes_per_texel, 0, &mut texture_data)
+            .await?;
+        Ok(texture_data)
+    }
+
+    /// Private method to read back the image of the given size and array layer from a staging
+    /// buffer into the given buffer, replacing its contents and stripping the padding from the end
+    /// of each row. The staging buffer may be larger than the images it holds.
+    async fn read_staging_buffer_into(
+        &self,
+        buffer: &Buffer,
+        size: (u32, u32),
+        bytes_per_texel: u32,
+        array_layer: u32,
+        texture_data: &mut Vec<u8>,
+    ) -> Result<(), SaveError> {
+        let (width, height) = size;
+        let padded_bytes_per_row = Self::padded_bytes_per_row(width, bytes_per_texel) as usize;
+        texture_data.clear();
+        texture_data.reserve((width * height * bytes_per_texel) as usize);
+        let image_bytes = (padded_bytes_per_row * height as usize) as u64;
+        let offset = array_layer as u64 * image_bytes;
+        let buffer_slice = buffer.slice(offset..offset + image_bytes);
+        self.map_for_reading(buffer, buffer_slice).await?;
+        Self::unpad_rows(
+            &buffer_slice.get_mapped_range(),
+            padded_bytes_per_row,
+            (width * bytes_per_texel) as usize,
+            texture_data,
         );
-        self.queue.submit(Some(old_commands.finish()));
+        buffer.unmap();
+        Ok(())
     }
 
-    /// Save the current render target to a PNG file.
-    pub async fn save_render_target(&self, filename: &str) {
-        let width = self.render_target.width();
-        let height = self.render_target.height();
-        let mut texture_data = Vec::<u8>::with_capacity((width * height * 4) as usize);
-        let buffer_slice = self.output_staging_buffer.slice(..);
+    /// Private method to map a slice of the given staging buffer for reading, waiting until the
+    /// GPU has finished with it. A mapping which times out is cancelled, so the buffer can be
+    /// mapped again or destroyed.
+    async fn map_for_reading(
+        &self,
+        buffer: &Buffer,
+        buffer_slice: BufferSlice<'_>,
+    ) -> Result<(), SaveError> {
         let (sender, receiver) = flume::bounded(1);
-        buffer_slice.map_async(MapMode::Read, move |r| sender.send(r).unwrap());
-        self.device.poll(Maintain::wait()).panic_on_timeout();
-        receiver.recv_async().await.unwrap().unwrap();
-        {
-            let view = buffer_slice.get_mapped_range();
-            texture_data.extend_from_slice(&view[..]);
+        buffer_slice.map_async(MapMode::Read, move |r| {
+            // The reader may have stopped waiting for the mapping
+            let _ = sender.send(r);
+        });
+        // Only poll for the mapping, rather than blocking on the submission copying the render
+        // target, so a hung GPU fails with a timeout
+        let result = Self::wait_until_mapped(&self.device, &receiver);
+        if let Err(SaveError::GpuTimeout(_)) = result {
+            buffer.unmap();
+        }
+        result
+    }
+
+    /// Wait until a mapping requested with the sender of the given receiver has finished, failing
+    /// if it takes longer than `POLL_TIMEOUT`.
+    pub(crate) fn wait_until_mapped(
+        device: &Device,
+        receiver: &Receiver<Result<(), BufferAsyncError>>,
+    ) -> Result<(), SaveError> {
+        // Poll without blocking until the buffer is mapped, so a hung GPU produces an error
+        let deadline = Instant::now() + Self::POLL_TIMEOUT;
+        loop {
+            device.poll(Maintain::Poll);
+            match receiver.try_recv() {
+                Ok(result) => return Ok(result?),
+                Err(TryRecvError::Disconnected) => return Err(SaveError::MapCallbackDropped),
+                Err(TryRecvError::Empty) if Instant::now() >= deadline => {
+                    return Err(SaveError::GpuTimeout(Self::POLL_TIMEOUT))
+                }
+                Err(TryRecvError::Empty) => std::thread::sleep(Self::POLL_INTERVAL),
+            }
         }
+    }
 
-        let mut png_data = Vec::<u8>::with_capacity(texture_data.len());
+    /// Private method to encode image data of the given size to a PNG file in the image
+    /// directory, tagged with the given encoding, creating the directory if it does not exist.
+    fn write_png(
+        &self,
+        filename: &str,
+        size: (u32, u32),
+        color_type: png::ColorType,
+        encoding: ColorEncoding,
+        data: &[u8],
+    ) -> Result<PathBuf, SaveError> {
+        let (width, height) = size;
+        let mut png_data = Vec::<u8>::with_capacity(data.len());
         let mut encoder = png::Encoder::new(std::io::Cursor::new(&mut png_data), width, height);
-        encoder.set_color(png::ColorType::Rgba);
-        let mut png_writer = encoder.write_header().unwrap();
-        png_writer.write_image_data(&texture_data[..]).unwrap();
-        png_writer.finish().unwrap();
+        encoder.set_color(color_type);
+        Self::set_png_encoding(&mut encoder, encoding);
+        let mut png_writer = encoder.write_header()?;
+        png_writer.write_image_data(data)?;
+        png_writer.finish()?;
 
-        let mut file = File::create(
-            Self::image_directory().join(format!("{}_{}x{}.png", filename, width, height)),
-        )
-        .unwrap();
-        file.write_all(&png_data[..]).unwrap();
+        let path = self.image_path(filename, size, ImageFormat::Png);
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut file = File::create(&path)?;
+        file.write_all(&png_data[..])?;
+        Ok(path)
     }
 
-    /// Save the current render target to a PNG file, blocking the current thread until the data has been read from the GPU.
-    pub fn save_render_target_sync(&self, filename: &str) {
-        pollster::block_on(self.save_render_target(filename))
+    /// Private method to tag a PNG with metadata describing how its colors are encoded, so viewers
+    /// interpret them consistently.
+    fn set_png_encoding<W: Write>(encoder: &mut png::Encoder<W>, encoding: ColorEncoding) {
+        match encoding {
+            ColorEncoding::Srgb => {
+                encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+                // Fallback gamma for viewers which ignore the sRGB chunk
+                encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 2.2));
+            }
+            ColorEncoding::Linear => encoder.set_source_gamma(png::ScaledFloat::new(1.0)),
+        }
     }
 
-    /// Get the directory containing the images saved by the benchmark.
-    pub fn image_directory() -> PathBuf {
-        Path::new(env!("CARGO_MANIFEST_DIR")).join("images")
+    /// Private method to encode RGBA data of the given size to an image file of the given format
+    /// with the `image` crate, creating the image directory if it does not exist.
+    fn write_image(
+        &self,
+        filename: &str,
+        size: (u32, u32),
+        format: ImageFormat,
+        rgba_data: &[u8],
+    ) -> Result<PathBuf, SaveError> {
+        let (width, height) = size;
+        let mut image_data = Vec::<u8>::with_capacity(rgba_data.len());
+        match format {
+            ImageFormat::Jpeg { quality } => {
+                // JPEG has no alpha channel, so drop it
+                let rgb_data: Vec<u8> = rgba_data
+                    .chunks_exact(4)
+                    .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+                    .collect();
+                JpegEncoder::new_with_quality(&mut image_data, quality).write_image(
+                    &rgb_data,
+                    width,
+                    height,
+                    ExtendedColorType::Rgb8,
+                )?;
+            }
+            ImageFormat::WebP => WebPEncoder::new_lossless(&mut image_data).write_image(
+                rgba_data,
+                width,
+                height,
+                ExtendedColorType::Rgba8,
+            )?,
+            ImageFormat::Png => PngEncoder::new(&mut image_data).write_image(
+                rgba_data,
+                width,
+                height,
+                ExtendedColorType::Rgba8,
+            )?,
+        }
+
+        let path = self.image_path(filename, size, format);
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut file = File::create(&path)?;
+        file.write_all(&image_data[..])?;
+        Ok(path)
     }
 
-    /// Private method to create a render target texture.
-    fn render_target(device: &Device, size: (u32, u32)) -> Texture {
-        device.create_texture(&TextureDescriptor {
-            label: None,
-            size: Extent3d {
-                width: size.0,
-                height: size.1,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
-            view_formats: &[TextureFormat::Rgba8UnormSrgb],
-        })
+    /// Private method to estimate the number of bytes allocated per texel of a texture format.
+    /// Combined depth-stencil formats cannot be copied, so they have no exact size, but take at
+    /// least 4 bytes.
+    fn allocated_bytes_per_texel(format: TextureFormat) -> u32 {
+        format.block_copy_size(None).unwrap_or(4)
+    }
+
+    /// Private method to get the number of bytes per texel of a single-aspect texture format.
+    fn bytes_per_texel(format: TextureFormat) -> u32 {
+        format.block_copy_size(None).unwrap()
+    }
+
+    /// Private method to get the path of the image with the given filename and size, substituting
+    /// them into the filename template.
+    fn image_path(&self, filename: &str, size: (u32, u32), format: ImageFormat) -> PathBuf {
+        self.image_directory
+            .join(self.relative_image_path(filename, size, format))
+    }
+
+    /// Private method to get the path of the reference PNG compared against the render with the
+    /// given filename and size, which mirrors its path within the image directory.
+    fn reference_path(&self, filename: &str, size: (u32, u32)) -> PathBuf {
+        self.reference_directory
+            .join(self.relative_image_path(filename, size, ImageFormat::Png))
+    }
+
+    /// Private method to substitute the given filename and size into the filename template,
+    /// giving the path of the image relative to the image directory.
+    fn relative_image_path(&self, filename: &str, size: (u32, u32), format: ImageFormat) -> String {
+        let (shader, count) = match &self.last_pass {
+            Some((shader, count)) => (
+                shader
+                    .source_file()
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                count.map(|count| count.to_string()).unwrap_or_default(),
+            ),
+            None => (String::new(), String::new()),
+        };
+        // Adapter names may contain slashes, such as OpenGL renderer strings, which would
+        // otherwise become directories
+        let adapter = self.adapter_info.name.replace(['/', '\\'], "_");
+        self.filename_template
+            .replace("{name}", filename)
+            .replace("{w}", &size.0.to_string())
+            .replace("{h}", &size.1.to_string())
+            .replace("{ext}", format.extension())
+            .replace(
+                "{backend}",
+                &format!("{:?}", self.adapter_info.backend).to_lowercase(),
+            )
+            .replace("{adapter}", &adapter)
+            .replace("{shader}", &shader)
+            .replace("{count}", &count)
+    }
+
+    /// Compute the bytes per row of a texture of the given width, padded to a multiple of
+    /// `COPY_BYTES_PER_ROW_ALIGNMENT` as required for texture-to-buffer copies.
+    pub(crate) fn padded_bytes_per_row(width: u32, bytes_per_texel: u32) -> u32 {
+        (width * bytes_per_texel).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT)
+    }
+
+    /// Private function to replace the declaration of the `OutputTexture` storage texture type in
+    /// shader source with one of the given target format, leaving other storage textures as they
+    /// are.
+    fn adapt_output_texture(source: String, target_format: TextureFormat) -> String {
+        let storage_format = match target_format {
+            TextureFormat::Rgba16Float => "rgba16float",
+            _ => return source,
+        };
+        source.replace(
+            Self::OUTPUT_TEXTURE_DECLARATION,
+            &format!(
+                "alias OutputTexture = texture_storage_2d<{}, write>;",
+                storage_format
+            ),
+        )
     }
 
-    /// Private method to create an output staging buffer.
-    fn output_staging_buffer(device: &Device, size: (u32, u32)) -> Buffer {
+    /// Append rows of the given unpadded length to `data`, dropping the padding from the end of
+    /// each row of `padded`, which are copied from a texture with `padded_bytes_per_row`.
+    pub(crate) fn unpad_rows(
+        padded: &[u8],
+        padded_bytes_per_row: usize,
+        unpadded_bytes_per_row: usize,
+        data: &mut Vec<u8>,
+    ) {
+        for row in padded.chunks(padded_bytes_per_row) {
+            data.extend_from_slice(&row[..unpadded_bytes_per_row]);
+        }
+    }
+
+    /// Private method to create an output staging buffer holding the given number of array
+    /// layers, with rows padded for copying.
+    fn output_staging_buffer(
+        device: &Device,
+        size: (u32, u32),
+        bytes_per_texel: u32,
+        layer_count: u32,
+    ) -> Buffer {
         device.create_buffer(&BufferDescriptor {
-            label: None,
-            size: size.0 as u64 * size.1 as u64 * 4,
+            label: Some(&format!("raymarks.staging.{}x{}", size.0, size.1)),
+            size: Self::padded_bytes_per_row(size.0, bytes_per_texel) as u64
+                * size.1 as u64
+                * layer_count as u64,
             usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
             mapped_at_creation: false,
         })
     }
 }
+
+impl Drop for BenchmarkContext {
+    /// Wait for the GPU to finish, then release resources in a safe order, so no buffer or texture
+    /// is destroyed while still in use.
+    fn drop(&mut self) {
+        self.release_resources();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_rows_are_aligned() {
+        assert_eq!(BenchmarkContext::padded_bytes_per_row(300, 4), 1280);
+        assert_eq!(BenchmarkContext::padded_bytes_per_row(64, 4), 256);
+        assert_eq!(BenchmarkContext::padded_bytes_per_row(1, 8), 256);
+    }
+
+    #[test]
+    fn output_texture_matches_target_format() {
+        let source = format!(
+            "// Written as rgba8unorm\n{}\nvar mask: texture_storage_2d<rgba8unorm, write>;\n",
+            BenchmarkContext::OUTPUT_TEXTURE_DECLARATION
+        );
+        assert_eq!(
+            BenchmarkContext::adapt_output_texture(source.clone(), TextureFormat::Rgba8UnormSrgb),
+            source
+        );
+        assert_eq!(
+            BenchmarkContext::adapt_output_texture(source, TextureFormat::Rgba16Float),
+            "// Written as rgba8unorm\n\
+             alias OutputTexture = texture_storage_2d<rgba16float, write>;\n\
+             var mask: texture_storage_2d<rgba8unorm, write>;\n"
+        );
+    }
+
+    #[test]
+    fn workgroup_sizes_match_shaders() {
+        let raymarch = Shader::Raymarch.load_source_preprocessed().unwrap();
+        for entry_point in ["raymarch_shader", "raymarch_steps_shader"] {
+            check_workgroup_size(
+                &raymarch,
+                entry_point,
+                BenchmarkContext::RAYMARCH_WORKGROUP_SIZE,
+            )
+            .unwrap();
+        }
+        let blur = Shader::Blur.load_source_preprocessed().unwrap();
+        check_workgroup_size(
+            &blur,
+            "postprocess_shader",
+            BenchmarkContext::POSTPROCESS_WORKGROUP_SIZE,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn compute_shaders_adapt_to_float_targets() {
+        for shader in [Shader::Raymarch, Shader::Blur] {
+            let source = BenchmarkContext::adapt_output_texture(
+                shader.load_source_preprocessed().unwrap(),
+                TextureFormat::Rgba16Float,
+            );
+            assert!(source.contains("texture_storage_2d<rgba16float, write>"));
+            validate_wgsl(&source, Features::empty()).unwrap();
+        }
+    }
+
+    #[test]
+    fn unpad_rows_drops_row_padding() {
+        // A 300 texel wide RGBA8 texture has 1200 byte rows, padded to 1280 bytes for copying
+        let (width, height, bytes_per_texel) = (300u32, 3u32, 4u32);
+        let padded_bytes_per_row = BenchmarkContext::padded_bytes_per_row(width, bytes_per_texel);
+        let unpadded_bytes_per_row = width * bytes_per_texel;
+        assert_eq!(padded_bytes_per_row, 1280);
+
+        let mut padded = Vec::new();
+        for row in 0..height {
+            padded.extend((0..unpadded_bytes_per_row).map(|i| (row + i) as u8));
+            padded.resize(((row + 1) * padded_bytes_per_row) as usize, 0xff);
+        }
+
+        let mut data = vec![0xaa];
+        BenchmarkContext::unpad_rows(
+            &padded,
+            padded_bytes_per_row as usize,
+            unpadded_bytes_per_row as usize,
+            &mut data,
+        );
+        assert_eq!(data.len(), 1 + (unpadded_bytes_per_row * height) as usize);
+        assert_eq!(data[0], 0xaa);
+        for (row, bytes) in data[1..]
+            .chunks(unpadded_bytes_per_row as usize)
+            .enumerate()
+        {
+            let expected: Vec<u8> = (0..unpadded_bytes_per_row)
+                .map(|i| (row as u32 + i) as u8)
+                .collect();
+            assert_eq!(bytes, &expected[..]);
+        }
+    }
+}
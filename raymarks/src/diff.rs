@@ -0,0 +1,327 @@
+use crate::context::SaveError;
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Channel of an RGBA pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channel {
+    /// Every channel, in the order they are stored in RGBA data.
+    pub const ALL: [Channel; 4] = [Self::Red, Self::Green, Self::Blue, Self::Alpha];
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Red => write!(f, "red"),
+            Self::Green => write!(f, "green"),
+            Self::Blue => write!(f, "blue"),
+            Self::Alpha => write!(f, "alpha"),
+        }
+    }
+}
+
+/// Largest difference allowed in each channel of any pixel, in 8-bit channel values. sRGB
+/// encoding rounds dark colors more coarsely than bright ones, so color channels may need more
+/// tolerance than alpha, which is often exactly opaque.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tolerance {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Tolerance {
+    /// Allow the same difference in every channel.
+    pub const fn uniform(difference: u8) -> Self {
+        Self {
+            r: difference,
+            g: difference,
+            b: difference,
+            a: difference,
+        }
+    }
+
+    /// Get the largest difference allowed in the given channel.
+    pub fn channel(&self, channel: Channel) -> u8 {
+        match channel {
+            Channel::Red => self.r,
+            Channel::Green => self.g,
+            Channel::Blue => self.b,
+            Channel::Alpha => self.a,
+        }
+    }
+}
+
+/// Tolerances for comparing a render against a reference image, in 8-bit channel values.
+#[derive(Clone, Copy, Debug)]
+pub struct DiffOptions {
+    /// Largest difference allowed in each channel of any pixel.
+    pub max_difference: Tolerance,
+    /// Largest mean difference allowed over all channels of all pixels.
+    pub mean_difference: f64,
+    /// Whether to write a heatmap of the differences when the comparison fails.
+    pub write_diff_image: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            max_difference: Tolerance::uniform(2),
+            mean_difference: 0.5,
+            write_diff_image: true,
+        }
+    }
+}
+
+/// Result of comparing a render against a reference image.
+#[derive(Clone, Debug)]
+pub struct DiffResult {
+    /// Largest absolute difference in any channel of any pixel.
+    pub max_difference: u8,
+    /// Largest absolute difference of any pixel in each channel, in RGBA order.
+    pub channel_max_differences: [u8; 4],
+    /// Mean absolute difference over all channels of all pixels.
+    pub mean_difference: f64,
+    /// Channels whose largest difference exceeds their tolerance, in RGBA order.
+    pub failed_channels: Vec<Channel>,
+    /// Whether the differences are within tolerance.
+    pub passed: bool,
+    /// Path of the written difference heatmap, if one was written.
+    pub diff_image: Option<PathBuf>,
+}
+
+/// Error produced when a render cannot be compared against a reference image.
+#[derive(Debug)]
+pub enum CompareError {
+    /// The render target could not be read back, or the difference heatmap could not be saved.
+    Save(SaveError),
+    /// The reference image could not be opened.
+    Io(PathBuf, io::Error),
+    /// The reference image could not be decoded as a PNG.
+    Decode(PathBuf, png::DecodingError),
+    /// The reference image has a different size than the render target.
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Save(error) => write!(f, "{}", error),
+            Self::Io(path, error) => {
+                write!(f, "failed to open reference {}: {}", path.display(), error)
+            }
+            Self::Decode(path, error) => {
+                write!(
+                    f,
+                    "failed to decode reference {}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            Self::SizeMismatch { expected, actual } => write!(
+                f,
+                "reference is {}x{} but render target is {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl Error for CompareError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Save(error) => Some(error),
+            Self::Io(_, error) => Some(error),
+            Self::Decode(_, error) => Some(error),
+            Self::SizeMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<SaveError> for CompareError {
+    fn from(error: SaveError) -> Self {
+        Self::Save(error)
+    }
+}
+
+/// Load a PNG file as 8-bit RGBA data, returning the data and the size of the image.
+pub fn load_png_rgba8(path: &Path) -> Result<(Vec<u8>, (u32, u32)), CompareError> {
+    let file = File::open(path).map_err(|error| CompareError::Io(path.to_path_buf(), error))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|error| CompareError::Decode(path.to_path_buf(), error))?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut data)
+        .map_err(|error| CompareError::Decode(path.to_path_buf(), error))?;
+    data.truncate(info.buffer_size());
+
+    // Expand every color type to RGBA
+    let data = match info.color_type {
+        png::ColorType::Rgba => data,
+        png::ColorType::Rgb => data
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .flat_map(|gray| [gray[0], gray[0], gray[0], gray[1]])
+            .collect(),
+        _ => data
+            .iter()
+            .flat_map(|&gray| [gray, gray, gray, 255])
+            .collect(),
+    };
+    Ok((data, (info.width, info.height)))
+}
+
+/// Compare two RGBA images of the same size, returning the differences and a heatmap of the
+/// largest channel difference of each pixel, as RGBA data going from black through red to yellow.
+pub fn diff_images(
+    rendered: &[u8],
+    reference: &[u8],
+    options: &DiffOptions,
+) -> (DiffResult, Vec<u8>) {
+    let mut channel_max_differences = [0u8; 4];
+    let mut total_difference = 0u64;
+    let mut heatmap = Vec::with_capacity(rendered.len());
+    for (rendered, reference) in rendered.chunks_exact(4).zip(reference.chunks_exact(4)) {
+        let mut pixel_difference = 0;
+        for (channel, (a, b)) in rendered.iter().zip(reference).enumerate() {
+            let difference = a.abs_diff(*b);
+            total_difference += difference as u64;
+            pixel_difference = pixel_difference.max(difference);
+            channel_max_differences[channel] = channel_max_differences[channel].max(difference);
+        }
+
+        let heat = pixel_difference as u32 * 2;
+        heatmap.extend_from_slice(&[
+            heat.min(255) as u8,
+            heat.saturating_sub(255).min(255) as u8,
+            0,
+            255,
+        ]);
+    }
+
+    let mean_difference = total_difference as f64 / rendered.len().max(1) as f64;
+    let failed_channels: Vec<Channel> = Channel::ALL
+        .into_iter()
+        .zip(channel_max_differences)
+        .filter(|&(channel, difference)| difference > options.max_difference.channel(channel))
+        .map(|(channel, _)| channel)
+        .collect();
+    let result = DiffResult {
+        max_difference: channel_max_differences.into_iter().max().unwrap_or(0),
+        channel_max_differences,
+        mean_difference,
+        passed: failed_channels.is_empty() && mean_difference <= options.mean_difference,
+        failed_channels,
+        diff_image: None,
+    };
+    (result, heatmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build RGBA data from the given pixels.
+    fn image(pixels: &[[u8; 4]]) -> Vec<u8> {
+        pixels.iter().flatten().copied().collect()
+    }
+
+    #[test]
+    fn identical_images_pass() {
+        let data = image(&[[0, 64, 128, 255], [255, 255, 255, 0]]);
+        let (result, heatmap) = diff_images(&data, &data, &DiffOptions::default());
+        assert!(result.passed);
+        assert_eq!(result.max_difference, 0);
+        assert_eq!(result.channel_max_differences, [0; 4]);
+        assert_eq!(result.mean_difference, 0.0);
+        assert!(result.failed_channels.is_empty());
+        assert_eq!(heatmap, image(&[[0, 0, 0, 255]; 2]));
+    }
+
+    #[test]
+    fn differences_within_tolerance_pass() {
+        let rendered = image(&[[10, 20, 30, 255], [10, 20, 30, 255]]);
+        let reference = image(&[[12, 19, 30, 255], [10, 20, 30, 255]]);
+        let (result, _) = diff_images(&rendered, &reference, &DiffOptions::default());
+        assert!(result.passed);
+        assert_eq!(result.max_difference, 2);
+        assert_eq!(result.channel_max_differences, [2, 1, 0, 0]);
+        assert_eq!(result.mean_difference, 3.0 / 8.0);
+    }
+
+    #[test]
+    fn channels_beyond_tolerance_fail() {
+        let rendered = image(&[[0, 0, 0, 255], [100, 0, 0, 255]]);
+        let reference = image(&[[0, 0, 5, 250], [100, 0, 0, 255]]);
+        let options = DiffOptions {
+            max_difference: Tolerance {
+                r: 0,
+                g: 0,
+                b: 4,
+                a: 5,
+            },
+            mean_difference: f64::INFINITY,
+            write_diff_image: false,
+        };
+        let (result, _) = diff_images(&rendered, &reference, &options);
+        assert!(!result.passed);
+        assert_eq!(result.failed_channels, [Channel::Blue]);
+        assert_eq!(result.channel_max_differences, [0, 0, 5, 5]);
+    }
+
+    #[test]
+    fn mean_difference_beyond_tolerance_fails() {
+        let rendered = image(&[[2, 2, 2, 255]; 4]);
+        let reference = image(&[[0, 0, 0, 255]; 4]);
+        let options = DiffOptions {
+            max_difference: Tolerance::uniform(2),
+            mean_difference: 1.0,
+            write_diff_image: false,
+        };
+        let (result, _) = diff_images(&rendered, &reference, &options);
+        assert!(result.failed_channels.is_empty());
+        assert_eq!(result.mean_difference, 1.5);
+        assert!(!result.passed);
+    }
+
+    #[test]
+    fn heatmap_goes_from_red_to_yellow() {
+        let rendered = image(&[[0, 0, 0, 255], [100, 0, 0, 255], [0, 200, 0, 255]]);
+        let reference = image(&[[0, 0, 0, 255]; 3]);
+        let (_, heatmap) = diff_images(&rendered, &reference, &DiffOptions::default());
+        assert_eq!(
+            heatmap,
+            image(&[[0, 0, 0, 255], [200, 0, 0, 255], [255, 145, 0, 255]])
+        );
+    }
+
+    #[test]
+    fn empty_images_pass() {
+        let (result, heatmap) = diff_images(&[], &[], &DiffOptions::default());
+        assert!(result.passed);
+        assert_eq!(result.mean_difference, 0.0);
+        assert!(heatmap.is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use super::Mesh;
+use crate::mesh::Vertex;
+use glam::Vec3;
+use serde::Deserialize;
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+/// An axis-aligned cube exported from a Bevy scene by `bevy_extract`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct Cube {
+    /// Position of the center of the cube.
+    pub position: [f32; 3],
+    /// Size of the cube along each axis.
+    pub size: [f32; 3],
+    /// sRGB color of the cube's material. Rasterization passes shade with their own palette, so
+    /// colors are kept for reference only.
+    pub color: [f32; 3],
+}
+
+/// Error produced when a file of exported cubes cannot be read or parsed.
+#[derive(Debug)]
+pub enum CubesError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file is not a JSON array of cubes.
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for CubesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read cubes file: {}", error),
+            Self::Parse(error) => write!(f, "malformed cubes file: {}", error),
+        }
+    }
+}
+
+impl Error for CubesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for CubesError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for CubesError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Parse(error)
+    }
+}
+
+/// Load the cubes exported by `bevy_extract` from a JSON file.
+pub fn load_cubes(path: &Path) -> Result<Vec<Cube>, CubesError> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+impl Mesh {
+    /// A single mesh containing every given cube, sized and placed as exported. Instances scale
+    /// uniformly, so cubes of different proportions are baked into the mesh, which is drawn with
+    /// `Instance::IDENTITY`.
+    pub fn cubes(cubes: &[Cube]) -> Self {
+        let unit_cube = Self::cube(1.0);
+        let mut mesh = Self::default();
+        for cube in cubes {
+            let first = mesh.vertices.len() as u32;
+            mesh.vertices
+                .extend(unit_cube.vertices.iter().map(|vertex| {
+                    Vertex {
+                        position: (Vec3::from(vertex.position) * Vec3::from(cube.size)
+                            + Vec3::from(cube.position))
+                        .to_array(),
+                        ..*vertex
+                    }
+                }));
+            mesh.indices
+                .extend(unit_cube.indices.iter().map(|index| first + index));
+        }
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    /// A cube of the given position and size, with an arbitrary color.
+    fn cube(position: [f32; 3], size: [f32; 3]) -> Cube {
+        Cube {
+            position,
+            size,
+            color: [1.0, 0.0, 0.0],
+        }
+    }
+
+    /// Write the given JSON to a file named after the test and load it as cubes.
+    fn load(test: &str, source: &str) -> Result<Vec<Cube>, CubesError> {
+        let path = std::env::temp_dir().join(format!(
+            "raymarks-cubes-{}-{}.json",
+            test,
+            std::process::id()
+        ));
+        write(&path, source).unwrap();
+        load_cubes(&path)
+    }
+
+    #[test]
+    fn cubes_offset_indices_into_their_own_vertices() {
+        let unit_cube = Mesh::cube(1.0);
+        let mesh = Mesh::cubes(&[
+            cube([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+            cube([2.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+        ]);
+        let (vertex_count, index_count) = (unit_cube.vertices.len(), unit_cube.indices.len());
+        assert_eq!(mesh.vertices.len(), 2 * vertex_count);
+        assert_eq!(mesh.indices.len(), 2 * index_count);
+        assert_eq!(mesh.indices[..index_count], unit_cube.indices[..]);
+        for (index, &unit_index) in mesh.indices[index_count..].iter().zip(&unit_cube.indices) {
+            assert_eq!(*index, unit_index + vertex_count as u32);
+        }
+    }
+
+    #[test]
+    fn cubes_are_sized_and_placed() {
+        let unit_cube = Mesh::cube(1.0);
+        let mesh = Mesh::cubes(&[cube([1.0, 2.0, 3.0], [2.0, 4.0, 6.0])]);
+        for (vertex, unit_vertex) in mesh.vertices.iter().zip(&unit_cube.vertices) {
+            let [x, y, z] = unit_vertex.position;
+            assert_eq!(
+                vertex.position,
+                [1.0 + 2.0 * x, 2.0 + 4.0 * y, 3.0 + 6.0 * z]
+            );
+            assert_eq!(vertex.normal, unit_vertex.normal);
+        }
+    }
+
+    #[test]
+    fn no_cubes_make_an_empty_mesh() {
+        let mesh = Mesh::cubes(&[]);
+        assert!(mesh.vertices.is_empty());
+        assert!(mesh.indices.is_empty());
+    }
+
+    #[test]
+    fn cubes_are_loaded_from_json() {
+        let cubes = load(
+            "load",
+            r#"[{"position": [1, 2, 3], "size": [0.5, 1, 1.5], "color": [0, 0.5, 1]}]"#,
+        );
+        assert_eq!(
+            cubes.unwrap(),
+            [Cube {
+                position: [1.0, 2.0, 3.0],
+                size: [0.5, 1.0, 1.5],
+                color: [0.0, 0.5, 1.0],
+            }]
+        );
+    }
+
+    #[test]
+    fn malformed_cubes_are_rejected() {
+        let cubes = load("malformed", r#"{"position": [1, 2, 3]}"#);
+        assert!(matches!(cubes, Err(CubesError::Parse(_))));
+    }
+}
@@ -0,0 +1,202 @@
+mod cubes;
+mod obj;
+
+pub use cubes::{load_cubes, Cube, CubesError};
+pub use obj::{load_obj, ObjError};
+
+use crate::mesh::{Instance, Vertex};
+use glam::Vec3;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    f32::consts::PI,
+    path::{Path, PathBuf},
+};
+
+/// Triangle mesh geometry on the CPU, in the representation uploaded by
+/// `BenchmarkContext::set_mesh`.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// A single triangle covering the center of the render target.
+    pub fn triangle() -> Self {
+        let normal = [0.0, 0.0, 1.0];
+        Self {
+            vertices: vec![
+                Vertex {
+                    position: [0.0, 1.0, 0.0],
+                    normal,
+                    uv: [0.5, 0.0],
+                },
+                Vertex {
+                    position: [-1.0, -1.0, 0.0],
+                    normal,
+                    uv: [0.0, 1.0],
+                },
+                Vertex {
+                    position: [1.0, -1.0, 0.0],
+                    normal,
+                    uv: [1.0, 1.0],
+                },
+            ],
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    /// A square of side 2 centered at the origin, facing the positive z axis, with texture
+    /// coordinates spanning it once with v pointing down.
+    pub fn quad() -> Self {
+        let normal = [0.0, 0.0, 1.0];
+        let vertex = |x: f32, y: f32| Vertex {
+            position: [x, y, 0.0],
+            normal,
+            uv: [(x + 1.0) / 2.0, (1.0 - y) / 2.0],
+        };
+        Self {
+            vertices: vec![
+                vertex(-1.0, -1.0),
+                vertex(1.0, -1.0),
+                vertex(1.0, 1.0),
+                vertex(-1.0, 1.0),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        }
+    }
+
+    /// A square of the given side centered at the origin in the xz plane, facing the positive y
+    /// axis, divided into the given number of cells along each side, at least 1. Each cell is two
+    /// triangles, so the triangle count grows with the square of the subdivisions.
+    pub fn plane(size: f32, subdivisions: u32) -> Self {
+        let cells = subdivisions.max(1);
+        let mut vertices = Vec::with_capacity(((cells + 1) * (cells + 1)) as usize);
+        for row in 0..=cells {
+            for column in 0..=cells {
+                let uv = [column as f32 / cells as f32, row as f32 / cells as f32];
+                vertices.push(Vertex {
+                    position: [(uv[0] - 0.5) * size, 0.0, (uv[1] - 0.5) * size],
+                    normal: [0.0, 1.0, 0.0],
+                    uv,
+                });
+            }
+        }
+
+        let mut indices = Vec::with_capacity((cells * cells * 6) as usize);
+        for row in 0..cells {
+            for column in 0..cells {
+                let near = row * (cells + 1) + column;
+                let far = near + cells + 1;
+                indices.extend_from_slice(&[near, far, near + 1, near + 1, far, far + 1]);
+            }
+        }
+        Self { vertices, indices }
+    }
+
+    /// A cube of the given side centered at the origin, like Bevy's `Cuboid::from_size`. Each face
+    /// has its own four vertices, so normals are flat, and texture coordinates span each face
+    /// once.
+    pub fn cube(size: f32) -> Self {
+        // Normal of each face, followed by the axes of its texture coordinates, which are
+        // counter-clockwise around the normal
+        let faces = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+            (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+            (Vec3::Z, Vec3::X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+        ];
+        let mut mesh = Self::default();
+        for (normal, u, v) in faces {
+            let first = mesh.vertices.len() as u32;
+            for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                mesh.vertices.push(Vertex {
+                    position: ((normal + u * x + v * y) * size / 2.0).to_array(),
+                    normal: normal.to_array(),
+                    uv: [(x + 1.0) / 2.0, (1.0 - y) / 2.0],
+                });
+            }
+            mesh.indices
+                .extend([0, 1, 2, 0, 2, 3].map(|corner| first + corner));
+        }
+        mesh
+    }
+
+    /// A unit sphere centered at the origin, divided into the given numbers of rings from pole to
+    /// pole and sectors around its equator. Triangles wind counter-clockwise from outside, and
+    /// texture coordinates wrap once around the equator, with v going from pole to pole.
+    pub fn uv_sphere(rings: u32, sectors: u32) -> Self {
+        let mut vertices = Vec::with_capacity(((sectors + 1) * (rings + 1)) as usize);
+        for ring in 0..=rings {
+            let polar = PI * ring as f32 / rings as f32;
+            for sector in 0..=sectors {
+                let azimuth = 2.0 * PI * sector as f32 / sectors as f32;
+                // Positions on a unit sphere are also its normals
+                let position = [
+                    polar.sin() * azimuth.cos(),
+                    polar.cos(),
+                    polar.sin() * azimuth.sin(),
+                ];
+                vertices.push(Vertex {
+                    position,
+                    normal: position,
+                    uv: [sector as f32 / sectors as f32, ring as f32 / rings as f32],
+                });
+            }
+        }
+
+        let mut indices = Vec::with_capacity((sectors * rings * 6) as usize);
+        for ring in 0..rings {
+            for sector in 0..sectors {
+                let top = ring * (sectors + 1) + sector;
+                let bottom = top + sectors + 1;
+                indices.extend_from_slice(&[top, top + 1, bottom, top + 1, bottom + 1, bottom]);
+            }
+        }
+        Self { vertices, indices }
+    }
+}
+
+/// Get the directory containing model assets used by the benchmarks.
+pub fn asset_directory() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")
+}
+
+/// Generate the given number of instances stacked along the z axis at the given spacing, from
+/// the origin towards negative z, so a mesh facing the positive z axis overlaps itself.
+pub fn stacked_instances(count: u32, spacing: f32) -> Vec<Instance> {
+    (0..count)
+        .map(|index| Instance {
+            translation: [0.0, 0.0, -(index as f32) * spacing],
+            ..Instance::IDENTITY
+        })
+        .collect()
+}
+
+/// Sort instances from the farthest to the nearest to the given eye position, the order in which
+/// translucent objects must be drawn to blend correctly.
+pub fn sort_back_to_front(instances: &mut [Instance], eye: Vec3) {
+    instances.sort_by(|a, b| {
+        let distance = |instance: &Instance| Vec3::from(instance.translation).distance_squared(eye);
+        distance(b).total_cmp(&distance(a))
+    });
+}
+
+/// Generate the given number of instances scattered randomly within the render target. The same
+/// seed always yields the same placement, across runs and machines.
+pub fn scatter_instances(count: u32, seed: u64) -> Vec<Instance> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| Instance {
+            translation: [
+                rng.random_range(-1.0..1.0), // x
+                rng.random_range(-1.0..1.0), // y
+                rng.random_range(0.0..1.0),  // z
+            ],
+            scale: rng.random_range(0.05..0.2),
+            material: 0,
+        })
+        .collect()
+}
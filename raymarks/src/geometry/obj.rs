@@ -0,0 +1,306 @@
+use super::Mesh;
+use crate::mesh::Vertex;
+use std::{collections::HashMap, error::Error, fmt, fs::read_to_string, io, path::Path};
+
+/// Error produced when a Wavefront `.obj` file cannot be read or parsed.
+#[derive(Debug)]
+pub enum ObjError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// A line of the file was malformed.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read obj file: {}", error),
+            Self::Parse { line, message } => {
+                write!(f, "malformed obj file on line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Load the positions, texture coordinates, normals, and faces of a Wavefront `.obj` file into a
+/// mesh. Faces with more than three vertices are triangulated, faces without normals are given
+/// flat normals, and corners without texture coordinates are given zero coordinates.
+pub fn load_obj(path: &Path) -> Result<Mesh, ObjError> {
+    parse_obj(&read_to_string(path)?)
+}
+
+/// Parse the contents of a Wavefront `.obj` file into a mesh.
+pub fn parse_obj(source: &str) -> Result<Mesh, ObjError> {
+    let mut positions = Vec::<[f32; 3]>::new();
+    let mut uvs = Vec::<[f32; 2]>::new();
+    let mut normals = Vec::<[f32; 3]>::new();
+    let mut mesh = Mesh::default();
+
+    // Vertices which share a position, texture coordinates and a normal are shared in the index
+    // buffer
+    let mut shared_vertices = HashMap::<(usize, Option<usize>, usize), u32>::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let error = |message: String| ObjError::Parse {
+            line: line_number,
+            message,
+        };
+
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(parse_vector(tokens).map_err(error)?),
+            Some("vt") => uvs.push(parse_vector(tokens).map_err(error)?),
+            Some("vn") => normals.push(parse_vector(tokens).map_err(error)?),
+            Some("f") => {
+                let corners = tokens
+                    .map(|token| parse_corner(token, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                if corners.len() < 3 {
+                    return Err(error(format!(
+                        "face has {} vertices, expected at least 3",
+                        corners.len()
+                    )));
+                }
+
+                // Triangulate the face as a fan around its first vertex
+                for i in 1..corners.len() - 1 {
+                    let triangle = [corners[0], corners[i], corners[i + 1]];
+                    let uv = |uv: Option<usize>| uv.map_or([0.0; 2], |uv| uvs[uv]);
+                    if let [(a, a_uv, Some(a_normal)), (b, b_uv, Some(b_normal)), (c, c_uv, Some(c_normal))] =
+                        triangle
+                    {
+                        for corner in [
+                            (a, a_uv, a_normal),
+                            (b, b_uv, b_normal),
+                            (c, c_uv, c_normal),
+                        ] {
+                            let index = *shared_vertices.entry(corner).or_insert_with(|| {
+                                let (position, texture_coordinate, normal) = corner;
+                                mesh.vertices.push(Vertex {
+                                    position: positions[position],
+                                    normal: normals[normal],
+                                    uv: uv(texture_coordinate),
+                                });
+                                mesh.vertices.len() as u32 - 1
+                            });
+                            mesh.indices.push(index);
+                        }
+                    } else {
+                        // Flat normals need vertices unique to this triangle
+                        let normal =
+                            flat_normal(triangle.map(|(position, _, _)| positions[position]));
+                        for (position, texture_coordinate, _) in triangle {
+                            mesh.indices.push(mesh.vertices.len() as u32);
+                            mesh.vertices.push(Vertex {
+                                position: positions[position],
+                                normal,
+                                uv: uv(texture_coordinate),
+                            });
+                        }
+                    }
+                }
+            }
+            // Groups, materials, and comments are not needed
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// Parse the first components of a `v`, `vt` or `vn` line.
+fn parse_vector<'a, const N: usize>(
+    mut tokens: impl Iterator<Item = &'a str>,
+) -> Result<[f32; N], String> {
+    let mut vector = [0.0; N];
+    for component in vector.iter_mut() {
+        let token = tokens
+            .next()
+            .ok_or_else(|| format!("expected {} components", N))?;
+        *component = token
+            .parse()
+            .map_err(|_| format!("invalid number `{}`", token))?;
+    }
+    Ok(vector)
+}
+
+/// Parse a face corner in the `v`, `v/vt`, `v//vn`, or `v/vt/vn` format, returning zero-based
+/// position, optional texture coordinate, and optional normal indices.
+fn parse_corner(
+    token: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = token.split('/');
+    let position = resolve_index(parts.next().unwrap_or_default(), position_count)?;
+    let texture_coordinate = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, uv_count)?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, normal_count)?),
+        _ => None,
+    };
+    Ok((position, texture_coordinate, normal))
+}
+
+/// Convert a one-based (or negative, relative to the end) obj index into a zero-based index.
+fn resolve_index(token: &str, count: usize) -> Result<usize, String> {
+    let index: isize = token
+        .parse()
+        .map_err(|_| format!("invalid index `{}`", token))?;
+    let resolved = match index {
+        1.. => index - 1,
+        ..=-1 => count as isize + index,
+        0 => return Err("indices start at 1".to_string()),
+    };
+    if resolved < 0 || resolved as usize >= count {
+        return Err(format!("index {} is out of range", index));
+    }
+    Ok(resolved as usize)
+}
+
+/// Compute the normal of a triangle from its counter-clockwise corner positions.
+fn flat_normal([a, b, c]: [[f32; 3]; 3]) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let cross = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let length = (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt();
+    if length == 0.0 {
+        return [0.0, 0.0, 1.0];
+    }
+    cross.map(|component| component / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the line and message of a parse error, panicking on other results.
+    fn parse_error(source: &str) -> (usize, String) {
+        match parse_obj(source) {
+            Err(ObjError::Parse { line, message }) => (line, message),
+            result => panic!("expected a parse error, got {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn faces_with_normals_share_vertices() {
+        let mesh = parse_obj(
+            "# A quad of two triangles\n\
+             v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+             vt 0 0\nvt 1 1\n\
+             vn 0 0 1\n\
+             f 1/1/1 2//1 3/2/1\n\
+             f 1/1/1 3/2/1 4//1\n",
+        )
+        .unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[2].position, [1.0, 1.0, 0.0]);
+        assert_eq!(mesh.vertices[2].uv, [1.0, 1.0]);
+        // Corners without texture coordinates are given zero coordinates
+        assert_eq!(mesh.vertices[1].uv, [0.0, 0.0]);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn polygons_are_triangulated_with_flat_normals() {
+        let mesh =
+            parse_obj("v 0 0 0\nv 0 1 0\nv 0 1 1\nv 0 0 1\nv 0 -1 0.5\nf 1 2 3 4 5\n").unwrap();
+        // A fan of three triangles, each with vertices of its own
+        assert_eq!(mesh.indices, (0..9).collect::<Vec<u32>>());
+        assert_eq!(mesh.vertices[3].position, [0.0, 0.0, 0.0]);
+        assert_eq!(mesh.vertices[5].position, [0.0, 0.0, 1.0]);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.normal == [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_end() {
+        let relative =
+            parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf -3//-1 -2//-1 -1//-1\n").unwrap();
+        let absolute =
+            parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n").unwrap();
+        assert_eq!(relative.indices, absolute.indices);
+        let positions = |mesh: &Mesh| -> Vec<[f32; 3]> {
+            mesh.vertices.iter().map(|vertex| vertex.position).collect()
+        };
+        assert_eq!(positions(&relative), positions(&absolute));
+
+        // Negative indices are relative to the vertices defined so far, not the whole file
+        let mesh = parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -1 -2 -3\nv 5 5 5\n").unwrap();
+        assert_eq!(mesh.vertices[0].position, [0.0, 1.0, 0.0]);
+        assert_eq!(mesh.vertices[2].position, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn unknown_lines_are_ignored() {
+        let mesh = parse_obj("mtllib bunny.mtl\no bunny\ng body\nusemtl fur\ns 1\n\n").unwrap();
+        assert!(mesh.vertices.is_empty());
+        assert!(mesh.indices.is_empty());
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        let (line, message) = parse_error("v 0 0 0\nv 1 x 0\n");
+        assert_eq!(line, 2);
+        assert_eq!(message, "invalid number `x`");
+
+        assert_eq!(parse_error("vn 0 1\n").1, "expected 3 components");
+        assert_eq!(parse_error("vt 0\n").1, "expected 2 components");
+
+        let faces = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+        let (line, message) = parse_error(&format!("{}f 1 2\n", faces));
+        assert_eq!(line, 4);
+        assert_eq!(message, "face has 2 vertices, expected at least 3");
+        assert_eq!(
+            parse_error(&format!("{}f 0 1 2\n", faces)).1,
+            "indices start at 1"
+        );
+        assert_eq!(
+            parse_error(&format!("{}f 1 2 4\n", faces)).1,
+            "index 4 is out of range"
+        );
+        assert_eq!(
+            parse_error(&format!("{}f 1 2 -4\n", faces)).1,
+            "index -4 is out of range"
+        );
+        assert_eq!(
+            parse_error(&format!("{}f 1 2 a\n", faces)).1,
+            "invalid index `a`"
+        );
+        // Texture coordinates and normals must exist too
+        assert_eq!(
+            parse_error(&format!("{}f 1/1 2/1 3/1\n", faces)).1,
+            "index 1 is out of range"
+        );
+    }
+}
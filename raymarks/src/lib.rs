@@ -0,0 +1,31 @@
+//! Benchmarks comparing rasterization and raymarching on the GPU, along with the context used to
+//! set up, run, time and save render passes.
+//!
+//! With the `tracing` feature, passes, submissions, saves and pipeline creation are wrapped in
+//! `tracing` spans alongside the existing logs, so the CPU time of the harness can be profiled
+//! with subscribers such as `tracing-flame` or `tracing-chrome`.
+
+pub mod benchmark;
+pub mod benchmarks;
+pub mod bind_groups;
+pub mod camera;
+pub mod capture;
+mod color;
+pub mod config;
+pub mod context;
+pub mod diff;
+pub mod geometry;
+pub mod mesh;
+pub mod pass_parameters;
+pub mod pipeline_statistics;
+#[cfg(feature = "window")]
+pub mod preview;
+pub mod report;
+pub mod resolution;
+pub mod scene;
+pub mod shaders;
+pub mod texture;
+mod timestamps;
+#[cfg(feature = "hot-reload")]
+pub mod watch;
+pub mod watchdog;
@@ -1,39 +1,396 @@
-mod context;
-mod shaders;
+use clap::{Parser, ValueEnum};
+use log::{error, info};
+use raymarks::{
+    benchmarks::{run_all, run_all_adapters, run_cube_scene, run_shader_directory},
+    config::{Backend, BenchmarkConfig},
+    context::{BenchmarkContext, BenchmarkContextBuilder},
+    mesh::{DrawMode, InstanceStorage},
+    report::write_reports_json,
+    resolution::Resolution,
+};
+use std::path::PathBuf;
+use wgpu::{Adapter, Backends, Face, PolygonMode, PrimitiveTopology};
 
-use context::BenchmarkContext;
-use log::info;
+/// Benchmarks comparing rasterization and raymarching on the GPU.
+#[derive(Debug, Parser)]
+struct Arguments {
+    /// TOML file describing the benchmarks to run, such as `benchmarks.toml`. Other arguments
+    /// override its values.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Comma-separated render target sizes, such as `512x512,1920x1080`.
+    #[arg(long = "res", value_delimiter = ',')]
+    resolutions: Vec<Resolution>,
+    /// Comma-separated numbers of bunnies drawn by the rasterization benchmark.
+    #[arg(long = "counts", value_delimiter = ',')]
+    bunny_counts: Vec<u32>,
+    /// Number of measured passes per benchmark.
+    #[arg(long)]
+    samples: Option<usize>,
+    /// Number of unmeasured warmup passes per benchmark.
+    #[arg(long)]
+    warmup: Option<usize>,
+    /// Interval in milliseconds between the starts of consecutive passes, such as 16.6.
+    #[arg(long)]
+    pace_ms: Option<f32>,
+    /// Seconds to measure each configuration for, reporting how timings drift, instead of a
+    /// number of samples.
+    #[arg(long)]
+    soak_seconds: Option<f32>,
+    /// Comma-separated percentiles of the GPU durations to report, such as `95,99,99.9`.
+    #[arg(long, value_delimiter = ',')]
+    percentiles: Vec<f64>,
+    /// Largest standard deviation over mean of GPU durations before a configuration is flagged as
+    /// noisy, such as 0.1, or 0 to never flag configurations.
+    #[arg(long)]
+    max_variation: Option<f64>,
+    /// Measure configurations flagged as noisy once more.
+    #[arg(long)]
+    rerun_noisy: bool,
+    /// Seconds each benchmark may run for before it is marked failed and the results so far are
+    /// written to the JSON path, or 0 to never time out.
+    #[arg(long)]
+    timeout_seconds: Option<f32>,
+    /// Graphics backend to select an adapter from.
+    #[arg(long, value_enum)]
+    backend: Option<Backend>,
+    /// Whether bunnies are drawn with one instanced draw call, a draw call each, or draw calls
+    /// reading their parameters from a GPU buffer.
+    #[arg(long, value_enum, default_value_t = DrawModeArgument::Instanced)]
+    draw_mode: DrawModeArgument,
+    /// Whether rasterization shaders read instances from a vertex, uniform or storage buffer.
+    #[arg(long, value_enum, default_value_t = InstanceStorageArgument::Vertex)]
+    instance_storage: InstanceStorageArgument,
+    /// Draw triangle edges instead of filling triangles, if the adapter supports it.
+    #[arg(long)]
+    wireframe: bool,
+    /// How the vertices of meshes are assembled into primitives, such as points to benchmark vertex
+    /// throughput with little fragment work.
+    #[arg(long, value_enum, default_value_t = TopologyArgument::TriangleList)]
+    topology: TopologyArgument,
+    /// Render at this factor times each side of every resolution, and downsample saved images back
+    /// to the resolution, such as 2 or 4 for supersampled anti-aliasing.
+    #[arg(long, default_value_t = 1)]
+    supersample: u32,
+    /// Cull back faces of counter-clockwise triangles.
+    #[arg(long)]
+    cull_back: bool,
+    /// Directory of WGSL shaders to run in place of the raymarch shader one after another,
+    /// comparing them at every resolution instead of running the configured benchmarks.
+    #[arg(long)]
+    shader_dir: Option<PathBuf>,
+    /// JSON file of cubes exported by `bevy_extract` to rasterize at every resolution, instead of
+    /// running the configured benchmarks.
+    #[arg(long, conflicts_with = "shader_dir")]
+    cubes: Option<PathBuf>,
+    /// Write the results as JSON to the given path, along with adapter metadata.
+    #[arg(long)]
+    json: Option<PathBuf>,
+    /// Directory to save images to, instead of `RAYMARKS_IMAGE_DIR` or the crate's `images`
+    /// directory.
+    #[arg(long)]
+    image_dir: Option<PathBuf>,
+    /// Template of the paths images are saved to within the image directory, such as
+    /// `{backend}/{shader}/{name}_{w}x{h}_{count}.{ext}`.
+    #[arg(long)]
+    filename_template: Option<String>,
+    /// Check that shaders compile, the adapter supports the requested features and the image
+    /// directory is writable, then exit without rendering.
+    #[arg(long)]
+    check: bool,
+    /// Run the benchmarks on every available adapter in turn, such as both an integrated and a
+    /// discrete GPU, writing a report per adapter.
+    #[arg(long)]
+    all_adapters: bool,
+    /// Print the adapters available on every backend, with their features and limits, then exit
+    /// without rendering.
+    #[arg(long)]
+    list_adapters: bool,
+    /// Re-run the benchmarks every time a shader changes.
+    #[cfg(feature = "hot-reload")]
+    #[arg(long)]
+    watch: bool,
+    /// Only log warnings and errors. `RUST_LOG` still overrides this, such as
+    /// `RUST_LOG=raymarks::context=debug` to debug the context alone.
+    #[arg(long)]
+    quiet: bool,
+    /// Preview passes in a window.
+    #[cfg(feature = "window")]
+    #[arg(long)]
+    window: bool,
+}
+
+/// Draw modes which can be selected on the command line.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DrawModeArgument {
+    Instanced,
+    Individual,
+    Indirect,
+}
+
+impl From<DrawModeArgument> for DrawMode {
+    fn from(draw_mode: DrawModeArgument) -> Self {
+        match draw_mode {
+            DrawModeArgument::Instanced => DrawMode::Instanced,
+            DrawModeArgument::Individual => DrawMode::Individual,
+            DrawModeArgument::Indirect => DrawMode::Indirect,
+        }
+    }
+}
+
+/// Instance storages which can be selected on the command line.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum InstanceStorageArgument {
+    Vertex,
+    Uniform,
+    Storage,
+}
+
+impl From<InstanceStorageArgument> for InstanceStorage {
+    fn from(storage: InstanceStorageArgument) -> Self {
+        match storage {
+            InstanceStorageArgument::Vertex => InstanceStorage::Vertex,
+            InstanceStorageArgument::Uniform => InstanceStorage::Uniform,
+            InstanceStorageArgument::Storage => InstanceStorage::Storage,
+        }
+    }
+}
+
+/// Primitive topologies which can be selected on the command line.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TopologyArgument {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    TriangleStrip,
+}
+
+impl From<TopologyArgument> for PrimitiveTopology {
+    fn from(topology: TopologyArgument) -> Self {
+        match topology {
+            TopologyArgument::PointList => PrimitiveTopology::PointList,
+            TopologyArgument::LineList => PrimitiveTopology::LineList,
+            TopologyArgument::LineStrip => PrimitiveTopology::LineStrip,
+            TopologyArgument::TriangleList => PrimitiveTopology::TriangleList,
+            TopologyArgument::TriangleStrip => PrimitiveTopology::TriangleStrip,
+        }
+    }
+}
 
 #[forbid(unsafe_code)]
 #[forbid(missing_docs)]
-
-/// Main entry point for benchmarking.
+/// Main entry point for benchmarking. Resolutions, bunny counts, sample counts and the backend
+/// not given on the command line fall back to the config file, or the embedded `benchmarks.toml`.
 fn main() {
+    let arguments = Arguments::parse();
+    // Parse `RUST_LOG` after setting the default level, so its directives take precedence
+    let level = if arguments.quiet {
+        log::LevelFilter::Warn
+    } else {
+        log::LevelFilter::Info
+    };
     env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
+        .filter_level(level)
+        .parse_default_env()
         .format_timestamp_secs()
         .init();
-    run_all();
+
+    if arguments.list_adapters {
+        let adapters = BenchmarkContextBuilder::default()
+            .backends(Backends::all())
+            .enumerate_adapters();
+        if adapters.is_empty() {
+            error!("No adapters are available.");
+            std::process::exit(1);
+        }
+        for adapter in &adapters {
+            print_adapter(adapter);
+        }
+        return;
+    }
+
+    let mut config = match &arguments.config {
+        Some(path) => match BenchmarkConfig::load(path) {
+            Ok(config) => config,
+            Err(config_error) => {
+                error!("{}", config_error);
+                std::process::exit(1);
+            }
+        },
+        None => BenchmarkConfig::default(),
+    };
+    if let Some(backend) = arguments.backend {
+        config.backend = backend;
+    }
+    if !arguments.resolutions.is_empty() {
+        config.resolutions = arguments.resolutions;
+    }
+    if !arguments.bunny_counts.is_empty() {
+        config.counts = arguments.bunny_counts;
+    }
+    if let Some(samples) = arguments.samples {
+        config.samples = samples;
+    }
+    if let Some(warmup) = arguments.warmup {
+        config.warmup = warmup;
+    }
+    if let Some(pace_ms) = arguments.pace_ms {
+        config.pace_ms = pace_ms;
+    }
+    if let Some(soak_seconds) = arguments.soak_seconds {
+        config.soak_seconds = soak_seconds;
+    }
+    if !arguments.percentiles.is_empty() {
+        config.percentiles = arguments.percentiles;
+    }
+    if let Some(max_variation) = arguments.max_variation {
+        config.max_variation = max_variation;
+    }
+    if arguments.rerun_noisy {
+        config.rerun_noisy = true;
+    }
+    if let Some(timeout_seconds) = arguments.timeout_seconds {
+        config.timeout_seconds = timeout_seconds;
+    }
+    // Arguments override values the config file was checked with
+    if let Err(problem) = config.check() {
+        error!("Invalid arguments: {}.", problem);
+        std::process::exit(1);
+    }
+
+    let mut builder = config.context_builder();
+    if let Some(image_dir) = arguments.image_dir {
+        builder = builder.image_directory(image_dir);
+    }
+    #[cfg(feature = "window")]
+    let builder = builder.preview(arguments.window);
+    let configure = |context: &mut BenchmarkContext| {
+        context.set_draw_mode(arguments.draw_mode.into());
+        context.set_instance_storage(arguments.instance_storage.into());
+        if let Some(template) = &arguments.filename_template {
+            context.set_filename_template(template);
+        }
+        context.set_topology(arguments.topology.into());
+        if arguments.supersample != 1 {
+            context.set_supersample(arguments.supersample);
+        }
+        if arguments.wireframe {
+            context.set_polygon_mode(PolygonMode::Line);
+        }
+        if arguments.cull_back {
+            context.set_cull_mode(Some(Face::Back));
+        }
+    };
+    let mut options = config.options();
+    options.partial_results = arguments.json.clone();
+    let parameters = config.parameters();
+
+    if arguments.all_adapters {
+        let reports = run_all_adapters(&builder, &options, &parameters, configure);
+        if let Some(path) = &arguments.json {
+            match write_reports_json(&reports, path) {
+                Ok(()) => info!("Results written to {}.", path.display()),
+                Err(error) => error!("Failed to write results: {}", error),
+            }
+        }
+        exit_on_failures(reports.iter().map(|report| report.failures.len()).sum());
+        return;
+    }
+
+    let mut context = match BenchmarkContext::new_with_sync(builder) {
+        Ok(context) => context,
+        Err(context_error) => {
+            error!("{}", context_error);
+            std::process::exit(1);
+        }
+    };
+    configure(&mut context);
+    if arguments.check {
+        match context.validate() {
+            Ok(()) => info!("Setup is valid."),
+            Err(errors) => {
+                for setup_error in errors {
+                    error!("{}", setup_error);
+                }
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Failures of the last run, such as failed checks or timeouts, which fail the exit status
+    let mut failures = 0;
+    let mut benchmarks = |context: &mut BenchmarkContext| {
+        let report = match (&arguments.shader_dir, &arguments.cubes) {
+            (Some(directory), _) => {
+                match run_shader_directory(context, &options, &parameters, directory) {
+                    Ok(report) => report,
+                    Err(error) => {
+                        error!("Failed to read {}: {}", directory.display(), error);
+                        return;
+                    }
+                }
+            }
+            (None, Some(path)) => match run_cube_scene(context, &options, &parameters, path) {
+                Ok(report) => report,
+                Err(error) => {
+                    error!("Failed to load {}: {}", path.display(), error);
+                    return;
+                }
+            },
+            (None, None) => run_all(context, &options, &parameters),
+        };
+        if let Some(path) = &arguments.json {
+            match report.write_json(path) {
+                Ok(()) => info!("Results written to {}.", path.display()),
+                Err(error) => error!("Failed to write results: {}", error),
+            }
+        }
+        failures = report.failures.len();
+    };
+    #[cfg(feature = "hot-reload")]
+    if arguments.watch {
+        if let Err(watch_error) = raymarks::watch::run_on_change(&mut context, benchmarks) {
+            error!("Failed to watch shaders: {}", watch_error);
+        }
+        context.shutdown();
+        return;
+    }
+    benchmarks(&mut context);
+    context.shutdown();
+    exit_on_failures(failures);
 }
 
-/// Run all benchmarks.
-fn run_all() {
-    let context = BenchmarkContext::new_sync();
-    bunny_rasterization(context, vec![(512, 512)], vec![1000]);
+/// Exit with a failure status if any benchmarks failed, after logging how many.
+fn exit_on_failures(failures: usize) {
+    if failures > 0 {
+        error!("{} benchmarks failed.", failures);
+        std::process::exit(1);
+    }
 }
 
-/// Benchmark which renders configurable amounts of Stanford bunny models using rasterization
-/// at configurable resolutions. At the moment, it only renders a single triangle.
-fn bunny_rasterization(
-    mut context: BenchmarkContext,
-    resolutions: Vec<(u32, u32)>,
-    bunny_counts: Vec<u32>,
-) {
-    for size in resolutions {
-        context.resize_render_target(size);
-        context.rasterization_pass();
-        context.submit();
-        context.save_render_target_sync("bunny_rasterization");
-    }
-    info!("Bunny rasterization benchmark complete.");
+/// Print an adapter's info, along with which of the features used by benchmarks it supports and
+/// the limits which bound them.
+fn print_adapter(adapter: &Adapter) {
+    let info = adapter.get_info();
+    let limits = adapter.limits();
+    println!("{} ({:?}, {:?})", info.name, info.backend, info.device_type);
+    println!(
+        "  vendor {:#06x}, device {:#06x}, driver {} {}",
+        info.vendor, info.device, info.driver, info.driver_info
+    );
+    println!(
+        "  benchmark features: {:?}",
+        adapter.features() & BenchmarkContext::DESIRED_FEATURES
+    );
+    println!(
+        "  max texture dimension {}, max buffer size {}, max storage binding {}, max uniform \
+         binding {}, max push constant size {}",
+        limits.max_texture_dimension_2d,
+        limits.max_buffer_size,
+        limits.max_storage_buffer_binding_size,
+        limits.max_uniform_buffer_binding_size,
+        limits.max_push_constant_size
+    );
 }
@@ -0,0 +1,370 @@
+use crate::color::f32_to_f16;
+use bytemuck::{Pod, Zeroable};
+use serde::Serialize;
+use wgpu::{util::DeviceExt, *};
+
+/// A single vertex of a mesh, laid out as expected by the rasterization shader.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+impl Vertex {
+    /// Shader locations and formats of the vertex attributes: position at 0, normal at 1 and
+    /// texture coordinates at 2.
+    const ATTRIBUTES: [VertexAttribute; 3] =
+        vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+
+    /// Get the layout of a vertex buffer containing these vertices, exposing every attribute.
+    pub fn layout() -> VertexBufferLayout<'static> {
+        Self::layout_with(&Self::ATTRIBUTES)
+    }
+
+    /// Get the layout of a vertex buffer containing these vertices, exposing only the position at
+    /// location 0, for shaders which need nothing else, such as depth-only passes.
+    pub fn position_layout() -> VertexBufferLayout<'static> {
+        Self::layout_with(&Self::ATTRIBUTES[..1])
+    }
+
+    /// Get the layout of a vertex buffer containing these vertices, exposing the given subset of
+    /// their attributes. The stride is always that of a whole vertex, so the same buffers can be
+    /// drawn with any subset.
+    pub fn layout_with(attributes: &'static [VertexAttribute]) -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes,
+        }
+    }
+}
+
+/// A single vertex of a mesh with half-precision attributes, which shaders read as the same
+/// inputs as those of `Vertex`, in 20 bytes instead of 32. Positions and normals are padded to
+/// four components, since there is no three-component half-precision format.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct HalfVertex {
+    pub position: [u16; 4],
+    pub normal: [u16; 4],
+    pub uv: [u16; 2],
+}
+
+impl HalfVertex {
+    /// Shader locations and formats of the vertex attributes, at the same locations as those of
+    /// `Vertex`.
+    const ATTRIBUTES: [VertexAttribute; 3] =
+        vertex_attr_array![0 => Float16x4, 1 => Float16x4, 2 => Float16x2];
+
+    /// Get the layout of a vertex buffer containing these vertices, exposing every attribute.
+    pub fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+impl From<Vertex> for HalfVertex {
+    /// Round each attribute of a vertex to the nearest half-precision float.
+    fn from(vertex: Vertex) -> Self {
+        let [x, y, z] = vertex.position;
+        let [nx, ny, nz] = vertex.normal;
+        Self {
+            position: [x, y, z, 1.0].map(f32_to_f16),
+            normal: [nx, ny, nz, 0.0].map(f32_to_f16),
+            uv: vertex.uv.map(f32_to_f16),
+        }
+    }
+}
+
+/// Precision of the vertex attributes of meshes uploaded for rasterization passes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum VertexPrecision {
+    /// Single-precision floats, laid out as `Vertex`.
+    #[default]
+    Single,
+    /// Half-precision floats, laid out as `HalfVertex`, converted from single precision when
+    /// meshes are uploaded, to measure how much memory bandwidth compressed attributes save.
+    Half,
+}
+
+impl VertexPrecision {
+    /// Get the layout of vertex buffers of this precision, exposing every attribute.
+    pub fn layout(self) -> VertexBufferLayout<'static> {
+        match self {
+            Self::Single => Vertex::layout(),
+            Self::Half => HalfVertex::layout(),
+        }
+    }
+}
+
+/// Per-instance transform applied to every vertex of a drawn mesh, along with the material it
+/// is shaded with.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Instance {
+    pub translation: [f32; 3],
+    pub scale: f32,
+    pub material: u32,
+}
+
+impl Instance {
+    /// An instance which leaves the mesh untransformed.
+    pub const IDENTITY: Self = Self {
+        translation: [0.0; 3],
+        scale: 1.0,
+        material: 0,
+    };
+
+    /// Shader locations and formats of the instance attributes, following the vertex attributes.
+    const ATTRIBUTES: [VertexAttribute; 3] =
+        vertex_attr_array![3 => Float32x3, 4 => Float32, 5 => Uint32];
+
+    /// Size in bytes of each instance in an instance buffer. Instances are padded to the 16-byte
+    /// alignment of structs in WGSL arrays, so the same buffer can be read as vertices, uniforms
+    /// or storage.
+    pub const STRIDE: BufferAddress = size_of::<PaddedInstance>() as BufferAddress;
+
+    /// Get the layout of an instance buffer containing these instances.
+    pub fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: Self::STRIDE,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// An instance padded to the size of the matching struct in WGSL arrays.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PaddedInstance {
+    instance: Instance,
+    padding: [u32; 3],
+}
+
+/// Where rasterization shaders read instances from. Every instance buffer can be read in each
+/// of these ways, to compare the speed of each and the number of instances each allows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum InstanceStorage {
+    /// Instance attributes in a vertex buffer, fetched once per instance.
+    #[default]
+    Vertex,
+    /// A fixed-size array in a uniform buffer, indexed by the instance index. Uniform bindings
+    /// are small, so this allows the fewest instances.
+    Uniform,
+    /// A runtime-sized array in a storage buffer, indexed by the instance index.
+    Storage,
+}
+
+/// How the instances of a mesh are drawn by rasterization passes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum DrawMode {
+    /// A single instanced draw call covering every instance.
+    #[default]
+    Instanced,
+    /// A separate draw call per instance, to measure the CPU overhead of issuing draw calls.
+    Individual,
+    /// A draw call per object whose parameters are read from an indirect buffer on the GPU, to
+    /// compare GPU-driven with CPU-driven draw submission.
+    Indirect,
+}
+
+/// Indexed draw parameters, one entry per scene object, which have been uploaded to a GPU buffer
+/// for indirect draws.
+pub struct IndirectBuffer {
+    buffer: Buffer,
+    count: u32,
+}
+
+impl IndirectBuffer {
+    /// Upload the given draw parameters to a new GPU buffer.
+    pub fn new(device: &Device, args: &[util::DrawIndexedIndirectArgs]) -> Self {
+        let contents: Vec<u8> = args
+            .iter()
+            .flat_map(|args| args.as_bytes())
+            .copied()
+            .collect();
+        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("raymarks.indirect_draws"),
+            contents: &contents,
+            usage: BufferUsages::INDIRECT,
+        });
+
+        Self {
+            buffer,
+            count: args.len() as u32,
+        }
+    }
+
+    /// Get the number of entries of draw parameters in the buffer.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Get the size of the GPU buffer in bytes.
+    pub fn size(&self) -> BufferAddress {
+        self.buffer.size()
+    }
+
+    /// Free the GPU buffer.
+    pub fn destroy(&self) {
+        self.buffer.destroy();
+    }
+}
+
+/// Instance transforms which have been uploaded to a GPU buffer.
+pub struct InstanceBuffer {
+    buffer: Buffer,
+    count: u32,
+}
+
+impl InstanceBuffer {
+    /// Upload the given instances to a new GPU buffer, which can be read as any
+    /// `InstanceStorage`. The buffer holds room for at least `min_capacity` instances, since
+    /// uniform arrays have a fixed size which the bound buffer must cover.
+    pub fn new(device: &Device, instances: &[Instance], min_capacity: u32) -> Self {
+        let mut padded: Vec<PaddedInstance> = instances
+            .iter()
+            .map(|&instance| PaddedInstance {
+                instance,
+                padding: [0; 3],
+            })
+            .collect();
+        padded.resize(
+            instances.len().max(min_capacity as usize),
+            PaddedInstance::zeroed(),
+        );
+        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("raymarks.instances"),
+            contents: bytemuck::cast_slice(&padded),
+            usage: BufferUsages::VERTEX | BufferUsages::UNIFORM | BufferUsages::STORAGE,
+        });
+
+        Self {
+            buffer,
+            count: instances.len() as u32,
+        }
+    }
+
+    /// Get the number of instances in the buffer.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Get the GPU buffer, to bind it for shaders reading instances as uniforms or storage.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Get the size of the GPU buffer in bytes.
+    pub fn size(&self) -> BufferAddress {
+        self.buffer.size()
+    }
+
+    /// Free the GPU buffer.
+    pub fn destroy(&self) {
+        self.buffer.destroy();
+    }
+}
+
+/// Mesh geometry which has been uploaded to GPU buffers, ready to be drawn.
+pub struct MeshBuffers {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+}
+
+impl MeshBuffers {
+    /// Upload the given vertices and triangle indices to new GPU buffers, converting the vertices
+    /// to the given precision.
+    pub fn new(
+        device: &Device,
+        vertices: &[Vertex],
+        indices: &[u32],
+        precision: VertexPrecision,
+    ) -> Self {
+        let half_vertices: Vec<HalfVertex>;
+        let contents: &[u8] = match precision {
+            VertexPrecision::Single => bytemuck::cast_slice(vertices),
+            VertexPrecision::Half => {
+                half_vertices = vertices.iter().copied().map(HalfVertex::from).collect();
+                bytemuck::cast_slice(&half_vertices)
+            }
+        };
+        let vertex_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("raymarks.vertices"),
+            contents,
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("raymarks.indices"),
+            contents: bytemuck::cast_slice(indices),
+            usage: BufferUsages::INDEX,
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    /// Bind the buffers to the render pass and draw the given number of instances of the mesh,
+    /// with one or many draw calls depending on the mode. Indirect draws ignore the count, and
+    /// read their parameters from the entry of the indirect buffer with the given index instead,
+    /// drawing nothing without one. The instance buffer is only bound as a vertex buffer when
+    /// instances are stored in one, otherwise it must be bound in a bind group by the caller.
+    pub fn draw(
+        &self,
+        render_pass: &mut RenderPass,
+        instances: &InstanceBuffer,
+        count: u32,
+        mode: DrawMode,
+        storage: InstanceStorage,
+        indirect: Option<(&IndirectBuffer, u32)>,
+    ) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        if storage == InstanceStorage::Vertex {
+            render_pass.set_vertex_buffer(1, instances.buffer.slice(..));
+        }
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint32);
+        match mode {
+            DrawMode::Instanced => render_pass.draw_indexed(0..self.index_count, 0, 0..count),
+            DrawMode::Individual => {
+                for instance in 0..count {
+                    render_pass.draw_indexed(0..self.index_count, 0, instance..instance + 1);
+                }
+            }
+            DrawMode::Indirect => {
+                if let Some((indirect_buffer, index)) = indirect {
+                    if index < indirect_buffer.count {
+                        let stride = size_of::<util::DrawIndexedIndirectArgs>() as BufferAddress;
+                        render_pass
+                            .draw_indexed_indirect(&indirect_buffer.buffer, index as u64 * stride);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the number of indices of the mesh, three per triangle.
+    pub fn index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    /// Get the combined size of the GPU buffers in bytes.
+    pub fn size(&self) -> BufferAddress {
+        self.vertex_buffer.size() + self.index_buffer.size()
+    }
+
+    /// Free the GPU buffers.
+    pub fn destroy(&self) {
+        self.vertex_buffer.destroy();
+        self.index_buffer.destroy();
+    }
+}
@@ -0,0 +1,208 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use wgpu::{util::DeviceExt, *};
+
+/// Small parameters which can change between passes without rebuilding pipelines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PassParameters {
+    /// Position rays are cast from by the raymarch shader.
+    pub camera_position: Vec3,
+    /// Time in seconds, for animated shaders.
+    pub time: f32,
+}
+
+impl Default for PassParameters {
+    /// A camera in front of the origin, at time zero.
+    fn default() -> Self {
+        Self {
+            camera_position: Vec3::new(0.0, 0.0, 4.0),
+            time: 0.0,
+        }
+    }
+}
+
+/// Pass parameters laid out as expected by `parameters.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct PassParametersUniform {
+    camera_position: [f32; 3],
+    time: f32,
+}
+
+impl From<&PassParameters> for PassParametersUniform {
+    fn from(parameters: &PassParameters) -> Self {
+        Self {
+            camera_position: parameters.camera_position.to_array(),
+            time: parameters.time,
+        }
+    }
+}
+
+/// Uniform buffer and bind group used in place of push constants when they are unsupported.
+struct UniformFallback {
+    buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+/// Pass parameters exposed to shaders as push constants where the device supports them, or
+/// through a uniform buffer otherwise, such as on WebGPU and Metal. Shaders include
+/// `parameters.wgsl`, whose uniform declaration is swapped for a push constant declaration when
+/// push constants are used.
+pub struct PassParametersBuffer {
+    uniform: PassParametersUniform,
+    fallback: Option<UniformFallback>,
+}
+
+impl PassParametersBuffer {
+    /// Size in bytes of the parameters, and of the push constant range holding them.
+    pub const SIZE: u32 = std::mem::size_of::<PassParametersUniform>() as u32;
+
+    /// Index of the bind group holding the uniform buffer, when push constants are unsupported.
+    pub const GROUP: u32 = 1;
+
+    /// Stages of render pipelines the parameters are visible to.
+    const RENDER_STAGES: ShaderStages = ShaderStages::VERTEX_FRAGMENT;
+
+    /// Declaration of the parameters in `parameters.wgsl`, which binds them as a uniform.
+    const UNIFORM_DECLARATION: &'static str = "@group(1) @binding(0)\nvar<uniform> parameters";
+
+    /// Declaration replacing the uniform declaration when push constants are used.
+    const PUSH_CONSTANT_DECLARATION: &'static str = "var<push_constant> parameters";
+
+    /// Hold the given parameters, creating a uniform buffer for them if the device was not
+    /// created with the `PUSH_CONSTANTS` feature.
+    pub fn new(device: &Device, parameters: &PassParameters) -> Self {
+        let uniform = PassParametersUniform::from(parameters);
+        if device.features().contains(Features::PUSH_CONSTANTS) {
+            return Self {
+                uniform,
+                fallback: None,
+            };
+        }
+
+        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("raymarks.pass_parameters"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("raymarks.pass_parameters_bind_group_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: Self::RENDER_STAGES | ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("raymarks.pass_parameters_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            uniform,
+            fallback: Some(UniformFallback {
+                buffer,
+                bind_group_layout,
+                bind_group,
+            }),
+        }
+    }
+
+    /// Get whether the parameters are passed as push constants, rather than a uniform buffer.
+    pub fn uses_push_constants(&self) -> bool {
+        self.fallback.is_none()
+    }
+
+    /// Store the given parameters. Push constants are set by the next pass, while the uniform
+    /// buffer is written from the next submission.
+    pub fn write(&mut self, queue: &Queue, parameters: &PassParameters) {
+        self.uniform = PassParametersUniform::from(parameters);
+        if let Some(fallback) = &self.fallback {
+            queue.write_buffer(&fallback.buffer, 0, bytemuck::bytes_of(&self.uniform));
+        }
+    }
+
+    /// Adapt shader source including `parameters.wgsl` to the way parameters are passed.
+    pub fn adapt_source(&self, source: String) -> String {
+        if self.uses_push_constants() {
+            source.replace(Self::UNIFORM_DECLARATION, Self::PUSH_CONSTANT_DECLARATION)
+        } else {
+            source
+        }
+    }
+
+    /// Get the bind group layouts to append to a pipeline layout after group 0.
+    pub fn bind_group_layouts(&self) -> Vec<&BindGroupLayout> {
+        self.fallback
+            .iter()
+            .map(|fallback| &fallback.bind_group_layout)
+            .collect()
+    }
+
+    /// Get the push constant ranges of a render pipeline layout.
+    pub fn render_push_constant_ranges(&self) -> Vec<PushConstantRange> {
+        self.push_constant_ranges(Self::RENDER_STAGES)
+    }
+
+    /// Get the push constant ranges of a compute pipeline layout.
+    pub fn compute_push_constant_ranges(&self) -> Vec<PushConstantRange> {
+        self.push_constant_ranges(ShaderStages::COMPUTE)
+    }
+
+    /// Private method to get a range covering the parameters in the given stages, if push
+    /// constants are used.
+    fn push_constant_ranges(&self, stages: ShaderStages) -> Vec<PushConstantRange> {
+        if self.uses_push_constants() {
+            vec![PushConstantRange {
+                stages,
+                range: 0..Self::SIZE,
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Set the parameters for the draws of a render pass.
+    pub fn set_render_pass(&self, render_pass: &mut RenderPass) {
+        match &self.fallback {
+            Some(fallback) => render_pass.set_bind_group(Self::GROUP, &fallback.bind_group, &[]),
+            None => render_pass.set_push_constants(
+                Self::RENDER_STAGES,
+                0,
+                bytemuck::bytes_of(&self.uniform),
+            ),
+        }
+    }
+
+    /// Set the parameters for the dispatches of a compute pass.
+    pub fn set_compute_pass(&self, compute_pass: &mut ComputePass) {
+        match &self.fallback {
+            Some(fallback) => compute_pass.set_bind_group(Self::GROUP, &fallback.bind_group, &[]),
+            None => compute_pass.set_push_constants(0, bytemuck::bytes_of(&self.uniform)),
+        }
+    }
+
+    /// Get the size of the GPU buffer in bytes, which is zero when push constants are used.
+    pub fn size(&self) -> BufferAddress {
+        self.fallback
+            .as_ref()
+            .map_or(0, |fallback| fallback.buffer.size())
+    }
+
+    /// Free the GPU buffer, if there is one.
+    pub fn destroy(&self) {
+        if let Some(fallback) = &self.fallback {
+            fallback.buffer.destroy();
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::context::{BenchmarkContext, SaveError};
+use serde::Serialize;
+use std::{cell::Cell, fmt};
+use wgpu::*;
+
+/// Counts of work done by the GPU during a pass. Counts for stages a pass does not use are zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PipelineStatistics {
+    pub vertex_shader_invocations: u64,
+    pub clipper_invocations: u64,
+    pub clipper_primitives_out: u64,
+    pub fragment_shader_invocations: u64,
+    pub compute_shader_invocations: u64,
+}
+
+impl fmt::Display for PipelineStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} vertex invocations, {} clipper invocations, {} primitives out, \
+             {} fragment invocations, {} compute invocations",
+            self.vertex_shader_invocations,
+            self.clipper_invocations,
+            self.clipper_primitives_out,
+            self.fragment_shader_invocations,
+            self.compute_shader_invocations
+        )
+    }
+}
+
+/// A GPU pipeline statistics query covering a whole pass, along with the buffers needed to resolve
+/// and read it back on the CPU.
+pub struct PipelineStatisticsQueries {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    mapped: Cell<bool>,
+}
+
+impl PipelineStatisticsQueries {
+    /// Statistics collected by the query. They are resolved in the order of their bits.
+    const TYPES: PipelineStatisticsTypes = PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS
+        .union(PipelineStatisticsTypes::CLIPPER_INVOCATIONS)
+        .union(PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT)
+        .union(PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS)
+        .union(PipelineStatisticsTypes::COMPUTE_SHADER_INVOCATIONS);
+
+    /// Size in bytes of the buffers holding resolved statistics, one 64-bit value per statistic.
+    const BUFFER_SIZE: BufferAddress = Self::TYPES.bits().count_ones() as BufferAddress * 8;
+
+    /// Create the query set and buffers, or `None` if the device was not created with the
+    /// `PIPELINE_STATISTICS_QUERY` feature.
+    pub fn new(device: &Device) -> Option<Self> {
+        if !device
+            .features()
+            .contains(Features::PIPELINE_STATISTICS_QUERY)
+        {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("raymarks.pipeline_statistics"),
+            ty: QueryType::PipelineStatistics(Self::TYPES),
+            count: 1,
+        });
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("raymarks.pipeline_statistics_resolve"),
+            size: Self::BUFFER_SIZE,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("raymarks.pipeline_statistics_readback"),
+            size: Self::BUFFER_SIZE,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            mapped: Cell::new(false),
+        })
+    }
+
+    /// Begin the query at the start of a render pass.
+    pub fn begin_render_pass(&self, render_pass: &mut RenderPass) {
+        render_pass.begin_pipeline_statistics_query(&self.query_set, 0);
+    }
+
+    /// Begin the query at the start of a compute pass.
+    pub fn begin_compute_pass(&self, compute_pass: &mut ComputePass) {
+        compute_pass.begin_pipeline_statistics_query(&self.query_set, 0);
+    }
+
+    /// Record commands resolving the query and copying it to the readback buffer.
+    pub fn resolve(&self, commands: &mut CommandEncoder) {
+        commands.resolve_query_set(&self.query_set, 0..1, &self.resolve_buffer, 0);
+        commands.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            Self::BUFFER_SIZE,
+        );
+    }
+
+    /// Read back the resolved statistics, blocking the current thread until the submission which
+    /// resolved them has finished, for at most `BenchmarkContext::POLL_TIMEOUT`. Returns an error
+    /// if the readback buffer could not be mapped, such as when the device is lost, or the GPU did
+    /// not finish in time, in which case the mapping is cancelled so the buffer can be mapped
+    /// again.
+    pub fn statistics(&self, device: &Device) -> Result<PipelineStatistics, SaveError> {
+        let buffer_slice = self.readback_buffer.slice(..);
+        let (sender, receiver) = flume::bounded(1);
+        self.mapped.set(true);
+        buffer_slice.map_async(MapMode::Read, move |r| {
+            // The reader may have stopped waiting for the mapping
+            let _ = sender.send(r);
+        });
+        if let Err(error) = BenchmarkContext::wait_until_mapped(device, &receiver) {
+            // Only a mapping which timed out is still pending
+            if let SaveError::GpuTimeout(_) = error {
+                self.readback_buffer.unmap();
+            }
+            self.mapped.set(false);
+            return Err(error);
+        }
+
+        let statistics = {
+            let view = buffer_slice.get_mapped_range();
+            let value = |index: usize| {
+                u64::from_le_bytes(view[index * 8..index * 8 + 8].try_into().unwrap())
+            };
+            PipelineStatistics {
+                vertex_shader_invocations: value(0),
+                clipper_invocations: value(1),
+                clipper_primitives_out: value(2),
+                fragment_shader_invocations: value(3),
+                compute_shader_invocations: value(4),
+            }
+        };
+        self.readback_buffer.unmap();
+        self.mapped.set(false);
+        Ok(statistics)
+    }
+
+    /// Destroy the query buffers, first unmapping the readback buffer if a read was interrupted.
+    pub fn destroy(&self) {
+        if self.mapped.replace(false) {
+            self.readback_buffer.unmap();
+        }
+        self.resolve_buffer.destroy();
+        self.readback_buffer.destroy();
+    }
+}
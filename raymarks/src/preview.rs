@@ -0,0 +1,235 @@
+use crate::shaders::{validate_wgsl, Shader};
+use log::{error, warn};
+use std::{sync::Arc, time::Duration};
+use wgpu::*;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    platform::pump_events::EventLoopExtPumpEvents,
+    window::{Window, WindowId},
+};
+
+/// Handles window events, creating the window when the event loop starts.
+#[derive(Default)]
+struct PreviewHandler {
+    window: Option<Arc<Window>>,
+    close_requested: bool,
+}
+
+impl ApplicationHandler for PreviewHandler {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_none() {
+            match event_loop.create_window(Window::default_attributes().with_title("raymarks")) {
+                Ok(window) => self.window = Some(Arc::new(window)),
+                Err(window_error) => error!("Failed to create preview window: {}", window_error),
+            }
+        }
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        if let WindowEvent::CloseRequested = event {
+            self.close_requested = true;
+        }
+    }
+}
+
+/// A window which the render target is presented to after each submit, for watching benchmarks
+/// live.
+pub struct Preview {
+    event_loop: EventLoop<()>,
+    handler: PreviewHandler,
+    surface: Surface<'static>,
+    config: Option<SurfaceConfiguration>,
+    pipeline: Option<RenderPipeline>,
+    sampler: Option<Sampler>,
+}
+
+impl Preview {
+    /// Open a preview window and create a surface for it, or `None` if either fails.
+    pub fn new(instance: &wgpu::Instance) -> Option<Self> {
+        let mut event_loop = EventLoop::new()
+            .inspect_err(|loop_error| warn!("Failed to create event loop: {}", loop_error))
+            .ok()?;
+
+        // The window is created once the event loop has started
+        let mut handler = PreviewHandler::default();
+        event_loop.pump_app_events(Some(Duration::ZERO), &mut handler);
+        let window = handler.window.clone()?;
+
+        let surface = instance
+            .create_surface(window)
+            .inspect_err(|surface_error| warn!("Failed to create surface: {}", surface_error))
+            .ok()?;
+
+        Some(Self {
+            event_loop,
+            handler,
+            surface,
+            config: None,
+            pipeline: None,
+            sampler: None,
+        })
+    }
+
+    /// Get the surface, so an adapter compatible with it can be requested.
+    pub fn surface(&self) -> &Surface<'static> {
+        &self.surface
+    }
+
+    /// Process pending window events. Returns `false` once the window has been closed.
+    pub fn pump_events(&mut self) -> bool {
+        self.event_loop
+            .pump_app_events(Some(Duration::ZERO), &mut self.handler);
+        !self.handler.close_requested
+    }
+
+    /// Draw the render target to the window, scaled to fit, and present it.
+    pub fn present(
+        &mut self,
+        adapter: &Adapter,
+        device: &Device,
+        queue: &Queue,
+        render_target: &TextureView,
+    ) {
+        if !self.pump_events() {
+            return;
+        }
+        let Some(window) = &self.handler.window else {
+            return;
+        };
+
+        // Reconfigure the surface whenever the window is resized
+        let size = window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        let configured = matches!(
+            &self.config,
+            Some(config) if config.width == size.width && config.height == size.height
+        );
+        if !configured {
+            let Some(mut config) =
+                self.surface
+                    .get_default_config(adapter, size.width, size.height)
+            else {
+                return;
+            };
+            let formats = self.surface.get_capabilities(adapter).formats;
+            config.format = formats
+                .iter()
+                .copied()
+                .find(TextureFormat::is_srgb)
+                .unwrap_or(config.format);
+            config.present_mode = PresentMode::AutoNoVsync;
+            self.surface.configure(device, &config);
+            if self.config.as_ref().map(|old| old.format) != Some(config.format) {
+                self.pipeline = None;
+            }
+            self.config = Some(config);
+        }
+        let Some(config) = &self.config else {
+            return;
+        };
+
+        if self.pipeline.is_none() {
+            self.pipeline = Self::pipeline(device, config.format);
+        }
+        let Some(pipeline) = &self.pipeline else {
+            return;
+        };
+        let sampler = self.sampler.get_or_insert_with(|| {
+            device.create_sampler(&SamplerDescriptor {
+                label: Some("raymarks.preview_sampler"),
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            })
+        });
+
+        let surface_texture = match self.surface.get_current_texture() {
+            Ok(surface_texture) => surface_texture,
+            Err(surface_error) => {
+                warn!("Failed to get surface texture: {}", surface_error);
+                self.config = None;
+                return;
+            }
+        };
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("raymarks.preview_bind_group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(render_target),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        let mut commands = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("raymarks.preview"),
+        });
+        {
+            let mut render_pass = commands.begin_render_pass(&RenderPassDescriptor {
+                label: Some("raymarks.preview_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &surface_texture
+                        .texture
+                        .create_view(&TextureViewDescriptor::default()),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(commands.finish()));
+        surface_texture.present();
+    }
+
+    /// Private method to create a pipeline drawing a texture over the whole surface.
+    fn pipeline(device: &Device, format: TextureFormat) -> Option<RenderPipeline> {
+        let source = Shader::Blit
+            .load_source_preprocessed()
+            .and_then(|source| validate_wgsl(&source, device.features()).map(|_| source))
+            .inspect_err(|shader_error| error!("Failed to load preview shader: {}", shader_error))
+            .ok()?;
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("raymarks.blit"),
+            source: ShaderSource::Wgsl(source.into()),
+        });
+
+        Some(device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("raymarks.preview_pipeline"),
+            layout: None,
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vertex_shader"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fragment_shader"),
+                compilation_options: Default::default(),
+                targets: &[Some(format.into())],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        }))
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{
+    benchmark::{Drift, Measurement, Statistics, TailLatency},
+    context::BenchmarkContext,
+    mesh::{DrawMode, InstanceStorage},
+    pipeline_statistics::PipelineStatistics,
+    resolution::Resolution,
+};
+use serde::Serialize;
+use std::{
+    fs::{create_dir_all, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use wgpu::AdapterInfo;
+
+/// Version of wgpu the benchmarks are built against, which must be kept in sync with `Cargo.toml`.
+const WGPU_VERSION: &str = "23.0.1";
+
+/// Description of the machine a report was produced on, so results can be compared across
+/// machines.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportHeader {
+    pub adapter_name: String,
+    pub backend: String,
+    pub driver: String,
+    pub driver_info: String,
+    pub device_type: String,
+    pub wgpu_version: String,
+    pub os: String,
+}
+
+impl From<&AdapterInfo> for ReportHeader {
+    fn from(info: &AdapterInfo) -> Self {
+        Self {
+            adapter_name: info.name.clone(),
+            backend: format!("{:?}", info.backend),
+            driver: info.driver.clone(),
+            driver_info: info.driver_info.clone(),
+            device_type: format!("{:?}", info.device_type),
+            wgpu_version: WGPU_VERSION.to_string(),
+            os: std::env::consts::OS.to_string(),
+        }
+    }
+}
+
+/// Comparison of rasterizing and raymarching the same scene at the same resolution.
+#[derive(Clone, Debug, Serialize)]
+pub struct Comparison {
+    /// GPU timing of the rasterization passes, or `None` if timestamp queries are unsupported.
+    pub rasterization: Option<Statistics>,
+    /// GPU timing of the raymarch passes, or `None` if timestamp queries are unsupported.
+    pub raymarch: Option<Statistics>,
+    /// Mean raymarch duration over mean rasterization duration, so values above 1 mean
+    /// raymarching is slower. `None` if the passes were not timed.
+    pub raymarch_to_rasterization_ratio: Option<f64>,
+    /// Largest absolute difference between the two images in any channel of any pixel.
+    pub max_difference: u8,
+    /// Mean absolute difference between the two images over all channels of all pixels.
+    pub mean_difference: f64,
+    /// Path of the image showing both renders and their difference heatmap side by side, if it
+    /// was saved.
+    pub image: Option<PathBuf>,
+}
+
+/// Which side limited how quickly a configuration's passes could be run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bound {
+    /// Recording and submitting a pass took at least as long as the GPU took to run it.
+    Cpu,
+    /// The GPU took longer to run a pass than the CPU took to record and submit it.
+    Gpu,
+}
+
+/// Classification of a configuration as CPU- or GPU-bound, derived from its timing breakdown.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Bottleneck {
+    pub bound: Bound,
+    /// Mean CPU time per pass over mean GPU time per pass, so values of 1 or more mean the
+    /// configuration is CPU-bound.
+    pub cpu_to_gpu_ratio: f64,
+}
+
+/// Measurements of a single benchmark configuration.
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchmarkResult {
+    /// Name of the benchmark.
+    pub benchmark: String,
+    /// Name of the adapter the configuration was measured on, so results of several adapters can
+    /// be compared.
+    pub adapter: String,
+    /// Size of the render target.
+    pub resolution: Resolution,
+    /// Number of models or triangles drawn, for benchmarks which draw a configurable amount.
+    pub count: Option<u32>,
+    /// How models were drawn, for benchmarks which draw models.
+    pub draw_mode: Option<DrawMode>,
+    /// Where instances were read from, for benchmarks which draw instanced models.
+    pub instance_storage: Option<InstanceStorage>,
+    /// GPU timing of the measured passes, or `None` if timestamp queries are unsupported.
+    pub statistics: Option<Statistics>,
+    /// CPU timing of recording the measured passes' commands.
+    pub encode_statistics: Option<Statistics>,
+    /// CPU timing of finishing each submission's command buffer.
+    pub finish_statistics: Option<Statistics>,
+    /// CPU timing of the calls submitting each command buffer to the queue.
+    pub submit_statistics: Option<Statistics>,
+    /// Work done by the GPU during the last pass, or `None` if pipeline statistics queries are
+    /// unsupported.
+    pub pipeline_statistics: Option<PipelineStatistics>,
+    /// GPU memory allocated by the context for the configuration, in bytes.
+    pub allocated_bytes: u64,
+    /// Whether the configuration was CPU- or GPU-bound, or `None` if its passes were not timed on
+    /// both.
+    pub bottleneck: Option<Bottleneck>,
+    /// Change in GPU timing over a soak, or `None` if the configuration was not soaked.
+    pub drift: Option<Drift>,
+    /// Percentiles and slowest samples of the GPU timing, or `None` if the passes were not timed.
+    pub tail_latency: Option<TailLatency>,
+    /// Whether the GPU timing varied more than the configured maximum, so it may be skewed by
+    /// background load or throttling.
+    pub noisy: bool,
+    /// Comparison of rasterization and raymarching, for benchmarks which compare them.
+    pub comparison: Option<Comparison>,
+    /// Whether the pixels read back matched what the benchmark expected, for benchmarks which
+    /// check them, such as the self-test.
+    pub passed: Option<bool>,
+}
+
+impl BenchmarkResult {
+    /// An untimed result of the given benchmark at the given resolution on the context's adapter,
+    /// along with the memory the context has allocated. Benchmarks set the fields they measure
+    /// with struct update syntax.
+    pub fn new(context: &BenchmarkContext, benchmark: &str, resolution: Resolution) -> Self {
+        Self {
+            benchmark: benchmark.to_string(),
+            adapter: context.adapter_info().name.clone(),
+            resolution,
+            count: None,
+            draw_mode: None,
+            instance_storage: None,
+            statistics: None,
+            encode_statistics: None,
+            finish_statistics: None,
+            submit_statistics: None,
+            pipeline_statistics: None,
+            allocated_bytes: context.allocated_bytes(),
+            bottleneck: None,
+            drift: None,
+            tail_latency: None,
+            noisy: false,
+            comparison: None,
+            passed: None,
+        }
+    }
+
+    /// A result of the given benchmark at the given resolution from a measurement taken with
+    /// `benchmark::measure`, along with the pipeline statistics of the last submitted pass. The
+    /// bottleneck is classified with the context's batch size, which `measure` sets from its
+    /// options. Benchmarks drawing a configurable amount of models set the count, draw mode and
+    /// instance storage with struct update syntax.
+    pub fn from_measurement(
+        context: &BenchmarkContext,
+        benchmark: &str,
+        resolution: Resolution,
+        measurement: &Measurement,
+    ) -> Self {
+        Self {
+            statistics: measurement.gpu,
+            encode_statistics: measurement.encode,
+            finish_statistics: measurement.finish,
+            submit_statistics: measurement.submit,
+            pipeline_statistics: context.last_pass_statistics(),
+            bottleneck: measurement.bottleneck(context.batch_size()),
+            drift: measurement.drift,
+            tail_latency: measurement.tail.clone(),
+            noisy: measurement.noisy,
+            ..Self::new(context, benchmark, resolution)
+        }
+    }
+
+    /// The failure to record in the report for the result, if its check failed.
+    pub fn failure(&self) -> Option<BenchmarkFailure> {
+        (self.passed == Some(false)).then(|| BenchmarkFailure {
+            benchmark: self.benchmark.clone(),
+            reason: format!(
+                "check of the pixels read back at {} failed",
+                self.resolution
+            ),
+        })
+    }
+}
+
+/// A benchmark which failed to complete, such as by running past its timeout.
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchmarkFailure {
+    /// Name of the benchmark.
+    pub benchmark: String,
+    /// Why the benchmark failed.
+    pub reason: String,
+}
+
+/// Results of a benchmark run, along with a header describing the machine they were measured on.
+/// Benchmarks which failed keep the results of the configurations they completed.
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub header: ReportHeader,
+    pub results: Vec<BenchmarkResult>,
+    pub failures: Vec<BenchmarkFailure>,
+}
+
+impl BenchmarkReport {
+    /// Create an empty report for the given adapter.
+    pub fn new(adapter_info: &AdapterInfo) -> Self {
+        Self {
+            header: adapter_info.into(),
+            results: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Write the report as pretty-printed JSON to the given path, creating its directory if it
+    /// does not exist.
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+/// Write reports of several adapters as a pretty-printed JSON array to the given path, creating
+/// its directory if it does not exist.
+pub fn write_reports_json(reports: &[BenchmarkReport], path: &Path) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        create_dir_all(directory)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, reports)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An untimed self-test result at 64x64 whose check passed, failed or was not run.
+    fn result(passed: Option<bool>) -> BenchmarkResult {
+        BenchmarkResult {
+            benchmark: "self_test".to_string(),
+            adapter: "test".to_string(),
+            resolution: (64, 64).into(),
+            count: None,
+            draw_mode: None,
+            instance_storage: None,
+            statistics: None,
+            encode_statistics: None,
+            finish_statistics: None,
+            submit_statistics: None,
+            pipeline_statistics: None,
+            allocated_bytes: 0,
+            bottleneck: None,
+            drift: None,
+            tail_latency: None,
+            noisy: false,
+            comparison: None,
+            passed,
+        }
+    }
+
+    #[test]
+    fn failed_checks_are_failures() {
+        let failure = result(Some(false)).failure().unwrap();
+        assert_eq!(failure.benchmark, "self_test");
+        assert!(failure.reason.contains("64x64"), "{}", failure.reason);
+    }
+
+    #[test]
+    fn passed_and_unchecked_results_are_not_failures() {
+        assert!(result(Some(true)).failure().is_none());
+        assert!(result(None).failure().is_none());
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{error::Error, fmt, str::FromStr};
+
+/// Size of a render target in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    /// 1280x720.
+    pub const HD: Self = Self::new(1280, 720);
+
+    /// 1920x1080.
+    pub const FHD: Self = Self::new(1920, 1080);
+
+    /// 3840x2160.
+    pub const UHD_4K: Self = Self::new(3840, 2160);
+
+    /// Create a resolution of the given width and height.
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Get the ratio of width over height.
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+}
+
+impl From<(u32, u32)> for Resolution {
+    fn from((width, height): (u32, u32)) -> Self {
+        Self::new(width, height)
+    }
+}
+
+impl From<Resolution> for (u32, u32) {
+    fn from(resolution: Resolution) -> Self {
+        (resolution.width, resolution.height)
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Error produced when a resolution is not of the form `WIDTHxHEIGHT`, with positive sides.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseResolutionError(String);
+
+impl fmt::Display for ParseResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected WIDTHxHEIGHT, got `{}`", self.0)
+    }
+}
+
+impl Error for ParseResolutionError {}
+
+impl<'de> Deserialize<'de> for Resolution {
+    /// Deserialize a resolution from a string of the form `WIDTHxHEIGHT`, as written in configs.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = ParseResolutionError;
+
+    /// Parse a resolution of the form `WIDTHxHEIGHT`, such as `1920x1080`.
+    fn from_str(resolution: &str) -> Result<Self, Self::Err> {
+        let error = || ParseResolutionError(resolution.to_string());
+        let (width, height) = resolution.split_once('x').ok_or_else(error)?;
+        let parse = |side: &str| {
+            side.trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|&side| side > 0)
+                .ok_or_else(error)
+        };
+        Ok(Self::new(parse(width)?, parse(height)?))
+    }
+}
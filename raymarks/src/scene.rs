@@ -0,0 +1,80 @@
+use crate::{geometry::Mesh, mesh::Instance};
+
+/// Material of a scene object, selecting its base color from the palette in `common.wgsl`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub u32);
+
+impl MaterialId {
+    pub const RED: Self = Self(0);
+    pub const GREEN: Self = Self(1);
+    pub const BLUE: Self = Self(2);
+    pub const WHITE: Self = Self(3);
+}
+
+/// A mesh drawn once per transform, with a single material.
+#[derive(Clone, Debug)]
+pub struct SceneObject {
+    pub mesh: Mesh,
+    pub transforms: Vec<Instance>,
+    pub material: MaterialId,
+}
+
+/// A list of objects drawn by rasterization passes, so heterogeneous scenes can be benchmarked.
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    pub objects: Vec<SceneObject>,
+}
+
+impl Scene {
+    /// Create an empty scene.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A scene of a single untransformed triangle.
+    pub fn single_triangle() -> Self {
+        Self::new().with_object(Mesh::triangle(), vec![Instance::IDENTITY], MaterialId::RED)
+    }
+
+    /// Spheres matching the signed distance field raymarched by `raymarch.wgsl` at time zero, so
+    /// rasterization and raymarching can be compared on the same scene.
+    pub fn sdf_spheres() -> Self {
+        let sphere = |center: [f32; 3], radius: f32| Instance {
+            translation: center,
+            scale: radius,
+            material: 0,
+        };
+        Self::new().with_object(
+            Mesh::uv_sphere(32, 64),
+            vec![
+                sphere([0.0, 0.0, 0.0], 1.0),
+                sphere([-1.5, -0.5, 1.0], 0.5),
+                sphere([1.5, 0.5, -1.0], 0.75),
+            ],
+            MaterialId::RED,
+        )
+    }
+
+    /// Add an object drawn once per transform with the given material.
+    pub fn with_object(
+        mut self,
+        mesh: Mesh,
+        transforms: Vec<Instance>,
+        material: MaterialId,
+    ) -> Self {
+        self.objects.push(SceneObject {
+            mesh,
+            transforms,
+            material,
+        });
+        self
+    }
+
+    /// Get the total number of instances drawn across all objects.
+    pub fn instance_count(&self) -> usize {
+        self.objects
+            .iter()
+            .map(|object| object.transforms.len())
+            .sum()
+    }
+}
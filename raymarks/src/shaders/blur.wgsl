@@ -0,0 +1,38 @@
+#include "common.wgsl"
+
+// Storage texture of the render target's format, whose declaration is replaced before compiling
+alias OutputTexture = texture_storage_2d<rgba8unorm, write>;
+
+@group(0) @binding(0) var input: texture_2d<f32>;
+@group(0) @binding(1) var input_sampler: sampler;
+@group(0) @binding(2) var output: OutputTexture;
+
+// Whether the output is 8-bit sRGB, rather than linear HDR
+override ENCODE_SRGB: bool = true;
+
+// Width and height of each workgroup, which must match `POSTPROCESS_WORKGROUP_SIZE` on the CPU side
+const WORKGROUP_SIZE: u32 = 8u;
+
+// Blur each pixel with a 3x3 gaussian kernel, weighting neighbors by 1, 2, 1 along each axis.
+// Dispatches round up to whole workgroups, so invocations past the edges of the output return early
+@compute @workgroup_size(WORKGROUP_SIZE, WORKGROUP_SIZE)
+fn postprocess_shader(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    // The input is sampled, so its sRGB texels are read as linear colors
+    var color = vec4<f32>(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let weight = f32((2 - abs(x)) * (2 - abs(y))) / 16.0;
+            let uv = (vec2<f32>(id.xy) + 0.5 + vec2<f32>(f32(x), f32(y))) / vec2<f32>(size);
+            color += weight * textureSampleLevel(input, input_sampler, uv, 0.0);
+        }
+    }
+    if ENCODE_SRGB {
+        color = vec4<f32>(linear_to_srgb(color.rgb), color.a);
+    }
+    textureStore(output, id.xy, color);
+}
@@ -0,0 +1,46 @@
+// Definitions shared between the rasterization, raymarch and post-process shaders
+
+// Normalized (0.5, 1.0, 1.0)
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.33333333, 0.66666667, 0.66666667);
+const BASE_COLOR: vec3<f32> = vec3<f32>(1.0, 0.0, 0.0);
+
+// Base color of each material, matching the constants of `MaterialId`
+fn material_color(material: u32) -> vec3<f32> {
+    switch material {
+        case 1u: {
+            return vec3<f32>(0.0, 1.0, 0.0);
+        }
+        case 2u: {
+            return vec3<f32>(0.0, 0.0, 1.0);
+        }
+        case 3u: {
+            return vec3<f32>(1.0, 1.0, 1.0);
+        }
+        default: {
+            return BASE_COLOR;
+        }
+    }
+}
+
+// Simple directional lighting of the given base color with an ambient term
+fn shade_color(normal: vec3<f32>, color: vec3<f32>) -> vec3<f32> {
+    let diffuse = max(dot(normalize(normal), LIGHT_DIRECTION), 0.0);
+    return color * (0.2 + 0.8 * diffuse);
+}
+
+// Simple directional lighting of the default base color
+fn shade(normal: vec3<f32>) -> vec3<f32> {
+    return shade_color(normal, BASE_COLOR);
+}
+
+// Storage textures cannot be sRGB, so compute shaders encode colors manually
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+// Signed distance to a sphere of the given radius centered at the given point
+fn sphere_distance(position: vec3<f32>, center: vec3<f32>, radius: f32) -> f32 {
+    return length(position - center) - radius;
+}
@@ -0,0 +1,15 @@
+// Copies the first sample of a multisampled depth buffer into a single-sampled color target, since
+// multisampled textures cannot be copied to a buffer
+@group(0) @binding(0) var depth: texture_depth_multisampled_2d;
+
+// A single triangle covering the whole screen
+@vertex
+fn vertex_shader(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+@fragment
+fn fragment_shader(@builtin(position) position: vec4<f32>) -> @location(0) f32 {
+    return textureLoad(depth, vec2<i32>(position.xy), 0);
+}
@@ -0,0 +1,18 @@
+#include "rasterization.wgsl"
+
+// Surface attributes written to each attachment of the G-buffer, for deferred shading
+struct GBufferOutput {
+    @location(0) albedo: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+    @location(2) position: vec4<f32>,
+}
+
+@fragment
+fn gbuffer_fragment_shader(in: VertexOutput) -> GBufferOutput {
+    var out: GBufferOutput;
+    out.albedo = vec4<f32>(material_color(in.material), 1.0);
+    // Normals are packed into the 0 to 1 range so they can be stored in unsigned formats
+    out.normal = vec4<f32>(normalize(in.normal) * 0.5 + 0.5, 1.0);
+    out.position = vec4<f32>(in.world_position, 1.0);
+    return out;
+}
@@ -0,0 +1,18 @@
+// Size of the render target, so the gradient reaches exactly 0 and 1 at the edge pixels
+override WIDTH: f32 = 2.0;
+override HEIGHT: f32 = 2.0;
+
+// A single triangle covering the whole render target
+@vertex
+fn vertex_shader(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+// Red increases to the right and green downwards, while blue decreases to the right, so every
+// corner has a different color and flipped rows or swapped channels are caught
+@fragment
+fn fragment_shader(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = floor(position.xy) / max(vec2<f32>(WIDTH, HEIGHT) - 1.0, vec2<f32>(1.0));
+    return vec4<f32>(uv.x, uv.y, 1.0 - uv.x, 1.0);
+}
@@ -1,32 +1,550 @@
+use naga::valid::{Capabilities, ValidationFlags, Validator};
 use std::{
+    collections::HashSet,
+    env::var_os,
+    error::Error,
+    fmt,
     fs::read_to_string,
+    io::{self, ErrorKind},
     path::{Path, PathBuf},
 };
+use wgpu::Features;
 
-/// A layer of abstraction over loading shader files directly from the `src/shaders` directory,
-/// for ease of use.
+/// A layer of abstraction over loading shader files from the `src/shaders` directory, for ease of
+/// use. Built-in shaders are embedded in the binary, and only read from the shader directory with
+/// the `hot-reload` feature or when `RAYMARKS_SHADER_DIR` is set. Custom shaders can be loaded
+/// from any path.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Shader {
     Rasterization,
+    GBuffer,
+    Raymarch,
+    Triangles,
+    Blit,
+    Blur,
+    Stencil,
+    DepthResolve,
+    Gradient,
+    VertexProbe,
+    Custom(PathBuf),
+}
+
+/// Files of the `src/shaders` directory embedded in the binary, by filename, so built-in shaders
+/// and their includes load without the source tree.
+const EMBEDDED_SOURCES: [(&str, &str); 12] = [
+    ("blit.wgsl", include_str!("blit.wgsl")),
+    ("blur.wgsl", include_str!("blur.wgsl")),
+    ("common.wgsl", include_str!("common.wgsl")),
+    ("depth_resolve.wgsl", include_str!("depth_resolve.wgsl")),
+    ("gbuffer.wgsl", include_str!("gbuffer.wgsl")),
+    ("gradient.wgsl", include_str!("gradient.wgsl")),
+    ("parameters.wgsl", include_str!("parameters.wgsl")),
+    ("rasterization.wgsl", include_str!("rasterization.wgsl")),
+    ("raymarch.wgsl", include_str!("raymarch.wgsl")),
+    ("stencil.wgsl", include_str!("stencil.wgsl")),
+    ("triangles.wgsl", include_str!("triangles.wgsl")),
+    ("vertex_probe.wgsl", include_str!("vertex_probe.wgsl")),
+];
+
+/// Error produced when a shader cannot be loaded, or its source is invalid.
+#[derive(Debug)]
+pub enum ShaderError {
+    /// The shader source file does not exist.
+    NotFound(PathBuf),
+    /// The shader source file could not be read.
+    Io(PathBuf, io::Error),
+    /// An `#include` directive on the given line of a file is not followed by a quoted filename.
+    InvalidInclude(PathBuf, usize),
+    /// A file includes itself, directly or through other includes.
+    IncludeCycle(PathBuf),
+    /// The shader source could not be parsed. Holds the diagnostic, including line and column.
+    Parse(String),
+    /// The shader source parsed, but is invalid. Holds the diagnostic, including line and column.
+    Validation(String),
+    /// A compute entry point's workgroup size differs from the size dispatches are computed with,
+    /// so they would not cover the output. Holds the entry point and its workgroup size.
+    WorkgroupSize(String, [u32; 3]),
+    /// The shader has no compute entry point with the given name.
+    MissingEntryPoint(String),
+    /// The shader declares no constant with the given name on a line of its own, so the value
+    /// substituted for it before compiling has nowhere to go.
+    MissingConstant(String),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "shader file {} not found", path.display()),
+            Self::Io(path, error) => {
+                write!(f, "failed to read shader {}: {}", path.display(), error)
+            }
+            Self::InvalidInclude(path, line) => write!(
+                f,
+                "invalid include directive in shader {} on line {}",
+                path.display(),
+                line
+            ),
+            Self::IncludeCycle(path) => write!(f, "shader {} includes itself", path.display()),
+            Self::Parse(diagnostic) => write!(f, "failed to parse shader:\n{}", diagnostic),
+            Self::Validation(diagnostic) => write!(f, "invalid shader:\n{}", diagnostic),
+            Self::WorkgroupSize(entry_point, size) => write!(
+                f,
+                "entry point {} has workgroup size {:?}, which differs from the dispatched size",
+                entry_point, size
+            ),
+            Self::MissingEntryPoint(entry_point) => {
+                write!(f, "shader has no compute entry point {}", entry_point)
+            }
+            Self::MissingConstant(name) => {
+                write!(f, "shader declares no constant {} to substitute", name)
+            }
+        }
+    }
+}
+
+impl Error for ShaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(_, error) => Some(error),
+            _ => None,
+        }
+    }
 }
 
 impl Shader {
-    /// Get the name for the source file of the shader.
-    pub fn source_file(&self) -> &str {
+    /// Every shader built into the benchmark, excluding custom shaders.
+    pub const BUILT_IN: [Shader; 10] = [
+        Shader::Rasterization,
+        Shader::GBuffer,
+        Shader::Raymarch,
+        Shader::Triangles,
+        Shader::Blit,
+        Shader::Blur,
+        Shader::Stencil,
+        Shader::DepthResolve,
+        Shader::Gradient,
+        Shader::VertexProbe,
+    ];
+
+    /// Create a custom shader loaded from the given path.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self::Custom(path.into())
+    }
+
+    /// Get the name for the source file of the shader, or the path given for custom shaders.
+    pub fn source_file(&self) -> &Path {
         match self {
-            Self::Rasterization => "rasterization.wgsl",
+            Self::Rasterization => Path::new("rasterization.wgsl"),
+            Self::GBuffer => Path::new("gbuffer.wgsl"),
+            Self::Raymarch => Path::new("raymarch.wgsl"),
+            Self::Triangles => Path::new("triangles.wgsl"),
+            Self::Blit => Path::new("blit.wgsl"),
+            Self::Blur => Path::new("blur.wgsl"),
+            Self::Stencil => Path::new("stencil.wgsl"),
+            Self::DepthResolve => Path::new("depth_resolve.wgsl"),
+            Self::Gradient => Path::new("gradient.wgsl"),
+            Self::VertexProbe => Path::new("vertex_probe.wgsl"),
+            Self::Custom(path) => path,
         }
     }
 
-    /// Load the source code of the shader.
-    pub fn load_source(&self) -> String {
-        let path = Self::shader_directory().join(self.source_file());
-        read_to_string(path).unwrap()
+    /// Get the path of the source file of the shader. Built-in shaders are in the shader
+    /// directory, while custom shaders are at their given path. Built-in shaders may be loaded
+    /// from their embedded copies instead, so the file need not exist.
+    pub fn source_path(&self) -> PathBuf {
+        match self {
+            Self::Custom(path) => path.clone(),
+            _ => Self::shader_directory().join(self.source_file()),
+        }
+    }
+
+    /// Load the source code of the shader, without resolving `#include` directives.
+    pub fn load_source(&self) -> Result<String, ShaderError> {
+        read_source(&self.source_path())
+    }
+
+    /// Load the source code of the shader, recursively replacing `#include "file.wgsl"` lines
+    /// with the contents of the named file in the shader directory. Each file is included once,
+    /// so files included through several others do not define their items twice.
+    pub fn load_source_preprocessed(&self) -> Result<String, ShaderError> {
+        let mut output = String::new();
+        preprocess(
+            &self.source_path(),
+            &Self::shader_directory(),
+            &mut Vec::new(),
+            &mut HashSet::new(),
+            &mut output,
+        )?;
+        Ok(output)
     }
 
-    /// Get the directory containing the shader source files.
+    /// Environment variable overriding the directory containing the shader source files.
+    pub const DIRECTORY_VARIABLE: &str = "RAYMARKS_SHADER_DIR";
+
+    /// Get the directory containing the shader source files. This is the directory named by the
+    /// `RAYMARKS_SHADER_DIR` environment variable if it is set, and otherwise the crate's
+    /// `src/shaders` directory, which only exists when running from the source tree.
     pub fn shader_directory() -> PathBuf {
-        Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("src")
-            .join("shaders")
+        match var_os(Self::DIRECTORY_VARIABLE) {
+            Some(directory) => PathBuf::from(directory),
+            None => Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("src")
+                .join("shaders"),
+        }
+    }
+}
+
+/// Read a shader source file, distinguishing missing files from other errors. Files in the
+/// shader directory are read from their embedded copies if they are used.
+fn read_source(path: &Path) -> Result<String, ShaderError> {
+    if let Some(source) = embedded_source(path) {
+        return Ok(source.to_string());
+    }
+    read_to_string(path).map_err(|error| match error.kind() {
+        ErrorKind::NotFound => ShaderError::NotFound(path.to_path_buf()),
+        _ => ShaderError::Io(path.to_path_buf(), error),
+    })
+}
+
+/// Get the embedded copy of a file in the shader directory. Files are read from disk instead with
+/// the `hot-reload` feature, so edits take effect, or when the shader directory is overridden.
+fn embedded_source(path: &Path) -> Option<&'static str> {
+    if cfg!(feature = "hot-reload") || var_os(Shader::DIRECTORY_VARIABLE).is_some() {
+        return None;
+    }
+    let filename = path.strip_prefix(Shader::shader_directory()).ok()?;
+    EMBEDDED_SOURCES
+        .iter()
+        .find(|(name, _)| Path::new(name) == filename)
+        .map(|(_, source)| *source)
+}
+
+/// Append a shader source file to the output, resolving includes relative to the given
+/// directory. The stack holds the files currently being included, to detect cycles, while files
+/// which were already included are skipped.
+fn preprocess(
+    path: &Path,
+    directory: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut HashSet<PathBuf>,
+    output: &mut String,
+) -> Result<(), ShaderError> {
+    if stack.iter().any(|including| including == path) {
+        return Err(ShaderError::IncludeCycle(path.to_path_buf()));
+    }
+    if included.contains(path) {
+        return Ok(());
+    }
+    let source = read_source(path)?;
+
+    stack.push(path.to_path_buf());
+    for (line_index, line) in source.lines().enumerate() {
+        match line.trim().strip_prefix("#include") {
+            Some(include) => {
+                let filename = include
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|include| include.strip_suffix('"'))
+                    .ok_or_else(|| {
+                        ShaderError::InvalidInclude(path.to_path_buf(), line_index + 1)
+                    })?;
+                let path = directory.join(filename);
+                preprocess(&path, directory, stack, included, output)?;
+            }
+            None => {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+    stack.pop();
+    included.insert(path.to_path_buf());
+    Ok(())
+}
+
+/// Parse and validate WGSL source against the capabilities of a device with the given features,
+/// so errors are reported with line and column diagnostics rather than as a panic from within
+/// wgpu.
+pub fn validate_wgsl(source: &str, features: Features) -> Result<(), ShaderError> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|error| ShaderError::Parse(error.emit_to_string(source)))?;
+    Validator::new(ValidationFlags::all(), capabilities(features))
+        .validate(&module)
+        .map_err(|error| ShaderError::Validation(error.emit_to_string(source)))?;
+    Ok(())
+}
+
+/// Check that the given compute entry point of WGSL source has square workgroups of the given
+/// width and height, as dispatches over an image are computed with.
+pub fn check_workgroup_size(source: &str, entry_point: &str, size: u32) -> Result<(), ShaderError> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|error| ShaderError::Parse(error.emit_to_string(source)))?;
+    let workgroup_size = module
+        .entry_points
+        .iter()
+        .find(|entry| entry.name == entry_point && entry.stage == naga::ShaderStage::Compute)
+        .ok_or_else(|| ShaderError::MissingEntryPoint(entry_point.to_string()))?
+        .workgroup_size;
+    if workgroup_size != [size, size, 1] {
+        return Err(ShaderError::WorkgroupSize(
+            entry_point.to_string(),
+            workgroup_size,
+        ));
+    }
+    Ok(())
+}
+
+/// Replace the value of the named constant declared on a line of its own in WGSL source, such as
+/// `const SIZE: u32 = 16u;`, keeping its type. Returns an error if no such declaration is found,
+/// rather than compiling the source with its original value.
+pub fn substitute_constant(source: &str, name: &str, value: &str) -> Result<String, ShaderError> {
+    let mut output = String::with_capacity(source.len());
+    let mut found = false;
+    for line in source.split_inclusive('\n') {
+        let declares = line
+            .trim_start()
+            .strip_prefix("const ")
+            .and_then(|rest| rest.trim_start().strip_prefix(name))
+            .is_some_and(|rest| rest.trim_start().starts_with([':', '=']));
+        match (line.find('='), line.find(';')) {
+            (Some(equals), Some(end)) if declares && !found && equals < end => {
+                output.push_str(&line[..=equals]);
+                output.push(' ');
+                output.push_str(value);
+                output.push_str(&line[end..]);
+                found = true;
+            }
+            _ => output.push_str(line),
+        }
+    }
+    if found {
+        Ok(output)
+    } else {
+        Err(ShaderError::MissingConstant(name.to_string()))
+    }
+}
+
+/// Get the shader capabilities of a device with the given features, as wgpu derives them when
+/// creating shader modules. Capabilities wgpu derives from downlevel flags are assumed, as every
+/// adapter compliant with WebGPU supports them.
+pub fn capabilities(features: Features) -> Capabilities {
+    let mut capabilities = Capabilities::MULTISAMPLED_SHADING | Capabilities::CUBE_ARRAY_TEXTURES;
+    let feature_capabilities = [
+        (Features::PUSH_CONSTANTS, Capabilities::PUSH_CONSTANT),
+        (Features::SHADER_F64, Capabilities::FLOAT64),
+        (
+            Features::SHADER_PRIMITIVE_INDEX,
+            Capabilities::PRIMITIVE_INDEX,
+        ),
+        (
+            Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+            Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+                | Capabilities::SAMPLER_NON_UNIFORM_INDEXING,
+        ),
+        (
+            Features::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+            Capabilities::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+        ),
+        (
+            Features::TEXTURE_FORMAT_16BIT_NORM,
+            Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS,
+        ),
+        (Features::MULTIVIEW, Capabilities::MULTIVIEW),
+        (
+            Features::SHADER_EARLY_DEPTH_TEST,
+            Capabilities::EARLY_DEPTH_TEST,
+        ),
+        (Features::SHADER_INT64, Capabilities::SHADER_INT64),
+        (
+            Features::SHADER_INT64_ATOMIC_MIN_MAX,
+            Capabilities::SHADER_INT64_ATOMIC_MIN_MAX,
+        ),
+        (
+            Features::SHADER_INT64_ATOMIC_ALL_OPS,
+            Capabilities::SHADER_INT64_ATOMIC_MIN_MAX | Capabilities::SHADER_INT64_ATOMIC_ALL_OPS,
+        ),
+        (
+            Features::DUAL_SOURCE_BLENDING,
+            Capabilities::DUAL_SOURCE_BLENDING,
+        ),
+        (Features::SUBGROUP, Capabilities::SUBGROUP),
+        (Features::SUBGROUP_BARRIER, Capabilities::SUBGROUP_BARRIER),
+        (
+            Features::SUBGROUP_VERTEX,
+            Capabilities::SUBGROUP | Capabilities::SUBGROUP_VERTEX_STAGE,
+        ),
+    ];
+    for (feature, feature_capabilities) in feature_capabilities {
+        if features.contains(feature) {
+            capabilities |= feature_capabilities;
+        }
+    }
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    /// Write the given files to a fresh directory named after the test, returning its path.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "raymarks-preprocess-{}-{}",
+            test,
+            std::process::id()
+        ));
+        create_dir_all(&directory).unwrap();
+        for (name, source) in files {
+            write(directory.join(name), source).unwrap();
+        }
+        directory
+    }
+
+    /// Preprocess the named file in the given directory, resolving includes from it.
+    fn preprocess_file(directory: &Path, name: &str) -> Result<String, ShaderError> {
+        let mut output = String::new();
+        preprocess(
+            &directory.join(name),
+            directory,
+            &mut Vec::new(),
+            &mut HashSet::new(),
+            &mut output,
+        )?;
+        Ok(output)
+    }
+
+    #[test]
+    fn preprocess_replaces_includes() {
+        let directory = write_files(
+            "replaces",
+            &[
+                ("main.wgsl", "#include \"a.wgsl\"\nmain\n"),
+                ("a.wgsl", "  #include   \"b.wgsl\"  \na\n"),
+                ("b.wgsl", "b\n"),
+            ],
+        );
+        assert_eq!(
+            preprocess_file(&directory, "main.wgsl").unwrap(),
+            "b\na\nmain\n"
+        );
+    }
+
+    #[test]
+    fn preprocess_includes_files_once() {
+        // Both sides of the diamond include the common file, which is only defined once
+        let directory = write_files(
+            "diamond",
+            &[
+                (
+                    "main.wgsl",
+                    "#include \"left.wgsl\"\n#include \"right.wgsl\"\nmain\n",
+                ),
+                ("left.wgsl", "#include \"common.wgsl\"\nleft\n"),
+                ("right.wgsl", "#include \"common.wgsl\"\nright\n"),
+                ("common.wgsl", "common\n"),
+            ],
+        );
+        assert_eq!(
+            preprocess_file(&directory, "main.wgsl").unwrap(),
+            "common\nleft\nright\nmain\n"
+        );
+    }
+
+    #[test]
+    fn preprocess_detects_cycles() {
+        let directory = write_files(
+            "cycle",
+            &[
+                ("a.wgsl", "#include \"b.wgsl\"\n"),
+                ("b.wgsl", "#include \"a.wgsl\"\n"),
+            ],
+        );
+        match preprocess_file(&directory, "a.wgsl") {
+            Err(ShaderError::IncludeCycle(path)) => assert_eq!(path, directory.join("a.wgsl")),
+            result => panic!("expected an include cycle, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn preprocess_rejects_invalid_includes() {
+        let directory = write_files("invalid", &[("main.wgsl", "main\n#include common.wgsl\n")]);
+        match preprocess_file(&directory, "main.wgsl") {
+            Err(ShaderError::InvalidInclude(path, 2)) => {
+                assert_eq!(path, directory.join("main.wgsl"))
+            }
+            result => panic!("expected an invalid include, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn preprocess_reports_missing_includes() {
+        let directory = write_files("missing", &[("main.wgsl", "#include \"missing.wgsl\"\n")]);
+        match preprocess_file(&directory, "main.wgsl") {
+            Err(ShaderError::NotFound(path)) => assert_eq!(path, directory.join("missing.wgsl")),
+            result => panic!("expected a missing include, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn built_in_shaders_need_no_optional_features() {
+        for shader in Shader::BUILT_IN {
+            let source = shader.load_source_preprocessed().unwrap();
+            if let Err(error) = validate_wgsl(&source, Features::empty()) {
+                panic!("{:?} is invalid: {}", shader, error);
+            }
+        }
+    }
+
+    #[test]
+    fn workgroup_size_mismatches_are_errors() {
+        let source = "@compute @workgroup_size(16, 16) fn main() {}";
+        check_workgroup_size(source, "main", 16).unwrap();
+        match check_workgroup_size(source, "main", 8) {
+            Err(ShaderError::WorkgroupSize(entry_point, size)) => {
+                assert_eq!(entry_point, "main");
+                assert_eq!(size, [16, 16, 1]);
+            }
+            result => panic!("expected a workgroup size error, got {:?}", result),
+        }
+        assert!(matches!(
+            check_workgroup_size(source, "missing", 16),
+            Err(ShaderError::MissingEntryPoint(_))
+        ));
+    }
+
+    #[test]
+    fn constants_are_substituted() {
+        let source =
+            "const MAX_COUNT_2: u32 = 1u;\n  const MAX_COUNT : u32 = 2048u; // fits\nmain\n";
+        assert_eq!(
+            substitute_constant(source, "MAX_COUNT", "16u").unwrap(),
+            "const MAX_COUNT_2: u32 = 1u;\n  const MAX_COUNT : u32 = 16u; // fits\nmain\n"
+        );
+        assert!(matches!(
+            substitute_constant(source, "MAX", "16u"),
+            Err(ShaderError::MissingConstant(name)) if name == "MAX"
+        ));
+    }
+
+    #[test]
+    fn rasterization_shaders_declare_uniform_instance_count() {
+        for shader in [Shader::Rasterization, Shader::GBuffer] {
+            let source = shader.load_source_preprocessed().unwrap();
+            substitute_constant(&source, "MAX_UNIFORM_INSTANCES", "16u").unwrap();
+        }
+    }
+
+    #[test]
+    fn capabilities_follow_features() {
+        assert!(!capabilities(Features::empty()).contains(Capabilities::FLOAT64));
+        assert!(capabilities(Features::SHADER_F64).contains(Capabilities::FLOAT64));
+        assert!(capabilities(Features::SUBGROUP_VERTEX).contains(Capabilities::SUBGROUP));
+        let source = "fn double(x: f64) -> f64 { return x * 2.0lf; }";
+        assert!(matches!(
+            validate_wgsl(source, Features::empty()),
+            Err(ShaderError::Validation(_))
+        ));
+        assert!(validate_wgsl(source, Features::SHADER_F64).is_ok());
     }
 }
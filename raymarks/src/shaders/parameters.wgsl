@@ -0,0 +1,10 @@
+// Small parameters which change between passes. When push constants are supported, the uniform
+// declaration below is replaced with a push constant declaration before compiling
+
+struct PassParameters {
+    camera_position: vec3<f32>,
+    time: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> parameters: PassParameters;
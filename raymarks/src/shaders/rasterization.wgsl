@@ -1,14 +1,85 @@
+#include "common.wgsl"
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Alpha of every fragment, which is only visible when the render target blends
+override OPACITY: f32 = 1.0;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+}
+
+struct InstanceInput {
+    @location(3) translation: vec3<f32>,
+    @location(4) scale: f32,
+    @location(5) material: u32,
+}
+
+// Instance as laid out in uniform and storage buffers, padded to 32 bytes like `InstanceInput`
+// in instance vertex buffers
+struct Instance {
+    translation: vec3<f32>,
+    scale: f32,
+    material: u32,
+}
+
+// Number of instances in the uniform array, which is substituted to fit the device's uniform
+// binding size before compiling
+const MAX_UNIFORM_INSTANCES: u32 = 2048u;
+
+// Instances read by `uniform_vertex_shader`
+@group(0) @binding(1)
+var<uniform> uniform_instances: array<Instance, MAX_UNIFORM_INSTANCES>;
+
+// Instances read by `storage_vertex_shader`
+@group(0) @binding(2)
+var<storage, read> storage_instances: array<Instance>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) @interpolate(flat) material: u32,
+    @location(3) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex_shader(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    return transform(in, instance);
+}
+
+@vertex
+fn uniform_vertex_shader(in: VertexInput, @builtin(instance_index) index: u32) -> VertexOutput {
+    let instance = uniform_instances[index];
+    return transform(in, InstanceInput(instance.translation, instance.scale, instance.material));
+}
+
 @vertex
-fn vertex_shader(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
-    var vertices = array<vec4<f32>, 3>(
-        vec4<f32>(0.0, 1.0, 0.0, 1.0),
-        vec4<f32>(-1.0, -1.0, 0.0, 1.0),
-        vec4<f32>(1.0, -1.0, 0.0, 1.0)
-    );
-    return vertices[in_vertex_index];
+fn storage_vertex_shader(in: VertexInput, @builtin(instance_index) index: u32) -> VertexOutput {
+    let instance = storage_instances[index];
+    return transform(in, InstanceInput(instance.translation, instance.scale, instance.material));
+}
+
+// Transform a vertex by an instance, passing its attributes on to the fragment shader
+fn transform(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+    let world_position = in.position * instance.scale + instance.translation;
+    out.position = camera.view_projection * vec4<f32>(world_position, 1.0);
+    out.normal = in.normal;
+    out.world_position = world_position;
+    out.material = instance.material;
+    out.uv = in.uv;
+    return out;
 }
 
 @fragment
-fn fragment_shader() -> @location(0) vec4<f32> {
-    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+fn fragment_shader(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade_color(in.normal, material_color(in.material)), OPACITY);
 }
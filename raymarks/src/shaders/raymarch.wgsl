@@ -0,0 +1,99 @@
+#include "common.wgsl"
+#include "parameters.wgsl"
+
+// Storage texture of the render target's format, whose declaration is replaced before compiling
+alias OutputTexture = texture_storage_2d<rgba8unorm, write>;
+
+@group(0) @binding(0) var output: OutputTexture;
+// Number of steps each pixel's ray took, only written by `raymarch_steps_shader`
+@group(0) @binding(1) var steps_output: texture_storage_2d<r32uint, write>;
+
+// Whether the output is 8-bit sRGB, rather than linear HDR
+override ENCODE_SRGB: bool = true;
+
+// Width and height of each workgroup, which must match `RAYMARCH_WORKGROUP_SIZE` on the CPU side
+const WORKGROUP_SIZE: u32 = 8u;
+
+// Maximum number of steps per ray, matching `RAYMARCH_MAX_STEPS` on the CPU side
+const MAX_STEPS: u32 = 128u;
+const MAX_DISTANCE: f32 = 100.0;
+const SURFACE_DISTANCE: f32 = 0.001;
+
+// Signed distance to the closest sphere in the scene. The smaller spheres bob up and down over
+// time, starting from their rest positions at time zero
+fn scene_distance(position: vec3<f32>) -> f32 {
+    let bob = 0.5 * sin(parameters.time);
+    var closest = sphere_distance(position, vec3<f32>(0.0, 0.0, 0.0), 1.0);
+    closest = min(closest, sphere_distance(position, vec3<f32>(-1.5, -0.5 + bob, 1.0), 0.5));
+    closest = min(closest, sphere_distance(position, vec3<f32>(1.5, 0.5 - bob, -1.0), 0.75));
+    return closest;
+}
+
+// Approximate the surface normal from the gradient of the distance field
+fn scene_normal(position: vec3<f32>) -> vec3<f32> {
+    let offset = vec2<f32>(SURFACE_DISTANCE, 0.0);
+    return normalize(vec3<f32>(
+        scene_distance(position + offset.xyy) - scene_distance(position - offset.xyy),
+        scene_distance(position + offset.yxy) - scene_distance(position - offset.yxy),
+        scene_distance(position + offset.yyx) - scene_distance(position - offset.yyx)
+    ));
+}
+
+// Dispatches round up to whole workgroups, so invocations past the edges of the output return early
+@compute @workgroup_size(WORKGROUP_SIZE, WORKGROUP_SIZE)
+fn raymarch_shader(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+    raymarch(id.xy, size);
+}
+
+// Like `raymarch_shader`, also writing the number of steps each pixel's ray took, which shows
+// where the marcher is expensive
+@compute @workgroup_size(WORKGROUP_SIZE, WORKGROUP_SIZE)
+fn raymarch_steps_shader(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+    textureStore(steps_output, id.xy, vec4<u32>(raymarch(id.xy, size), 0u, 0u, 0u));
+}
+
+// Shade the given pixel of an output of the given size, returning the number of steps its ray took
+fn raymarch(pixel: vec2<u32>, size: vec2<u32>) -> u32 {
+    // Cast a ray from the camera through the center of this pixel
+    let aspect = f32(size.x) / f32(size.y);
+    let uv = (vec2<f32>(pixel) + 0.5) / vec2<f32>(size) * 2.0 - 1.0;
+    let origin = parameters.camera_position;
+    let direction = normalize(vec3<f32>(uv.x * aspect, -uv.y, -2.0));
+
+    // March along the ray until it hits a surface or escapes the scene
+    var traveled = 0.0;
+    var hit = false;
+    var steps = MAX_STEPS;
+    for (var i = 0u; i < MAX_STEPS; i++) {
+        let closest = scene_distance(origin + direction * traveled);
+        if closest < SURFACE_DISTANCE {
+            hit = true;
+            steps = i + 1u;
+            break;
+        }
+        traveled += closest;
+        if traveled > MAX_DISTANCE {
+            steps = i + 1u;
+            break;
+        }
+    }
+
+    // Shade hits with the same lighting as the rasterization shader
+    var color = vec3<f32>(0.0);
+    if hit {
+        color = shade(scene_normal(origin + direction * traveled));
+    }
+    if ENCODE_SRGB {
+        color = linear_to_srgb(color);
+    }
+    textureStore(output, pixel, vec4<f32>(color, 1.0));
+    return steps;
+}
@@ -0,0 +1,34 @@
+#include "common.wgsl"
+
+// Number of triangles in the fan making up the disc drawn as a stencil mask
+override MASK_SEGMENTS: u32 = 64u;
+
+// Vertices of a disc of radius 0.5 in clip space, as a fan of triangles around its center
+@vertex
+fn mask_vertex_shader(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let segment = in_vertex_index / 3u;
+    let corner = in_vertex_index % 3u;
+    if corner == 0u {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    let angle = 6.28318531 * f32(segment + corner - 1u) / f32(MASK_SEGMENTS);
+    return vec4<f32>(0.5 * cos(angle), 0.5 * sin(angle), 0.0, 1.0);
+}
+
+// Color writes are disabled for the mask, so only the stencil is written
+@fragment
+fn mask_fragment_shader() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0);
+}
+
+// A single triangle covering the whole render target
+@vertex
+fn fill_vertex_shader(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+@fragment
+fn fill_fragment_shader() -> @location(0) vec4<f32> {
+    return vec4<f32>(material_color(1u), 1.0);
+}
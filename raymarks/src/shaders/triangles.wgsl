@@ -0,0 +1,39 @@
+#include "common.wgsl"
+
+// Number of triangles drawn, which are tiled in pairs over a square grid of cells
+override TRIANGLE_COUNT: u32 = 1u;
+
+// Corners of the two triangles making up a cell, in cell space with y pointing down
+const CORNERS: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+);
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) material: u32,
+}
+
+// Vertices are generated from their index, so no vertex buffer is needed
+@vertex
+fn vertex_shader(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let triangle = in_vertex_index / 3u;
+    let cell = triangle / 2u;
+    let columns = u32(ceil(sqrt(f32((TRIANGLE_COUNT + 1u) / 2u))));
+    let corner = CORNERS[in_vertex_index % 6u];
+    let uv = (vec2<f32>(f32(cell % columns), f32(cell / columns)) + corner) / f32(columns);
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.material = triangle % 4u;
+    return out;
+}
+
+@fragment
+fn fragment_shader(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(material_color(in.material), 1.0);
+}
@@ -0,0 +1,10 @@
+// Fetches every attribute of a half-precision vertex, to check the device can before meshes are
+// uploaded with them
+@vertex
+fn vertex_shader(
+    @location(0) position: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+    @location(2) uv: vec2<f32>,
+) -> @builtin(position) vec4<f32> {
+    return position + normal * uv.x;
+}
@@ -0,0 +1,138 @@
+use image::{ImageError, RgbaImage};
+use std::{error::Error, fmt};
+use wgpu::*;
+
+/// Handle to a texture loaded by `BenchmarkContext::load_texture`, for looking up its view and
+/// sampler when building bind groups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(pub(crate) usize);
+
+/// Error produced when an image cannot be loaded into a texture.
+#[derive(Debug)]
+pub enum TextureError {
+    /// The image could not be read or decoded.
+    Decode(ImageError),
+    /// The image is larger than the device's `max_texture_dimension_2d` limit.
+    TooLarge {
+        width: u32,
+        height: u32,
+        max_dimension: u32,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Decode(error) => write!(f, "failed to load image: {}", error),
+            Self::TooLarge {
+                width,
+                height,
+                max_dimension,
+            } => write!(
+                f,
+                "image of {}x{} exceeds the device's max_texture_dimension_2d of {}",
+                width, height, max_dimension
+            ),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Decode(error) => Some(error),
+            Self::TooLarge { .. } => None,
+        }
+    }
+}
+
+impl From<ImageError> for TextureError {
+    fn from(error: ImageError) -> Self {
+        Self::Decode(error)
+    }
+}
+
+/// An 8-bit sRGB image which has been uploaded to a GPU texture, along with a sampler for
+/// reading it in shaders.
+pub struct InputTexture {
+    texture: Texture,
+    view: TextureView,
+    sampler: Sampler,
+}
+
+impl InputTexture {
+    /// Format of uploaded textures, so sampling them yields linear colors.
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+    /// Upload the given image to a new texture, sampled with the given filtering.
+    pub fn new(device: &Device, queue: &Queue, image: &RgbaImage, filter: FilterMode) -> Self {
+        let (width, height) = image.dimensions();
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(&format!("raymarks.input_texture.{}x{}", width, height)),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            image.as_raw(),
+            ImageDataLayout {
+                offset: 0,
+                // Unlike texture-to-buffer copies, writes need no padding between rows
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("raymarks.input_sampler"),
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Get the view of the whole texture, for binding as a sampled texture.
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// Get the sampler the texture is read with.
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// Get the size of the texture in bytes.
+    pub fn size(&self) -> BufferAddress {
+        let size = self.texture.size();
+        (size.width * size.height * 4) as BufferAddress
+    }
+
+    /// Free the GPU texture.
+    pub fn destroy(&self) {
+        self.texture.destroy();
+    }
+}
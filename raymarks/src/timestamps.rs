@@ -0,0 +1,128 @@
+use crate::context::{BenchmarkContext, SaveError};
+use std::{cell::Cell, time::Duration};
+use wgpu::*;
+
+/// GPU timestamp queries written at the start and end of a pass, along with the buffers needed to
+/// resolve and read them back on the CPU.
+pub struct TimestampQueries {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    mapped: Cell<bool>,
+}
+
+impl TimestampQueries {
+    /// Number of timestamps written per pass: one at the beginning, one at the end.
+    const COUNT: u32 = 2;
+
+    /// Size in bytes of the buffers holding resolved timestamps.
+    const BUFFER_SIZE: BufferAddress = Self::COUNT as BufferAddress * QUERY_SIZE as BufferAddress;
+
+    /// Create the query set and buffers, or `None` if the device was not created with the
+    /// `TIMESTAMP_QUERY` feature.
+    pub fn new(device: &Device) -> Option<Self> {
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("raymarks.timestamps"),
+            ty: QueryType::Timestamp,
+            count: Self::COUNT,
+        });
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("raymarks.timestamps_resolve"),
+            size: Self::BUFFER_SIZE,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("raymarks.timestamps_readback"),
+            size: Self::BUFFER_SIZE,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            mapped: Cell::new(false),
+        })
+    }
+
+    /// Timestamp writes to attach to a render pass, covering the whole pass.
+    pub fn render_pass_writes(&self) -> RenderPassTimestampWrites<'_> {
+        RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    /// Timestamp writes to attach to a compute pass, covering the whole pass.
+    pub fn compute_pass_writes(&self) -> ComputePassTimestampWrites<'_> {
+        ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    /// Record commands resolving the written timestamps and copying them to the readback buffer.
+    pub fn resolve(&self, commands: &mut CommandEncoder) {
+        commands.resolve_query_set(&self.query_set, 0..Self::COUNT, &self.resolve_buffer, 0);
+        commands.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            Self::BUFFER_SIZE,
+        );
+    }
+
+    /// Read back the resolved timestamps and convert the difference between them to a duration,
+    /// blocking the current thread until the submission which resolved them has finished, for at
+    /// most `BenchmarkContext::POLL_TIMEOUT`. Returns an error if the readback buffer could not be
+    /// mapped, such as when the device is lost, or the GPU did not finish in time, in which case
+    /// the mapping is cancelled so the buffer can be mapped again.
+    pub fn duration(&self, device: &Device, queue: &Queue) -> Result<Duration, SaveError> {
+        let buffer_slice = self.readback_buffer.slice(..);
+        let (sender, receiver) = flume::bounded(1);
+        self.mapped.set(true);
+        buffer_slice.map_async(MapMode::Read, move |r| {
+            // The reader may have stopped waiting for the mapping
+            let _ = sender.send(r);
+        });
+        if let Err(error) = BenchmarkContext::wait_until_mapped(device, &receiver) {
+            // Only a mapping which timed out is still pending
+            if let SaveError::GpuTimeout(_) = error {
+                self.readback_buffer.unmap();
+            }
+            self.mapped.set(false);
+            return Err(error);
+        }
+
+        let ticks = {
+            let view = buffer_slice.get_mapped_range();
+            let start = u64::from_le_bytes(view[0..8].try_into().unwrap());
+            let end = u64::from_le_bytes(view[8..16].try_into().unwrap());
+            end.saturating_sub(start)
+        };
+        self.readback_buffer.unmap();
+        self.mapped.set(false);
+
+        // The timestamp period is the number of nanoseconds per tick
+        let nanoseconds = ticks as f64 * queue.get_timestamp_period() as f64;
+        Ok(Duration::from_nanos(nanoseconds as u64))
+    }
+
+    /// Destroy the query buffers, first unmapping the readback buffer if a read was interrupted.
+    pub fn destroy(&self) {
+        if self.mapped.replace(false) {
+            self.readback_buffer.unmap();
+        }
+        self.resolve_buffer.destroy();
+        self.readback_buffer.destroy();
+    }
+}
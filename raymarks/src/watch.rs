@@ -0,0 +1,73 @@
+use crate::{context::BenchmarkContext, shaders::Shader};
+use log::{error, info};
+use notify::{recommended_watcher, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Watches the shader directory for changes to shader source files.
+pub struct ShaderWatcher {
+    // Kept alive so events keep being sent
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl ShaderWatcher {
+    /// Start watching the shader directory.
+    pub fn new() -> notify::Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher = recommended_watcher(sender)?;
+        watcher.watch(&Shader::shader_directory(), RecursiveMode::Recursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Check whether any shader has changed since the last check, without blocking.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            changed |= Self::is_change(event);
+        }
+        changed
+    }
+
+    /// Block the current thread until a shader changes. Returns `false` if the watcher stopped.
+    pub fn wait(&self) -> bool {
+        while let Ok(event) = self.events.recv() {
+            if Self::is_change(event) {
+                // Editors often write several events for a single save
+                self.changed();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Private method to check whether a watcher event is a change to a shader file.
+    fn is_change(event: notify::Result<Event>) -> bool {
+        match event {
+            Ok(event) => event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove(),
+            Err(watch_error) => {
+                error!("Error watching shaders: {}", watch_error);
+                false
+            }
+        }
+    }
+}
+
+/// Run a benchmark, then re-run it with reloaded shaders every time a shader changes, until the
+/// watcher stops.
+pub fn run_on_change(
+    context: &mut BenchmarkContext,
+    mut benchmark: impl FnMut(&mut BenchmarkContext),
+) -> notify::Result<()> {
+    let watcher = ShaderWatcher::new()?;
+    benchmark(context);
+    info!("Watching shaders for changes.");
+    while watcher.wait() {
+        info!("Shaders changed, re-running benchmark.");
+        context.reload_shaders();
+        benchmark(context);
+    }
+    Ok(())
+}
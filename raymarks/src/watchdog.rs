@@ -0,0 +1,136 @@
+use crate::report::{BenchmarkFailure, BenchmarkReport};
+use flume::{RecvTimeoutError, Sender};
+use log::{error, info};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Messages sent to the watchdog thread when benchmarks start and finish.
+enum Message {
+    /// A benchmark with the given name started.
+    Start(String),
+    /// The running benchmark finished, and the watchdog replies whether it timed out.
+    Finish(Sender<bool>),
+}
+
+/// Thread watching a suite of benchmarks, which marks the running benchmark failed once it runs
+/// for longer than a timeout. The benchmark is recorded as failed in the shared report, the
+/// results completed so far are written to disk, and the abort flag is set so measurements stop
+/// at their next submission. A benchmark hung within the driver may never return, so the
+/// results are written from the watchdog thread rather than waiting for it.
+pub struct Watchdog {
+    sender: Option<Sender<Message>>,
+    thread: Option<JoinHandle<()>>,
+    abort: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    /// Spawn a watchdog thread allowing each benchmark the given timeout. Failures are recorded
+    /// in the given report, which is written as JSON to `partial_results` on every timeout if a
+    /// path is given. The given abort flag is set on timeouts, such as the flag of
+    /// `BenchmarkContext::abort_handle`.
+    pub fn spawn(
+        timeout: Duration,
+        abort: Arc<AtomicBool>,
+        report: Arc<Mutex<BenchmarkReport>>,
+        partial_results: Option<PathBuf>,
+    ) -> Self {
+        let (sender, receiver) = flume::unbounded();
+        let thread_abort = abort.clone();
+        let thread = thread::Builder::new()
+            .name("raymarks-watchdog".to_string())
+            .spawn(move || {
+                let mut running: Option<(String, Instant)> = None;
+                let mut timed_out = false;
+                loop {
+                    let message = match &running {
+                        Some((_, deadline)) => receiver.recv_deadline(*deadline),
+                        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    };
+                    match message {
+                        Ok(Message::Start(benchmark)) => {
+                            running = Some((benchmark, Instant::now() + timeout));
+                            timed_out = false;
+                        }
+                        Ok(Message::Finish(reply)) => {
+                            running = None;
+                            // The runner may have stopped waiting for the reply
+                            let _ = reply.send(timed_out);
+                        }
+                        Err(RecvTimeoutError::Timeout) => {
+                            let (benchmark, _) = running.take().unwrap();
+                            timed_out = true;
+                            thread_abort.store(true, Ordering::Relaxed);
+                            error!(
+                                "Benchmark {} ran for longer than {:.1} s, so it is marked failed.",
+                                benchmark,
+                                timeout.as_secs_f32()
+                            );
+                            let mut report = report.lock().unwrap();
+                            report.failures.push(BenchmarkFailure {
+                                benchmark,
+                                reason: format!("timed out after {:.1} s", timeout.as_secs_f32()),
+                            });
+                            if let Some(path) = &partial_results {
+                                match report.write_json(path) {
+                                    Ok(()) => {
+                                        info!("Partial results written to {}.", path.display())
+                                    }
+                                    Err(write_error) => {
+                                        error!("Failed to write partial results: {}", write_error)
+                                    }
+                                }
+                            }
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            })
+            .expect("failed to spawn the watchdog thread");
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+            abort,
+        }
+    }
+
+    /// Start the timeout of the benchmark with the given name.
+    pub fn start(&self, benchmark: &str) {
+        self.send(Message::Start(benchmark.to_string()));
+    }
+
+    /// Stop the timeout of the running benchmark, and clear the abort flag for the next one.
+    /// Returns whether the benchmark timed out.
+    pub fn finish(&self) -> bool {
+        let (reply, receiver) = flume::bounded(1);
+        self.send(Message::Finish(reply));
+        // Waiting for the reply ensures a timeout cannot set the abort flag after it is cleared
+        let timed_out = receiver.recv().unwrap_or(false);
+        self.abort.store(false, Ordering::Relaxed);
+        timed_out
+    }
+
+    /// Private method to send a message to the watchdog thread.
+    fn send(&self, message: Message) {
+        if let Some(sender) = &self.sender {
+            // The thread only exits once the sender is dropped
+            sender.send(message).unwrap();
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    /// Stop the watchdog thread, waiting for it to finish writing any partial results.
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
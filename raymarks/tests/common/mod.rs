@@ -0,0 +1,26 @@
+// Each integration test uses a subset of the helpers
+#![allow(dead_code)]
+
+use raymarks::context::{BenchmarkContext, BenchmarkContextBuilder, ContextError};
+use wgpu::TextureFormat;
+
+/// Create a benchmark context on any available adapter, or `None` if the machine has none, such
+/// as in CI without a GPU or software renderer, so tests needing one are skipped. The render
+/// target is linear, so downlevel adapters without view formats, such as software OpenGL, can
+/// run the tests too.
+pub fn context() -> Option<BenchmarkContext> {
+    context_with(BenchmarkContextBuilder::default())
+}
+
+/// Create a benchmark context from the given builder like `context`, with a linear render target.
+pub fn context_with(builder: BenchmarkContextBuilder) -> Option<BenchmarkContext> {
+    let builder = builder.target_format(TextureFormat::Rgba16Float);
+    match BenchmarkContext::new_with_sync(builder) {
+        Ok(context) => Some(context),
+        Err(ContextError::NoAdapter { .. }) => {
+            eprintln!("No adapter is available, so the test is skipped.");
+            None
+        }
+        Err(error) => panic!("failed to create a benchmark context: {}", error),
+    }
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use raymarks::{
+    benchmark::BenchmarkOptions, benchmarks::cube_scene, context::BenchmarkContextBuilder,
+    geometry::Cube,
+};
+use std::fs::{read_dir, remove_dir_all};
+
+/// Rasterize a scene of exported cubes, checking a result and an image are produced at every
+/// resolution.
+#[test]
+fn cube_scene_rasterizes_exported_cubes() {
+    let images = std::env::temp_dir().join(format!("raymarks-cube-scene-{}", std::process::id()));
+    let builder = BenchmarkContextBuilder::default().image_directory(&images);
+    let Some(mut context) = common::context_with(builder) else {
+        return;
+    };
+    let cubes = [
+        Cube {
+            position: [0.0, 0.0, 0.0],
+            size: [1.0, 1.0, 1.0],
+            color: [1.0, 0.0, 0.0],
+        },
+        Cube {
+            position: [1.5, 0.5, -1.0],
+            size: [0.5, 2.0, 0.5],
+            color: [0.0, 1.0, 0.0],
+        },
+    ];
+    let options = BenchmarkOptions {
+        warmup: 1,
+        samples: 2,
+        ..Default::default()
+    };
+    let results = pollster::block_on(cube_scene(
+        &mut context,
+        &options,
+        &cubes,
+        [(64, 64), (96, 48)],
+    ));
+    context.shutdown();
+
+    let saved = read_dir(&images).map(Iterator::count).unwrap_or(0);
+    let _ = remove_dir_all(&images);
+    assert_eq!(results.len(), 2);
+    for result in &results {
+        assert_eq!(result.benchmark, "cube_scene");
+        assert_eq!(result.count, Some(2));
+    }
+    assert_eq!(saved, 2);
+}
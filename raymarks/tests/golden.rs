@@ -0,0 +1,120 @@
+mod common;
+
+use raymarks::{
+    benchmark::BenchmarkOptions,
+    benchmarks::bunny_rasterization,
+    context::{BenchmarkContext, BenchmarkContextBuilder},
+    diff::{diff_images, load_png_rgba8, DiffOptions, Tolerance},
+    geometry::{asset_directory, load_obj},
+};
+use std::{
+    env::var_os,
+    fs::{remove_dir_all, File},
+    io::BufWriter,
+    path::Path,
+};
+
+/// Environment variable which, when set, rewrites the golden images from the current renders
+/// instead of comparing against them.
+const BLESS_VARIABLE: &str = "RAYMARKS_BLESS";
+
+/// Tolerances for golden images. sRGB encoding rounds colors differently between drivers, while
+/// alpha is exactly opaque.
+const OPTIONS: DiffOptions = DiffOptions {
+    max_difference: Tolerance {
+        r: 2,
+        g: 2,
+        b: 2,
+        a: 0,
+    },
+    mean_difference: 0.5,
+    write_diff_image: false,
+};
+
+/// Compare 8-bit RGBA data of the given size against the named golden PNG in the reference
+/// directory, or rewrite the golden if `RAYMARKS_BLESS` is set.
+fn assert_matches_golden(name: &str, size: (u32, u32), rendered: &[u8]) {
+    let path = BenchmarkContext::default_reference_directory()
+        .join(format!("{}_{}x{}.png", name, size.0, size.1));
+    if var_os(BLESS_VARIABLE).is_some() {
+        write_png(&path, size, rendered);
+        return;
+    }
+
+    let (reference, reference_size) = load_png_rgba8(&path)
+        .unwrap_or_else(|error| panic!("{}, set {} to create it", error, BLESS_VARIABLE));
+    assert_eq!(reference_size, size, "golden {} has a different size", name);
+    let (result, _) = diff_images(rendered, &reference, &OPTIONS);
+    assert!(
+        result.passed,
+        "render differs from golden {}: largest channel differences {:?}, mean difference {}",
+        path.display(),
+        result.channel_max_differences,
+        result.mean_difference
+    );
+}
+
+/// Write 8-bit RGBA data of the given size to a PNG file.
+fn write_png(path: &Path, size: (u32, u32), data: &[u8]) {
+    let file = File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size.0, size.1);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(data).unwrap();
+}
+
+/// Raymarch the spheres, which need no assets, so a golden is compared even where the bunny model
+/// is missing.
+#[test]
+fn raymarch_matches_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    let size = (256, 256);
+    context.resize_render_target(size).unwrap();
+    context.raymarch_pass().unwrap();
+    context.submit();
+
+    let mut rendered = Vec::new();
+    context.read_render_target_into_sync(&mut rendered).unwrap();
+    assert_matches_golden("raymarch", size, &rendered);
+    context.shutdown();
+}
+
+/// Run the bunny rasterization benchmark, comparing its last frame against the golden. The model
+/// isn't checked in, so the test is skipped without it; see `assets/readme.md` to fetch it.
+#[test]
+fn bunny_rasterization_matches_golden() {
+    let path = asset_directory().join("bunny.obj");
+    if !path.is_file() {
+        eprintln!("{} is missing, so the test is skipped.", path.display());
+        return;
+    }
+    let mesh = load_obj(&path).unwrap();
+    let images = std::env::temp_dir().join(format!("raymarks-golden-{}", std::process::id()));
+    let builder = BenchmarkContextBuilder::default().image_directory(&images);
+    let Some(mut context) = common::context_with(builder) else {
+        return;
+    };
+    let options = BenchmarkOptions {
+        warmup: 1,
+        samples: 2,
+        ..Default::default()
+    };
+    let size = (256, 256);
+    let results = pollster::block_on(bunny_rasterization(
+        &mut context,
+        &options,
+        &mesh,
+        [size],
+        vec![16],
+    ));
+    let _ = remove_dir_all(&images);
+    assert_eq!(results.len(), 1);
+
+    let mut rendered = Vec::new();
+    context.read_render_target_into_sync(&mut rendered).unwrap();
+    assert_matches_golden("bunny_rasterization", size, &rendered);
+    context.shutdown();
+}
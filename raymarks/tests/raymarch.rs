@@ -0,0 +1,211 @@
+mod common;
+
+use raymarks::{
+    benchmark::{find_max_resolution, measure, BenchmarkOptions, Statistics},
+    benchmarks::submission_overlap,
+    context::BenchmarkContext,
+    geometry::{scatter_instances, Mesh},
+    mesh::VertexPrecision,
+    report::BenchmarkResult,
+};
+use std::time::Duration;
+use wgpu::{Color, Features};
+
+/// Raymarch a resolution which is not a multiple of the workgroup size, checking the partial
+/// workgroups along the right and bottom edges write their pixels instead of leaving a seam.
+#[test]
+fn raymarch_covers_odd_resolutions() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    let (width, height) = (500, 500);
+    context.resize_render_target((width, height)).unwrap();
+
+    // Clear to transparent magenta first, which the raymarch pass overwrites with opaque pixels
+    context.set_clear_color(Color {
+        r: 1.0,
+        g: 0.0,
+        b: 1.0,
+        a: 0.0,
+    });
+    context.clear_pass();
+    context.raymarch_pass().unwrap();
+    context.submit();
+
+    let mut data = Vec::new();
+    context.read_render_target_into_sync(&mut data).unwrap();
+    assert_eq!(data.len(), (width * height * 4) as usize);
+    for (index, pixel) in data.chunks_exact(4).enumerate() {
+        let (x, y) = (index as u32 % width, index as u32 / width);
+        assert_eq!(pixel[3], 255, "pixel ({}, {}) was not raymarched", x, y);
+    }
+    context.shutdown();
+}
+
+/// Search for the max resolution with targets which are not a positive number of milliseconds,
+/// which finds no resolution instead of panicking when converting them to durations.
+#[test]
+fn max_resolution_rejects_invalid_targets() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    let options = BenchmarkOptions::default();
+    for target_ms in [-16.6, 0.0, f32::NAN, f32::INFINITY] {
+        let resolution = find_max_resolution(
+            &mut context,
+            &options,
+            target_ms,
+            BenchmarkContext::raymarch_pass,
+        )
+        .unwrap();
+        assert!(
+            resolution.is_none(),
+            "target {} found {:?}",
+            target_ms,
+            resolution
+        );
+    }
+    context.shutdown();
+}
+
+/// Time raymarch and rasterization passes submitted separately and together, checking both are
+/// reported at every resolution, with one sample per measured iteration.
+#[test]
+fn submission_overlap_times_both_submission_modes() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    let options = BenchmarkOptions {
+        warmup: 1,
+        samples: 3,
+        ..Default::default()
+    };
+    let results = pollster::block_on(submission_overlap(
+        &mut context,
+        &options,
+        [(64, 64), (96, 48)],
+    ));
+    context.shutdown();
+
+    let benchmarks: Vec<&str> = results
+        .iter()
+        .map(|result| result.benchmark.as_str())
+        .collect();
+    assert_eq!(
+        benchmarks,
+        [
+            "submission_overlap_serial",
+            "submission_overlap_single",
+            "submission_overlap_serial",
+            "submission_overlap_single"
+        ]
+    );
+    for result in &results {
+        let statistics = result.statistics.expect("passes were not timed");
+        assert_eq!(statistics.samples, 3);
+        assert!(statistics.min > Duration::ZERO);
+    }
+}
+
+/// Build a result from a batched raymarch measurement, checking it carries the measurement's
+/// timings and classifies its bottleneck with the batch size the passes were measured with.
+#[test]
+fn results_are_built_from_measurements() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    context.resize_render_target((64, 64)).unwrap();
+    let options = BenchmarkOptions {
+        warmup: 1,
+        samples: 4,
+        batch_size: 2,
+        ..Default::default()
+    };
+    let measurement = measure(&mut context, &options, BenchmarkContext::raymarch_pass).unwrap();
+    let result =
+        BenchmarkResult::from_measurement(&context, "raymarch", (64, 64).into(), &measurement);
+    context.shutdown();
+
+    assert_eq!(result.benchmark, "raymarch");
+    assert_eq!(result.resolution, (64, 64).into());
+    assert_eq!(result.count, None);
+    let mean = |statistics: Option<Statistics>| statistics.map(|statistics| statistics.mean);
+    assert_eq!(mean(result.statistics), mean(measurement.gpu));
+    assert_eq!(mean(result.encode_statistics), mean(measurement.encode));
+    assert_eq!(mean(result.submit_statistics), mean(measurement.submit));
+    assert_eq!(
+        result
+            .bottleneck
+            .map(|bottleneck| bottleneck.cpu_to_gpu_ratio),
+        measurement
+            .bottleneck(options.batch_size)
+            .map(|bottleneck| bottleneck.cpu_to_gpu_ratio)
+    );
+    assert_eq!(result.noisy, measurement.noisy);
+}
+
+/// Read back the timings of a raymarch pass, checking they are read within the poll timeout
+/// instead of blocking on the submission, and are missing only without timestamp queries.
+#[test]
+fn pass_timings_are_read_back() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    context.resize_render_target((64, 64)).unwrap();
+    context.raymarch_pass().unwrap();
+    context.submit();
+    let duration = context.last_pass_duration().unwrap();
+    assert_eq!(
+        duration.is_some(),
+        context.features().contains(Features::TIMESTAMP_QUERY)
+    );
+    context.last_pass_statistics();
+    context.shutdown();
+}
+
+/// Wait for a raymarch submission, checking the wait returns once the GPU has finished it.
+#[test]
+fn submissions_are_waited_on() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    context.resize_render_target((64, 64)).unwrap();
+    context.raymarch_pass().unwrap();
+    let submission = context.submit();
+    context.wait_for_submission(&submission).unwrap();
+    assert!(submission.is_finished());
+    // An empty submission after a finished one is waited on too
+    let submission = context.submit();
+    context.wait_for_submission(&submission).unwrap();
+    assert!(submission.is_finished());
+    context.shutdown();
+}
+
+/// Rasterize a mesh uploaded with half-precision vertices, checking the precision falls back to
+/// single precision only where the device cannot fetch them.
+#[test]
+fn half_precision_vertices_fall_back_when_unsupported() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    let supported = context.supports_vertex_precision(VertexPrecision::Half);
+    assert_eq!(
+        context.set_vertex_precision(VertexPrecision::Half),
+        supported
+    );
+    let expected = if supported {
+        VertexPrecision::Half
+    } else {
+        VertexPrecision::Single
+    };
+    assert_eq!(context.vertex_precision(), expected);
+
+    let mesh = Mesh::cube(1.0);
+    context.set_mesh(&mesh.vertices, &mesh.indices);
+    context.set_instances(&scatter_instances(4, 0));
+    context.push_error_scope();
+    context.rasterization_pass(4).unwrap();
+    context.submit();
+    assert!(context.pop_error_scope_sync().is_none());
+    context.shutdown();
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use raymarks::{
+    benchmark::BenchmarkOptions,
+    benchmarks::{run_all, self_test, BenchmarkParameters},
+    config::BenchmarkKind,
+};
+
+/// Run the self-test with each supersampling factor, checking the corners of the downsampled
+/// gradient are read at the output size instead of past the end of the readback.
+#[test]
+fn self_test_checks_supersampled_gradients() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    let options = BenchmarkOptions {
+        warmup: 0,
+        samples: 1,
+        ..Default::default()
+    };
+    for supersample in [1, 2, 3] {
+        assert!(context.set_supersample(supersample));
+        let results = pollster::block_on(self_test(&mut context, &options, [(64, 64), (61, 37)]));
+        assert_eq!(results.len(), 2, "supersample {}", supersample);
+        for result in &results {
+            assert_eq!(
+                result.passed,
+                Some(true),
+                "supersample {} at {}",
+                supersample,
+                result.resolution
+            );
+        }
+    }
+    context.shutdown();
+}
+
+/// Run the self-test through the benchmark runner, checking passed checks are recorded on their
+/// results without being reported as failures.
+#[test]
+fn passed_self_tests_are_not_failures() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    let options = BenchmarkOptions {
+        warmup: 0,
+        samples: 1,
+        ..Default::default()
+    };
+    let parameters = BenchmarkParameters {
+        benchmarks: vec![BenchmarkKind::SelfTest],
+        resolutions: vec![(32, 32).into()],
+        ..Default::default()
+    };
+    let report = run_all(&mut context, &options, &parameters);
+    context.shutdown();
+
+    assert!(report.failures.is_empty(), "{:?}", report.failures);
+    assert_eq!(report.results.len(), 1);
+    assert_eq!(report.results[0].passed, Some(true));
+}